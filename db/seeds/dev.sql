@@ -2,6 +2,7 @@
 -- This script inserts test users for development purposes
 
 -- Note: This should NOT be run in production
+-- Every user's password is "cwrdd-dev-password" (cwrdd-make's DEV_PASSWORD)
 
 -- Test user 1
 INSERT INTO users (
@@ -14,7 +15,7 @@ INSERT INTO users (
     account_status
 ) VALUES (
     'alice@example.com',
-    '$argon2id$v=19$m=19456,t=2,p=1$randomsalt$hashedpassword', -- Replace with actual hash
+    '$argon2id$v=19$m=19456,t=2,p=1$x0t2wG/EwSqPbmv3ymuD+w$zEfvmR5DB+rHyQCqbdaymho+6OF0EDnyetyrnSzUbAo',
     TRUE,
     'alice',
     'Alice Smith',
@@ -34,7 +35,7 @@ INSERT INTO users (
     public_bio
 ) VALUES (
     'bob@example.com',
-    '$argon2id$v=19$m=19456,t=2,p=1$randomsalt$hashedpassword',
+    '$argon2id$v=19$m=19456,t=2,p=1$tOd/qwEgi69UDZpmcqBp6A$OVx0+UXpcpLPgCKc282ao9dB9MHvCGDsSZNPlwqzBDY',
     TRUE,
     'bob',
     'Bob Johnson',
@@ -54,7 +55,7 @@ INSERT INTO users (
     account_status
 ) VALUES (
     'charlie@example.com',
-    '$argon2id$v=19$m=19456,t=2,p=1$randomsalt$hashedpassword',
+    '$argon2id$v=19$m=19456,t=2,p=1$0B5MUU21DbEM9dMwDzc1fw$4lAmPU08VcwF7Ct/LUvBU7db+pPIaSp4mTC63X2Tg1I',
     FALSE,
     'charlie',
     'Charlie Brown',
@@ -0,0 +1,376 @@
+//! Local development certificate authority.
+//!
+//! cwrdd-make creates a CA once, valid for ten years, and keeps it in
+//! `.cwrdd/ca/` (ignored by git, and outside the directory mounted into the
+//! app container). The app's certificate in `config/certs/` is a short-lived
+//! leaf issued by that CA for the names in [`CertSettings`], so reissuing it,
+//! for new hosts or when it expires, doesn't need trusting again: only the CA
+//! is trusted.
+//!
+//! Keys and certificates are generated in-process with rcgen (ECDSA P-256);
+//! no openssl binary is needed.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+    KeyUsagePurpose, SerialNumber,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::net::{IpAddr, UdpSocket};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// How long the CA is valid
+const CA_VALIDITY_DAYS: i64 = 3650;
+
+/// Common name of the CA, as shown in trust stores
+pub const CA_NAME: &str = "cwrdd development CA";
+
+/// Organisation on every certificate cwrdd-make issues
+const ORGANIZATION: &str = "cwrdd-dev";
+
+/// `[certs]` settings: what the dev certificate covers and how long it lasts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CertSettings {
+    /// DNS names and IP addresses every dev certificate covers
+    pub hosts: Vec<String>,
+    /// More names or addresses, e.g. a hostname other devices use
+    #[serde(default)]
+    pub extra_hosts: Vec<String>,
+    /// Also cover this machine's LAN IP address, so other devices can connect
+    pub lan_ip: bool,
+    /// How many days a dev certificate is valid
+    pub leaf_days: u32,
+    /// Warn this many days before the dev certificate or the CA expires
+    pub warn_days: u32,
+}
+
+impl Default for CertSettings {
+    fn default() -> Self {
+        Self {
+            hosts: ["localhost", "cwrdd.localhost", "127.0.0.1", "::1"]
+                .map(String::from)
+                .to_vec(),
+            extra_hosts: Vec::new(),
+            lan_ip: true,
+            leaf_days: 90,
+            warn_days: 14,
+        }
+    }
+}
+
+impl CertSettings {
+    /// Subject alternative names for a new dev certificate, without duplicates.
+    /// IP addresses are written the way certificates report them, e.g. `::1`.
+    pub fn subject_alt_names(&self) -> Vec<String> {
+        let lan_ip = self.lan_ip.then(lan_ip).flatten();
+        let mut names: Vec<String> = Vec::new();
+        for name in self
+            .hosts
+            .iter()
+            .chain(&self.extra_hosts)
+            .map(|name| match name.parse::<IpAddr>() {
+                Ok(ip) => ip.to_string(),
+                Err(_) => name.to_ascii_lowercase(),
+            })
+            .chain(lan_ip.map(|ip| ip.to_string()))
+        {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+}
+
+/// The address this machine uses to reach other networks, if it has one.
+///
+/// Connecting a UDP socket only picks a route; nothing is sent.
+pub fn lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:9").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_loopback() && !ip.is_unspecified()).then_some(ip)
+}
+
+/// Where the CA and the dev certificate live
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertPaths {
+    /// CA certificate, the only thing that needs trusting
+    pub ca_cert: PathBuf,
+    /// CA private key
+    pub ca_key: PathBuf,
+    /// Dev certificate served by the app
+    pub cert: PathBuf,
+    /// Dev certificate's private key
+    pub key: PathBuf,
+}
+
+impl CertPaths {
+    /// The CA in `ca_dir`, the dev certificate in `certs_dir`
+    pub fn new(ca_dir: &Path, certs_dir: &Path) -> Self {
+        Self {
+            ca_cert: ca_dir.join("ca.pem"),
+            ca_key: ca_dir.join("ca-key.pem"),
+            cert: certs_dir.join("cert.pem"),
+            key: certs_dir.join("key.pem"),
+        }
+    }
+}
+
+/// A CA certificate and its key
+pub struct Authority {
+    cert_pem: String,
+    key: KeyPair,
+    issuer: rcgen::Certificate,
+}
+
+impl Authority {
+    /// Create a new CA, valid from `now` for ten years
+    pub fn generate(now: DateTime<Utc>) -> Result<Self> {
+        let key = KeyPair::generate().context("Failed to generate CA key")?;
+        let mut params = ca_params();
+        params.serial_number = Some(serial_number());
+        params.not_before = to_offset(now - Duration::hours(1))?;
+        params.not_after = to_offset(now + Duration::days(CA_VALIDITY_DAYS))?;
+        let cert = params
+            .self_signed(&key)
+            .context("Failed to create CA certificate")?;
+        Self::from_parts(cert.pem(), key)
+    }
+
+    /// Read a CA written by [`Authority::save`]
+    pub fn load(cert_path: &Path, key_path: &Path) -> Result<Self> {
+        let cert_pem = std::fs::read_to_string(cert_path).context(format!(
+            "Failed to read CA certificate: {}",
+            cert_path.display()
+        ))?;
+        let key_pem = std::fs::read_to_string(key_path)
+            .context(format!("Failed to read CA key: {}", key_path.display()))?;
+        let key = KeyPair::from_pem(&key_pem)
+            .context(format!("Invalid CA key: {}", key_path.display()))?;
+        Self::from_parts(cert_pem, key)
+    }
+
+    /// Signing uses only the CA's name and key, so the issuer is rebuilt from
+    /// the same parameters rather than parsed back from the certificate
+    fn from_parts(cert_pem: String, key: KeyPair) -> Result<Self> {
+        let issuer = ca_params().self_signed(&key).context("Failed to load CA")?;
+        Ok(Self {
+            cert_pem,
+            key,
+            issuer,
+        })
+    }
+
+    /// Write the certificate and key, the key readable only by the current user
+    pub fn save(&self, cert_path: &Path, key_path: &Path) -> Result<()> {
+        write_file(cert_path, &self.cert_pem, false)?;
+        write_file(key_path, &self.key.serialize_pem(), true)
+    }
+
+    /// The CA certificate in PEM format
+    pub fn cert_pem(&self) -> &str {
+        &self.cert_pem
+    }
+
+    /// Issue a server certificate for `names`, valid from `now` for `days` days.
+    /// Returns the certificate and its key in PEM format.
+    pub fn issue(
+        &self,
+        names: &[String],
+        days: u32,
+        now: DateTime<Utc>,
+    ) -> Result<(String, String)> {
+        let Some(common_name) = names.first() else {
+            bail!("A dev certificate needs at least one host name");
+        };
+        let mut params = CertificateParams::new(names.to_vec())
+            .context(format!("Invalid host name in {}", names.join(", ")))?;
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        params
+            .distinguished_name
+            .push(DnType::OrganizationName, ORGANIZATION);
+        params.serial_number = Some(serial_number());
+        params.not_before = to_offset(now - Duration::hours(1))?;
+        params.not_after = to_offset(now + Duration::days(days.into()))?;
+        params.key_usages = vec![
+            KeyUsagePurpose::DigitalSignature,
+            KeyUsagePurpose::KeyEncipherment,
+        ];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = true;
+
+        let key = KeyPair::generate().context("Failed to generate certificate key")?;
+        let cert = params
+            .signed_by(&key, &self.issuer, &self.key)
+            .context("Failed to issue certificate")?;
+        Ok((cert.pem(), key.serialize_pem()))
+    }
+
+    /// Whether `cert_pem` was issued by this CA
+    pub fn issued(&self, cert_pem: &str) -> Result<bool> {
+        let ca = parse_pem(self.cert_pem.as_bytes())?;
+        let ca = ca.parse_x509().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let cert = parse_pem(cert_pem.as_bytes())?;
+        let cert = cert.parse_x509().map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(cert.issuer() == ca.subject() && cert.verify_signature(Some(ca.public_key())).is_ok())
+    }
+}
+
+/// Name, usages and key identifiers of the CA; everything but validity and serial
+fn ca_params() -> CertificateParams {
+    let mut params = CertificateParams::default();
+    params.distinguished_name = rcgen::DistinguishedName::new();
+    params.distinguished_name.push(DnType::CommonName, CA_NAME);
+    params
+        .distinguished_name
+        .push(DnType::OrganizationName, ORGANIZATION);
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    params
+}
+
+/// A random positive 128-bit serial number
+fn serial_number() -> SerialNumber {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[0] &= 0x7f;
+    SerialNumber::from_slice(&bytes)
+}
+
+fn to_offset(time: DateTime<Utc>) -> Result<time::OffsetDateTime> {
+    time::OffsetDateTime::from_unix_timestamp(time.timestamp())
+        .context("Certificate date is out of range")
+}
+
+pub(crate) fn parse_pem(pem: &[u8]) -> Result<x509_parser::pem::Pem> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(pem).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(pem)
+}
+
+/// Write `contents` to `path`, creating its directory, optionally readable only by the owner
+pub fn write_file(path: &Path, contents: &str, private: bool) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+    }
+    let mode = if private { 0o600 } else { 0o644 };
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode)
+        .open(path)
+        .context(format!("Failed to write {}", path.display()))?;
+    // The mode only applies to new files; restrict an existing key before writing to it
+    if private {
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .context(format!("Failed to restrict {}", path.display()))?;
+    }
+    file.write_all(contents.as_bytes())
+        .context(format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::client::danger::ServerCertVerifier;
+    use rustls::client::WebPkiServerVerifier;
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use std::sync::Arc;
+
+    fn der(pem: &str) -> CertificateDer<'static> {
+        CertificateDer::from(parse_pem(pem.as_bytes()).unwrap().contents)
+    }
+
+    #[test]
+    fn test_issued_certificate_chains_to_saved_ca() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = CertPaths::new(&dir.path().join("ca"), &dir.path().join("certs"));
+        let now = Utc::now();
+        Authority::generate(now)
+            .unwrap()
+            .save(&paths.ca_cert, &paths.ca_key)
+            .unwrap();
+
+        let ca = Authority::load(&paths.ca_cert, &paths.ca_key).unwrap();
+        let names = ["cwrdd.localhost", "127.0.0.1", "devbox.lan"].map(String::from);
+        let (cert, _key) = ca.issue(&names, 30, now).unwrap();
+        assert!(ca.issued(&cert).unwrap());
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(der(ca.cert_pem())).unwrap();
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let verifier = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+            .build()
+            .unwrap();
+        for name in ["cwrdd.localhost", "127.0.0.1", "devbox.lan"] {
+            let server_name = ServerName::try_from(name).unwrap();
+            verifier
+                .verify_server_cert(&der(&cert), &[], &server_name, &[], UnixTime::now())
+                .unwrap_or_else(|e| panic!("{} should verify: {}", name, e));
+        }
+        let other = ServerName::try_from("example.com").unwrap();
+        assert!(verifier
+            .verify_server_cert(&der(&cert), &[], &other, &[], UnixTime::now())
+            .is_err());
+
+        let mode = std::fs::metadata(&paths.ca_key)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_write_file_restricts_existing_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = dir.path().join("key.pem");
+        std::fs::write(&key, "old").unwrap();
+        std::fs::set_permissions(&key, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_file(&key, "new", true).unwrap();
+        let mode = std::fs::metadata(&key).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(&key).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_other_ca_did_not_issue() {
+        let now = Utc::now();
+        let ca = Authority::generate(now).unwrap();
+        let other = Authority::generate(now).unwrap();
+        let (cert, _) = other.issue(&["localhost".to_string()], 30, now).unwrap();
+        assert!(!ca.issued(&cert).unwrap());
+        assert!(other.issued(&cert).unwrap());
+        assert!(ca.issue(&[], 30, now).is_err());
+    }
+
+    #[test]
+    fn test_subject_alt_names() {
+        let settings = CertSettings {
+            extra_hosts: vec!["devbox.lan".to_string(), "localhost".to_string()],
+            lan_ip: false,
+            ..CertSettings::default()
+        };
+        assert_eq!(
+            settings.subject_alt_names(),
+            [
+                "localhost",
+                "cwrdd.localhost",
+                "127.0.0.1",
+                "::1",
+                "devbox.lan"
+            ]
+        );
+    }
+}
@@ -0,0 +1,1107 @@
+//! A native reader for the Liquibase changelog and its history table.
+//!
+//! [`Changelog::load`] parses `db/migrations/changelog.xml` and everything it
+//! pulls in with `<include>` or `<includeAll>`, in the order Liquibase would
+//! run it. Included files may be XML changelogs or formatted SQL
+//! (`--liquibase formatted sql`). Each changeset is identified the way
+//! Liquibase identifies it: by id, author and changelog path.
+//!
+//! [`status`] compares the changelog with the `databasechangelog` table to find
+//! applied, pending and checksum-mismatched changesets without starting a JVM.
+//!
+//! Liquibase's own `MD5SUM` is computed from its internal model of each change,
+//! which isn't practical to reproduce. Instead, cwrdd-make records a SHA-256 of
+//! each changeset's source in `cwrdd_changeset_checksums` after applying or
+//! rolling back migrations ([`record_checksums`]), and reports a mismatch when
+//! an applied changeset has since been edited.
+
+use crate::profile::Database;
+use anyhow::{bail, Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The root changelog, relative to the db directory
+pub const CHANGELOG_FILE: &str = "migrations/changelog.xml";
+
+/// Table where cwrdd-make records the checksum of each applied changeset
+pub const CHECKSUM_TABLE: &str = "cwrdd_changeset_checksums";
+
+/// One changeset from the changelog
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangeSet {
+    pub id: String,
+    pub author: String,
+    /// Changelog path as Liquibase records it, e.g. `migrations/20251228-initial.xml`
+    pub filename: String,
+    /// SHA-256 of the changeset source, ignoring whitespace differences
+    pub checksum: String,
+    /// The changeset's source: the `<changeSet>` element, or the formatted SQL block
+    #[serde(skip)]
+    pub source: String,
+}
+
+impl ChangeSet {
+    /// Liquibase's `filename::id::author` notation
+    pub fn key(&self) -> String {
+        key(&self.filename, &self.id, &self.author)
+    }
+}
+
+/// One row of the `databasechangelog` table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppliedChangeSet {
+    pub id: String,
+    pub author: String,
+    pub filename: String,
+    /// When Liquibase ran the changeset, as `YYYY-MM-DD HH:MM:SS`
+    pub date_executed: String,
+    /// `EXECUTED`, `MARK_RAN`, `RERAN`, etc.
+    pub exec_type: String,
+    pub tag: Option<String>,
+}
+
+impl AppliedChangeSet {
+    /// Liquibase's `filename::id::author` notation
+    pub fn key(&self) -> String {
+        key(&self.filename, &self.id, &self.author)
+    }
+}
+
+/// All changesets in a changelog, in execution order
+#[derive(Debug, Clone, Default)]
+pub struct Changelog {
+    pub changesets: Vec<ChangeSet>,
+    /// Every changelog file that was read, relative to the db directory
+    pub files: Vec<String>,
+}
+
+/// How the changelog compares with the database
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MigrationReport {
+    /// Changesets in the changelog that have been applied
+    pub applied: Vec<String>,
+    /// Changesets in the changelog that have not been applied, in order
+    pub pending: Vec<String>,
+    /// Applied changesets whose source changed after they were applied
+    pub mismatched: Vec<String>,
+    /// Applied changesets that are no longer in the changelog
+    pub unknown: Vec<String>,
+}
+
+impl MigrationReport {
+    /// Whether every changeset has been applied and none have changed since
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl Changelog {
+    /// Parse the changelog at `changelog` (relative to `db_path`) and everything it includes
+    pub fn load(db_path: &Path, changelog: &str) -> Result<Self> {
+        let mut loaded = Self::default();
+        loaded.load_file(db_path, &normalize_filename(changelog), &mut Vec::new())?;
+        Ok(loaded)
+    }
+
+    fn load_file(&mut self, db_path: &Path, filename: &str, stack: &mut Vec<String>) -> Result<()> {
+        if stack.iter().any(|f| f == filename) {
+            bail!(
+                "Changelog {} includes itself (via {})",
+                filename,
+                stack.join(" -> ")
+            );
+        }
+        let path = db_path.join(filename);
+        let source = fs::read_to_string(&path)
+            .context(format!("Failed to read changelog: {}", path.display()))?;
+
+        stack.push(filename.to_string());
+        self.files.push(filename.to_string());
+        let result = match Path::new(filename).extension().and_then(|e| e.to_str()) {
+            Some("xml") => self.parse_xml(db_path, filename, &source, stack),
+            Some("sql") => self.parse_sql(filename, &source),
+            _ => bail!(
+                "Unsupported changelog format: {} (only XML and formatted SQL are supported)",
+                filename
+            ),
+        };
+        stack.pop();
+        result.context(format!("Failed to parse changelog: {}", path.display()))
+    }
+
+    fn parse_xml(
+        &mut self,
+        db_path: &Path,
+        filename: &str,
+        source: &str,
+        stack: &mut Vec<String>,
+    ) -> Result<()> {
+        let mut reader = Reader::from_str(source);
+        let mut logical_path = filename.to_string();
+
+        loop {
+            let start = reader.buffer_position() as usize;
+            let (element, has_body) = match reader.read_event()? {
+                Event::Start(e) => (e, true),
+                Event::Empty(e) => (e, false),
+                Event::Eof => return Ok(()),
+                _ => continue,
+            };
+
+            match element.local_name().as_ref() {
+                b"databaseChangeLog" => {
+                    if let Some(path) = attribute(&element, "logicalFilePath")? {
+                        logical_path = normalize_filename(&path);
+                    }
+                }
+                b"changeSet" => {
+                    if has_body {
+                        reader.read_to_end(element.name())?;
+                    }
+                    let end = reader.buffer_position() as usize;
+                    let id = required_attribute(&element, "id")?;
+                    let author = required_attribute(&element, "author")?;
+                    let filename = match attribute(&element, "logicalFilePath")? {
+                        Some(path) => normalize_filename(&path),
+                        None => logical_path.clone(),
+                    };
+                    self.changesets.push(ChangeSet {
+                        id,
+                        author,
+                        filename,
+                        checksum: checksum(&source[start..end]),
+                        source: source[start..end].to_string(),
+                    });
+                }
+                b"include" => {
+                    let file = required_attribute(&element, "file")?;
+                    let included = resolve(filename, &file, is_relative(&element)?);
+                    self.load_file(db_path, &included, stack)?;
+                }
+                b"includeAll" => {
+                    let dir = required_attribute(&element, "path")?;
+                    let dir = resolve(filename, &dir, is_relative(&element)?);
+                    for file in changelog_files(&db_path.join(&dir))? {
+                        self.load_file(db_path, &format!("{}/{}", dir, file), stack)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_sql(&mut self, filename: &str, source: &str) -> Result<()> {
+        let mut lines = source.lines().filter(|l| !l.trim().is_empty());
+        match lines.next() {
+            Some(first)
+                if first
+                    .trim()
+                    .eq_ignore_ascii_case("--liquibase formatted sql") => {}
+            _ => bail!("SQL changelogs must start with '--liquibase formatted sql'"),
+        }
+
+        let mut current: Option<(String, String, Vec<&str>)> = None;
+        for line in source.lines() {
+            if let Some(header) = changeset_header(line) {
+                let (author, id) = header
+                    .split_once(':')
+                    .filter(|(author, id)| !author.is_empty() && !id.is_empty())
+                    .context(format!(
+                        "Invalid changeset header, expected author:id: {}",
+                        line
+                    ))?;
+                if let Some(changeset) = current.take() {
+                    self.push_sql(filename, changeset);
+                }
+                current = Some((id.to_string(), author.to_string(), vec![line]));
+            } else if let Some((_, _, body)) = current.as_mut() {
+                body.push(line);
+            }
+        }
+        if let Some(changeset) = current {
+            self.push_sql(filename, changeset);
+        }
+        Ok(())
+    }
+
+    fn push_sql(&mut self, filename: &str, (id, author, body): (String, String, Vec<&str>)) {
+        self.changesets.push(ChangeSet {
+            id,
+            author,
+            filename: filename.to_string(),
+            checksum: checksum(&body.join("\n")),
+            source: body.join("\n"),
+        });
+    }
+
+    /// Tags set by `<tagDatabase>` changes, in changelog order
+    pub fn tags(&self) -> Result<Vec<String>> {
+        let mut tags = Vec::new();
+        for changeset in &self.changesets {
+            if changeset.filename.ends_with(".sql") {
+                continue;
+            }
+            let mut reader = Reader::from_str(&changeset.source);
+            loop {
+                match reader.read_event()? {
+                    Event::Start(e) | Event::Empty(e)
+                        if e.local_name().as_ref() == b"tagDatabase" =>
+                    {
+                        tags.push(required_attribute(&e, "tag")?);
+                    }
+                    Event::Eof => break,
+                    _ => {}
+                }
+            }
+        }
+        Ok(tags)
+    }
+
+    /// Compare with the changesets recorded in the database
+    pub fn compare(
+        &self,
+        applied: &[AppliedChangeSet],
+        recorded: &BTreeMap<String, String>,
+    ) -> MigrationReport {
+        let applied_keys: Vec<String> = applied.iter().map(AppliedChangeSet::key).collect();
+        let mut report = MigrationReport::default();
+
+        for changeset in &self.changesets {
+            let key = changeset.key();
+            if !applied_keys.contains(&key) {
+                report.pending.push(key);
+                continue;
+            }
+            if recorded
+                .get(&key)
+                .is_some_and(|sum| *sum != changeset.checksum)
+            {
+                report.mismatched.push(key.clone());
+            }
+            report.applied.push(key);
+        }
+
+        let known: Vec<String> = self.changesets.iter().map(ChangeSet::key).collect();
+        report.unknown = applied_keys
+            .into_iter()
+            .filter(|key| !known.contains(key))
+            .collect();
+        report
+    }
+}
+
+/// Problems with the `<include>`s in the root changelog
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IncludeCheck {
+    /// Migration files that the changelog never includes, so would never run
+    pub not_included: Vec<String>,
+    /// Includes that point at a file or directory that doesn't exist
+    pub missing: Vec<String>,
+}
+
+impl IncludeCheck {
+    /// Whether every migration file is included and every include exists
+    pub fn is_ok(&self) -> bool {
+        self.not_included.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// An `<include>` or `<includeAll>` in the root changelog
+struct Include {
+    /// Path relative to the db directory
+    path: String,
+    /// Byte range of the element in the changelog source
+    span: (usize, usize),
+}
+
+/// The root changelog's includes, and where its closing tag starts
+fn root_includes(source: &str) -> Result<(Vec<Include>, Option<usize>)> {
+    let mut reader = Reader::from_str(source);
+    let mut includes = Vec::new();
+
+    loop {
+        let start = reader.buffer_position() as usize;
+        match reader.read_event()? {
+            Event::Empty(e) | Event::Start(e)
+                if matches!(e.local_name().as_ref(), b"include" | b"includeAll") =>
+            {
+                let name = if e.local_name().as_ref() == b"include" {
+                    "file"
+                } else {
+                    "path"
+                };
+                let path = resolve(
+                    CHANGELOG_FILE,
+                    &required_attribute(&e, name)?,
+                    is_relative(&e)?,
+                );
+                includes.push(Include {
+                    path,
+                    span: (start, reader.buffer_position() as usize),
+                });
+            }
+            Event::End(e) if e.local_name().as_ref() == b"databaseChangeLog" => {
+                return Ok((includes, Some(start)));
+            }
+            Event::Eof => return Ok((includes, None)),
+            _ => {}
+        }
+    }
+}
+
+/// Check that every file in the migrations directory is included, and every include exists
+pub fn check_includes(db_path: &Path) -> Result<IncludeCheck> {
+    let path = db_path.join(CHANGELOG_FILE);
+    let source = fs::read_to_string(&path)
+        .context(format!("Failed to read changelog: {}", path.display()))?;
+    let (includes, _) =
+        root_includes(&source).context(format!("Failed to parse changelog: {}", path.display()))?;
+
+    let missing: Vec<String> = includes
+        .iter()
+        .filter(|include| !db_path.join(&include.path).exists())
+        .map(|include| include.path.clone())
+        .collect();
+
+    // Follow nested includes too, unless a missing file would stop the walk
+    let included = if missing.is_empty() {
+        Changelog::load(db_path, CHANGELOG_FILE)?.files
+    } else {
+        includes.into_iter().map(|include| include.path).collect()
+    };
+
+    let migrations_dir = Path::new(CHANGELOG_FILE).parent().unwrap_or(Path::new(""));
+    let not_included = changelog_files(&db_path.join(migrations_dir))?
+        .into_iter()
+        .map(|file| normalize_filename(&migrations_dir.join(file).to_string_lossy()))
+        .filter(|file| file != CHANGELOG_FILE && !included.contains(file))
+        .collect();
+
+    Ok(IncludeCheck {
+        not_included,
+        missing,
+    })
+}
+
+/// Add an `<include>` for `file` (relative to the db directory) to the root changelog.
+///
+/// The include is placed among the existing ones in file name order, which for
+/// timestamped migrations is the order they were created. Returns `false` if
+/// the file was already included.
+pub fn register(db_path: &Path, file: &str) -> Result<bool> {
+    let path = db_path.join(CHANGELOG_FILE);
+    let source = fs::read_to_string(&path)
+        .context(format!("Failed to read changelog: {}", path.display()))?;
+    let file = normalize_filename(file);
+    let updated = insert_include(&source, &file)
+        .context(format!("Failed to update changelog: {}", path.display()))?;
+
+    match updated {
+        Some(updated) => {
+            fs::write(&path, updated)
+                .context(format!("Failed to write changelog: {}", path.display()))?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// `source` with an include for `file` added, or `None` if it is already included
+fn insert_include(source: &str, file: &str) -> Result<Option<String>> {
+    let (includes, closing) = root_includes(source)?;
+    if includes.iter().any(|include| include.path == file) {
+        return Ok(None);
+    }
+
+    let changelog_dir = Path::new(CHANGELOG_FILE).parent().unwrap_or(Path::new(""));
+    let relative = Path::new(file)
+        .strip_prefix(changelog_dir)
+        .context(format!("{} is outside {}", file, changelog_dir.display()))?;
+    let element = format!(
+        "<include file=\"{}\" relativeToChangelogFile=\"true\"/>",
+        relative.to_string_lossy()
+    );
+
+    let line_start = |pos: usize| source[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let indent_at = |pos: usize| {
+        let prefix = &source[line_start(pos)..pos];
+        prefix.trim().is_empty().then(|| prefix.to_string())
+    };
+
+    let mut updated = source.to_string();
+    if let Some(last) = includes.last().filter(|last| last.path.as_str() < file) {
+        let (start, end) = last.span;
+        let indent = indent_at(start).unwrap_or_else(|| "    ".to_string());
+        updated.insert_str(end, &format!("\n{}{}", indent, element));
+    } else {
+        // Before the first include that sorts after this one, or at the end
+        let before = match includes.iter().find(|include| include.path.as_str() > file) {
+            Some(next) => next.span.0,
+            None => closing.context("Missing </databaseChangeLog>")?,
+        };
+        match indent_at(before) {
+            Some(indent) if includes.is_empty() => {
+                updated.insert_str(line_start(before), &format!("{}    {}\n", indent, element))
+            }
+            Some(indent) => {
+                updated.insert_str(line_start(before), &format!("{}{}\n", indent, element))
+            }
+            None => updated.insert_str(before, &format!("\n    {}\n", element)),
+        }
+    }
+    Ok(Some(updated))
+}
+
+/// Compare the changelog in `db_path` with the database
+pub async fn status(db: &Database, db_path: &Path) -> Result<MigrationReport> {
+    let changelog = Changelog::load(db_path, CHANGELOG_FILE)?;
+    let client = db.connect().await?;
+    let applied = applied(&client).await?;
+    let recorded = recorded_checksums(&client).await?;
+    Ok(changelog.compare(&applied, &recorded))
+}
+
+/// Read `databasechangelog` in execution order; empty if Liquibase has never run
+pub async fn applied(client: &tokio_postgres::Client) -> Result<Vec<AppliedChangeSet>> {
+    if !table_exists(client, "databasechangelog").await? {
+        return Ok(Vec::new());
+    }
+
+    let rows = client
+        .query(
+            "SELECT id, author, filename, \
+                    to_char(dateexecuted, 'YYYY-MM-DD HH24:MI:SS'), exectype, tag \
+             FROM databasechangelog ORDER BY orderexecuted",
+            &[],
+        )
+        .await
+        .context("Failed to read databasechangelog")?;
+
+    Ok(rows
+        .iter()
+        .map(|row| AppliedChangeSet {
+            id: row.get(0),
+            author: row.get(1),
+            filename: normalize_filename(row.get(2)),
+            date_executed: row.get::<_, Option<String>>(3).unwrap_or_default(),
+            exec_type: row.get::<_, Option<String>>(4).unwrap_or_default(),
+            tag: row.get(5),
+        })
+        .collect())
+}
+
+/// How `cwrdd_changeset_checksums` must change to match `databasechangelog`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ChecksumChanges<'a> {
+    /// Recorded keys whose changesets are no longer applied, e.g. after a rollback
+    pub stale: Vec<String>,
+    /// Applied changesets with no recorded checksum yet
+    pub missing: Vec<&'a ChangeSet>,
+}
+
+impl Changelog {
+    /// Compare the recorded checksums with the applied changesets. Existing
+    /// checksums of applied changesets are kept, so later edits show up as mismatches.
+    pub fn checksum_changes(
+        &self,
+        applied: &[String],
+        recorded: &BTreeMap<String, String>,
+    ) -> ChecksumChanges<'_> {
+        ChecksumChanges {
+            stale: recorded
+                .keys()
+                .filter(|key| !applied.contains(key))
+                .cloned()
+                .collect(),
+            missing: self
+                .changesets
+                .iter()
+                .filter(|c| applied.contains(&c.key()) && !recorded.contains_key(&c.key()))
+                .collect(),
+        }
+    }
+}
+
+/// Bring `cwrdd_changeset_checksums` in line with `databasechangelog`: record the
+/// checksum of every applied changeset that doesn't have one yet, and forget
+/// those of changesets that have been rolled back. Run after every `update` and
+/// `rollback`. Returns how many were added.
+pub async fn record_checksums(db: &Database, db_path: &Path) -> Result<u64> {
+    let changelog = Changelog::load(db_path, CHANGELOG_FILE)?;
+    let client = db.connect().await?;
+    let applied: Vec<String> = applied(&client)
+        .await?
+        .iter()
+        .map(AppliedChangeSet::key)
+        .collect();
+
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+                 id TEXT NOT NULL, \
+                 author TEXT NOT NULL, \
+                 filename TEXT NOT NULL, \
+                 checksum TEXT NOT NULL, \
+                 recorded_at TIMESTAMPTZ NOT NULL DEFAULT now(), \
+                 PRIMARY KEY (id, author, filename))",
+            CHECKSUM_TABLE
+        ))
+        .await
+        .context(format!("Failed to create {}", CHECKSUM_TABLE))?;
+
+    let recorded = recorded_checksums(&client).await?;
+    let changes = changelog.checksum_changes(&applied, &recorded);
+    let mut added = 0;
+    for key in &changes.stale {
+        let (filename, id, author) = split_key(key);
+        client
+            .execute(
+                &format!(
+                    "DELETE FROM {} WHERE filename = $1 AND id = $2 AND author = $3",
+                    CHECKSUM_TABLE
+                ),
+                &[&filename, &id, &author],
+            )
+            .await
+            .context(format!("Failed to forget checksum of {}", key))?;
+    }
+    for changeset in changes.missing {
+        added += client
+            .execute(
+                &format!(
+                    "INSERT INTO {} (id, author, filename, checksum) VALUES ($1, $2, $3, $4) \
+                     ON CONFLICT DO NOTHING",
+                    CHECKSUM_TABLE
+                ),
+                &[
+                    &changeset.id,
+                    &changeset.author,
+                    &changeset.filename,
+                    &changeset.checksum,
+                ],
+            )
+            .await
+            .context(format!("Failed to record checksum of {}", changeset.key()))?;
+    }
+    Ok(added)
+}
+
+/// Recorded checksums by `filename::id::author`; empty if none have been recorded
+async fn recorded_checksums(client: &tokio_postgres::Client) -> Result<BTreeMap<String, String>> {
+    if !table_exists(client, CHECKSUM_TABLE).await? {
+        return Ok(BTreeMap::new());
+    }
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT filename, id, author, checksum FROM {}",
+                CHECKSUM_TABLE
+            ),
+            &[],
+        )
+        .await
+        .context(format!("Failed to read {}", CHECKSUM_TABLE))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| (key(row.get(0), row.get(1), row.get(2)), row.get(3)))
+        .collect())
+}
+
+pub(crate) async fn table_exists(client: &tokio_postgres::Client, table: &str) -> Result<bool> {
+    let row = client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&table])
+        .await
+        .context(format!("Failed to look up table {}", table))?;
+    Ok(row.get(0))
+}
+
+fn key(filename: &str, id: &str, author: &str) -> String {
+    format!("{}::{}::{}", filename, id, author)
+}
+
+fn split_key(key: &str) -> (&str, &str, &str) {
+    let mut parts = key.splitn(3, "::");
+    (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    )
+}
+
+/// SHA-256 of `source` with runs of whitespace collapsed, so reformatting isn't a change
+fn checksum(source: &str) -> String {
+    let normalized = source.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+/// The `author:id` from a `--changeset author:id ...` line
+fn changeset_header(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix("--")?.trim_start();
+    let rest = rest.strip_prefix("changeset")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    rest.split_whitespace().next()
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>> {
+    match element.try_get_attribute(name)? {
+        Some(attr) => Ok(Some(attr.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+fn required_attribute(element: &BytesStart, name: &str) -> Result<String> {
+    attribute(element, name)?.context(format!(
+        "<{}> is missing the '{}' attribute",
+        String::from_utf8_lossy(element.local_name().as_ref()),
+        name
+    ))
+}
+
+fn is_relative(element: &BytesStart) -> Result<bool> {
+    Ok(attribute(element, "relativeToChangelogFile")?.is_some_and(|v| v == "true"))
+}
+
+/// The path Liquibase records for `file` included from the changelog `from`
+fn resolve(from: &str, file: &str, relative_to_changelog: bool) -> String {
+    if relative_to_changelog {
+        let parent = Path::new(from).parent().unwrap_or(Path::new(""));
+        normalize_filename(&parent.join(file).to_string_lossy())
+    } else {
+        normalize_filename(file)
+    }
+}
+
+/// Normalise a changelog path: forward slashes, no `classpath:` or `./`, `..` resolved
+fn normalize_filename(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.strip_prefix("classpath:").unwrap_or(&path);
+
+    let mut parts: Vec<String> = Vec::new();
+    for component in PathBuf::from(path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+/// XML and SQL changelogs directly in `dir`, in the alphabetical order `includeAll` uses
+fn changelog_files(dir: &Path) -> Result<Vec<String>> {
+    let mut files: Vec<String> = fs::read_dir(dir)
+        .context(format!(
+            "Failed to read changelog directory: {}",
+            dir.display()
+        ))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".xml") || name.ends_with(".sql"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const ROOT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<databaseChangeLog xmlns="http://www.liquibase.org/xml/ns/dbchangelog">
+    <!-- <include file="commented-out.xml" relativeToChangelogFile="true"/> -->
+    <include file="20251228-initial.xml" relativeToChangelogFile="true"/>
+    <include file="migrations/20251229-orgs.sql"/>
+    <includeAll path="later/" relativeToChangelogFile="true"/>
+</databaseChangeLog>
+"#;
+
+    const INITIAL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<databaseChangeLog xmlns="http://www.liquibase.org/xml/ns/dbchangelog">
+    <changeSet id="1" author="andy">
+        <createTable tableName="users">
+            <column name="id" type="uuid"/>
+        </createTable>
+    </changeSet>
+    <changeSet id="2" author="andy" logicalFilePath="legacy.xml">
+        <addColumn tableName="users"><column name="email" type="text"/></addColumn>
+    </changeSet>
+</databaseChangeLog>
+"#;
+
+    const ORGS: &str = "--liquibase formatted sql\n\n\
+        --changeset andy:orgs-1\n\
+        CREATE TABLE organizations (id uuid PRIMARY KEY);\n\
+        --rollback DROP TABLE organizations;\n\n\
+        --changeset andy:orgs-2 runOnChange:true\n\
+        ALTER TABLE users ADD COLUMN org_id uuid;\n";
+
+    fn write(dir: &Path, file: &str, content: &str) {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn fixture() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), CHANGELOG_FILE, ROOT);
+        write(dir.path(), "migrations/20251228-initial.xml", INITIAL);
+        write(dir.path(), "migrations/20251229-orgs.sql", ORGS);
+        write(
+            dir.path(),
+            "migrations/later/b.xml",
+            r#"<databaseChangeLog><changeSet id="b" author="sam"/></databaseChangeLog>"#,
+        );
+        write(
+            dir.path(),
+            "migrations/later/a.xml",
+            r#"<databaseChangeLog><changeSet id="a" author="sam"/></databaseChangeLog>"#,
+        );
+        write(dir.path(), "migrations/later/README.md", "not a changelog");
+        dir
+    }
+
+    fn applied(filename: &str, id: &str, author: &str) -> AppliedChangeSet {
+        AppliedChangeSet {
+            id: id.to_string(),
+            author: author.to_string(),
+            filename: filename.to_string(),
+            date_executed: "2026-10-17 10:00:00".to_string(),
+            exec_type: "EXECUTED".to_string(),
+            tag: None,
+        }
+    }
+
+    #[test]
+    fn test_load_follows_includes_in_order() {
+        let dir = fixture();
+        let changelog = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap();
+        let keys: Vec<String> = changelog.changesets.iter().map(ChangeSet::key).collect();
+        assert_eq!(
+            keys,
+            [
+                "migrations/20251228-initial.xml::1::andy",
+                "legacy.xml::2::andy",
+                "migrations/20251229-orgs.sql::orgs-1::andy",
+                "migrations/20251229-orgs.sql::orgs-2::andy",
+                "migrations/later/a.xml::a::sam",
+                "migrations/later/b.xml::b::sam",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tags() {
+        let dir = fixture();
+        write(
+            dir.path(),
+            "migrations/later/a.xml",
+            r#"<databaseChangeLog><changeSet id="a" author="sam"><tagDatabase tag="v1.0"/></changeSet></databaseChangeLog>"#,
+        );
+        let changelog = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap();
+        assert_eq!(changelog.tags().unwrap(), ["v1.0"]);
+    }
+
+    #[test]
+    fn test_empty_changelog_has_no_changesets() {
+        let dir = TempDir::new().unwrap();
+        write(
+            dir.path(),
+            CHANGELOG_FILE,
+            "<databaseChangeLog>\n    <!-- Generated changesets will be included here. -->\n</databaseChangeLog>\n",
+        );
+        assert!(Changelog::load(dir.path(), CHANGELOG_FILE)
+            .unwrap()
+            .changesets
+            .is_empty());
+    }
+
+    #[test]
+    fn test_load_reports_missing_include_and_cycles() {
+        let dir = TempDir::new().unwrap();
+        write(
+            dir.path(),
+            CHANGELOG_FILE,
+            r#"<databaseChangeLog><include file="missing.xml" relativeToChangelogFile="true"/></databaseChangeLog>"#,
+        );
+        let err = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap_err();
+        assert!(format!("{:#}", err).contains("missing.xml"));
+
+        write(
+            dir.path(),
+            CHANGELOG_FILE,
+            r#"<databaseChangeLog><include file="changelog.xml" relativeToChangelogFile="true"/></databaseChangeLog>"#,
+        );
+        let err = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap_err();
+        assert!(format!("{:#}", err).contains("includes itself"));
+    }
+
+    #[test]
+    fn test_changeset_requires_id_and_author() {
+        let dir = TempDir::new().unwrap();
+        write(
+            dir.path(),
+            CHANGELOG_FILE,
+            r#"<databaseChangeLog><changeSet id="1"></changeSet></databaseChangeLog>"#,
+        );
+        let err = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap_err();
+        assert!(format!("{:#}", err).contains("'author'"));
+    }
+
+    #[test]
+    fn test_checksum_ignores_formatting_only() {
+        let dir = fixture();
+        let before = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap();
+
+        write(
+            dir.path(),
+            "migrations/20251228-initial.xml",
+            &INITIAL.replace("        <createTable", "\t<createTable"),
+        );
+        let reformatted = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap();
+        let checksums = |changelog: &Changelog| -> Vec<String> {
+            changelog
+                .changesets
+                .iter()
+                .map(|c| c.checksum.clone())
+                .collect()
+        };
+        assert_eq!(checksums(&before), checksums(&reformatted));
+
+        write(
+            dir.path(),
+            "migrations/20251228-initial.xml",
+            &INITIAL.replace("type=\"uuid\"", "type=\"bigint\""),
+        );
+        let edited = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap();
+        assert_ne!(before.changesets[0].checksum, edited.changesets[0].checksum);
+        assert_eq!(before.changesets[1].checksum, edited.changesets[1].checksum);
+    }
+
+    #[test]
+    fn test_compare_reports_applied_pending_mismatched_and_unknown() {
+        let dir = fixture();
+        let changelog = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap();
+        let rows = [
+            applied("migrations/20251228-initial.xml", "1", "andy"),
+            applied("legacy.xml", "2", "andy"),
+            applied("classpath:migrations/20251229-orgs.sql", "orgs-1", "andy"),
+            applied("migrations/removed.xml", "old", "andy"),
+        ]
+        .map(|mut row| {
+            row.filename = normalize_filename(&row.filename);
+            row
+        });
+        let recorded = BTreeMap::from([
+            (
+                changelog.changesets[0].key(),
+                changelog.changesets[0].checksum.clone(),
+            ),
+            (changelog.changesets[1].key(), "edited".to_string()),
+        ]);
+
+        let report = changelog.compare(&rows, &recorded);
+        assert_eq!(
+            report.applied,
+            [
+                "migrations/20251228-initial.xml::1::andy",
+                "legacy.xml::2::andy",
+                "migrations/20251229-orgs.sql::orgs-1::andy",
+            ]
+        );
+        assert_eq!(
+            report.pending,
+            [
+                "migrations/20251229-orgs.sql::orgs-2::andy",
+                "migrations/later/a.xml::a::sam",
+                "migrations/later/b.xml::b::sam",
+            ]
+        );
+        assert_eq!(report.mismatched, ["legacy.xml::2::andy"]);
+        assert_eq!(report.unknown, ["migrations/removed.xml::old::andy"]);
+        assert!(!report.is_up_to_date());
+    }
+
+    #[test]
+    fn test_rolled_back_changeset_can_be_edited_and_reapplied() {
+        let dir = fixture();
+        let mut recorded = BTreeMap::new();
+        let sync = |changelog: &Changelog, applied: &[String], recorded: &mut BTreeMap<_, _>| {
+            let changes = changelog.checksum_changes(applied, recorded);
+            let missing: Vec<(String, String)> = changes
+                .missing
+                .iter()
+                .map(|c| (c.key(), c.checksum.clone()))
+                .collect();
+            for key in changes.stale {
+                recorded.remove(&key);
+            }
+            recorded.extend(missing);
+        };
+        let applied_rows = |keys: &[String]| -> Vec<AppliedChangeSet> {
+            keys.iter()
+                .map(|key| {
+                    let (filename, id, author) = split_key(key);
+                    applied(filename, id, author)
+                })
+                .collect()
+        };
+
+        // Apply everything
+        let changelog = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap();
+        let all: Vec<String> = changelog.changesets.iter().map(ChangeSet::key).collect();
+        sync(&changelog, &all, &mut recorded);
+        assert_eq!(recorded.len(), all.len());
+
+        // Roll back the first changeset
+        let rest = all[1..].to_vec();
+        sync(&changelog, &rest, &mut recorded);
+        assert!(!recorded.contains_key(&all[0]));
+
+        // Edit it and apply again
+        write(
+            dir.path(),
+            "migrations/20251228-initial.xml",
+            &INITIAL.replace("type=\"uuid\"", "type=\"bigint\""),
+        );
+        let edited = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap();
+        sync(&edited, &all, &mut recorded);
+        let report = edited.compare(&applied_rows(&all), &recorded);
+        assert!(report.mismatched.is_empty(), "{:?}", report.mismatched);
+
+        // Editing an applied changeset is still reported
+        let recorded_before = recorded.clone();
+        write(dir.path(), "migrations/20251228-initial.xml", INITIAL);
+        let reverted = Changelog::load(dir.path(), CHANGELOG_FILE).unwrap();
+        sync(&reverted, &all, &mut recorded);
+        assert_eq!(recorded, recorded_before);
+        assert_eq!(
+            reverted.compare(&applied_rows(&all), &recorded).mismatched,
+            [all[0].clone()]
+        );
+    }
+
+    #[test]
+    fn test_sql_changelog_requires_header() {
+        let dir = TempDir::new().unwrap();
+        write(
+            dir.path(),
+            "migrations/plain.sql",
+            "CREATE TABLE t (id int);\n",
+        );
+        let err = Changelog::load(dir.path(), "migrations/plain.sql").unwrap_err();
+        assert!(format!("{:#}", err).contains("--liquibase formatted sql"));
+    }
+
+    #[test]
+    fn test_insert_include_keeps_file_name_order() {
+        let empty =
+            "<databaseChangeLog>\n    <!-- <include file=\"x.xml\"/> -->\n\n</databaseChangeLog>\n";
+        let one = insert_include(empty, "migrations/20251229-b.xml")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            one,
+            "<databaseChangeLog>\n    <!-- <include file=\"x.xml\"/> -->\n\n    \
+             <include file=\"20251229-b.xml\" relativeToChangelogFile=\"true\"/>\n</databaseChangeLog>\n"
+        );
+
+        let two = insert_include(&one, "migrations/20251228-a.xml")
+            .unwrap()
+            .unwrap();
+        let three = insert_include(&two, "migrations/20251230-c.xml")
+            .unwrap()
+            .unwrap();
+        let files: Vec<String> = root_includes(&three)
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|include| include.path)
+            .collect();
+        assert_eq!(
+            files,
+            [
+                "migrations/20251228-a.xml",
+                "migrations/20251229-b.xml",
+                "migrations/20251230-c.xml",
+            ]
+        );
+        assert!(three.contains(
+            "\n    <include file=\"20251230-c.xml\" relativeToChangelogFile=\"true\"/>\n</"
+        ));
+
+        assert_eq!(
+            insert_include(&three, "migrations/20251228-a.xml").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_insert_include_into_single_line_changelog() {
+        let updated = insert_include(
+            "<databaseChangeLog></databaseChangeLog>",
+            "migrations/a.xml",
+        )
+        .unwrap()
+        .unwrap();
+        let (includes, _) = root_includes(&updated).unwrap();
+        assert_eq!(includes[0].path, "migrations/a.xml");
+    }
+
+    #[test]
+    fn test_check_includes() {
+        let dir = fixture();
+        assert!(check_includes(dir.path()).unwrap().is_ok());
+
+        write(
+            dir.path(),
+            "migrations/20251230-forgotten.xml",
+            "<databaseChangeLog/>",
+        );
+        let check = check_includes(dir.path()).unwrap();
+        assert_eq!(check.not_included, ["migrations/20251230-forgotten.xml"]);
+        assert!(check.missing.is_empty());
+
+        assert!(register(dir.path(), "migrations/20251230-forgotten.xml").unwrap());
+        assert!(!register(dir.path(), "migrations/20251230-forgotten.xml").unwrap());
+        assert!(check_includes(dir.path()).unwrap().is_ok());
+
+        fs::remove_file(dir.path().join("migrations/20251229-orgs.sql")).unwrap();
+        let check = check_includes(dir.path()).unwrap();
+        assert_eq!(check.missing, ["migrations/20251229-orgs.sql"]);
+        assert!(!check.is_ok());
+    }
+
+    #[test]
+    fn test_normalize_filename() {
+        assert_eq!(normalize_filename("./migrations/a.xml"), "migrations/a.xml");
+        assert_eq!(
+            normalize_filename("classpath:migrations/a.xml"),
+            "migrations/a.xml"
+        );
+        assert_eq!(
+            normalize_filename("migrations\\later\\..\\a.xml"),
+            "migrations/a.xml"
+        );
+        assert_eq!(
+            resolve("migrations/changelog.xml", "a.xml", true),
+            "migrations/a.xml"
+        );
+        assert_eq!(
+            resolve("migrations/changelog.xml", "db/a.xml", false),
+            "db/a.xml"
+        );
+    }
+}
@@ -2,9 +2,47 @@
 //!
 //! This module handles loading configuration from files or using defaults.
 //! The configuration specifies paths to the cwrdd repository components.
+//!
+//! Settings are merged from several layers, each overriding the ones before it:
+//!
+//! 1. Built-in defaults, with the `dev` profile's database settings taken
+//!    from `db/liquibase.properties` (see [`crate::properties`])
+//! 2. `cwrdd-make-config.toml` next to the cwrdd-make binary (legacy location)
+//! 3. The user config file, `$XDG_CONFIG_HOME/cwrdd-make/config.toml`
+//! 4. The repository found by walking up from the current directory
+//! 5. The nearest `.cwrdd-make.toml` between the current directory and that repository's root
+//! 6. `CWRDD_*` environment variables, e.g. `CWRDD_REPO_PATH`
+//! 7. Command-line flags, e.g. `--repo-path`
+//!
+//! Every [`Config`] remembers which layer each setting came from; see
+//! [`Config::settings`].
+//!
+//! Connection details and ports live in named [`Profile`]s, selected with
+//! `profile = "..."`, `CWRDD_PROFILE` or `--profile`. Config files can adjust
+//! them under `[profiles.<name>]`, and `CWRDD_DATABASE_URL`,
+//! `CWRDD_DATABASE_USER`, `CWRDD_DATABASE_PASSWORD` and `CWRDD_REDIS_URL`
+//! override the selected profile.
+//!
+//! `[lint]` sets the severity (`error`, `warning` or `off`) of each
+//! `migrate lint` rule, e.g. `missing-rollback = "error"`; see [`crate::lint`].
+//!
+//! `[certs]` sets the names the dev TLS certificate covers and how long it
+//! lasts, e.g. `extra_hosts = ["devbox.lan"]`; see [`crate::ca`].
+//!
+//! `[groups]` names sets of compose services for `--only` and `--without`,
+//! e.g. `data = ["postgres", "redis"]`; see [`crate::services`].
 
-use anyhow::{Context, Result};
+use crate::ca::{CertPaths, CertSettings};
+use crate::lint::{Rule, Severity};
+use crate::plan::{Plan, REDACTED};
+use crate::profile::{Profile, DEFAULT_PROFILE};
+use crate::properties::LiquibaseProperties;
+use crate::services::builtin_groups;
+use crate::task::Task;
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::{Path, PathBuf};
 
 /// Configuration for cwrdd-make.
@@ -23,6 +61,148 @@ use std::path::{Path, PathBuf};
 pub struct Config {
     /// Path to the cwrdd repository root
     pub repo_path: PathBuf,
+
+    /// Name of the selected profile
+    #[serde(rename = "profile")]
+    pub profile_name: String,
+
+    /// Every known profile, by name
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// `migrate lint` severities that differ from each rule's default, by rule name
+    #[serde(default)]
+    pub lint: BTreeMap<String, Severity>,
+
+    /// Names and lifetime of the dev TLS certificate
+    #[serde(default)]
+    pub certs: CertSettings,
+
+    /// Service groups for `--only` and `--without`, by name
+    #[serde(default = "builtin_groups")]
+    pub groups: BTreeMap<String, Vec<String>>,
+
+    /// Plan that tasks are recorded into instead of being run (`--dry-run`)
+    #[serde(skip)]
+    pub dry_run: Option<Plan>,
+
+    /// Where each setting's value came from
+    #[serde(skip)]
+    pub(crate) sources: BTreeMap<String, Source>,
+}
+
+/// Where a configuration value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Built-in default
+    Default,
+    /// `cwrdd-make-config.toml` next to the binary
+    BinaryFile(PathBuf),
+    /// The user config file under `$XDG_CONFIG_HOME`
+    UserFile(PathBuf),
+    /// The repository root found by walking up from the current directory
+    Discovered(PathBuf),
+    /// A `.cwrdd-make.toml` project file
+    ProjectFile(PathBuf),
+    /// A `CWRDD_*` environment variable
+    Env(String),
+    /// A command-line flag
+    Flag(String),
+    /// An entry in `liquibase.properties`, as `path:line`
+    LiquibaseProperties(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::BinaryFile(path) => write!(f, "binary config {}", path.display()),
+            Source::UserFile(path) => write!(f, "user config {}", path.display()),
+            Source::Discovered(path) => write!(f, "repository found at {}", path.display()),
+            Source::ProjectFile(path) => write!(f, "project config {}", path.display()),
+            Source::Env(var) => write!(f, "environment variable {}", var),
+            Source::Flag(flag) => write!(f, "{} flag", flag),
+            Source::LiquibaseProperties(location) => {
+                write!(f, "liquibase properties {}", location)
+            }
+        }
+    }
+}
+
+/// One layer of settings, as read from a config file, the environment or flags.
+///
+/// Unset fields leave the value from lower layers untouched.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    /// Path to the cwrdd repository root
+    pub repo_path: Option<PathBuf>,
+
+    /// Name of the profile to use
+    pub profile: Option<String>,
+
+    /// Changes to individual profiles, by name
+    pub profiles: Option<BTreeMap<String, toml::Table>>,
+
+    /// `migrate lint` severities, by rule name
+    pub lint: Option<BTreeMap<String, Severity>>,
+
+    /// Changes to the dev TLS certificate settings
+    pub certs: Option<toml::Table>,
+
+    /// Service groups to add or replace, by name
+    pub groups: Option<BTreeMap<String, Vec<String>>>,
+
+    /// Changes to whichever profile ends up selected
+    #[serde(skip)]
+    pub active_profile: Option<toml::Table>,
+}
+
+impl ConfigLayer {
+    /// Read a layer from a TOML file. Relative paths are resolved against the file's directory.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read config file: {}", path.display()))?;
+
+        let mut layer: ConfigLayer = toml::from_str(&content)
+            .context(format!("Failed to parse config file: {}", path.display()))?;
+
+        let base = path.parent().unwrap_or(Path::new("."));
+        layer.repo_path = layer.repo_path.map(|p| resolve_path(base, &p));
+        Ok(layer)
+    }
+
+    /// Read a layer from `CWRDD_*` variables, returning the variable each setting came from
+    pub fn from_env(
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> (Self, BTreeMap<String, String>) {
+        let mut layer = ConfigLayer::default();
+        let mut names = BTreeMap::new();
+        for (key, value) in vars {
+            let setting = match key.as_str() {
+                "CWRDD_REPO_PATH" => {
+                    layer.repo_path = Some(expand_tilde(&value));
+                    "repo_path"
+                }
+                "CWRDD_PROFILE" => {
+                    layer.profile = Some(value);
+                    "profile"
+                }
+                "CWRDD_DATABASE_URL" | "CWRDD_DATABASE_USER" | "CWRDD_DATABASE_PASSWORD"
+                | "CWRDD_REDIS_URL" => {
+                    let field = key["CWRDD_".len()..].to_ascii_lowercase();
+                    layer
+                        .active_profile
+                        .get_or_insert_with(toml::Table::new)
+                        .insert(field.clone(), toml::Value::String(value));
+                    names.insert(format!("profile.{}", field), key);
+                    continue;
+                }
+                _ => continue,
+            };
+            names.insert(setting.to_string(), key);
+        }
+        (layer, names)
+    }
 }
 
 impl Config {
@@ -32,28 +212,237 @@ impl Config {
     /// Config file name (placed alongside the cwrdd-make binary)
     const CONFIG_FILE_NAME: &'static str = "cwrdd-make-config.toml";
 
-    /// Load configuration from file or use defaults
+    /// Project config file name, looked for from the current directory up to the repo root
+    pub const PROJECT_FILE_NAME: &'static str = ".cwrdd-make.toml";
+
+    /// Load configuration from every layer, without command-line overrides
     pub fn load() -> Result<Self> {
-        // Try to load from config file next to binary
-        if let Some(config_path) = Self::config_file_path() {
-            if config_path.exists() {
-                return Self::load_from_file(&config_path);
+        Self::load_with(ConfigLayer::default())
+    }
+
+    /// Load configuration from every layer, with `flags` applied last
+    pub fn load_with(flags: ConfigLayer) -> Result<Self> {
+        let mut config = Self::default();
+
+        // Legacy config file next to binary
+        if let Some(path) = Self::config_file_path().filter(|p| p.exists()) {
+            config.apply(ConfigLayer::from_file(&path)?, |_| Source::BinaryFile(path.clone()))?;
+        }
+
+        if let Some(path) = user_config_path().filter(|p| p.exists()) {
+            config.apply(ConfigLayer::from_file(&path)?, |_| Source::UserFile(path.clone()))?;
+        }
+
+        if let Ok(cwd) = std::env::current_dir() {
+            let project = discover_project(&cwd);
+            if let Some(root) = project.repo_root {
+                let layer = ConfigLayer {
+                    repo_path: Some(root.clone()),
+                    ..ConfigLayer::default()
+                };
+                config.apply(layer, |_| Source::Discovered(root.clone()))?;
+            }
+            if let Some(path) = project.config_file {
+                config.apply(ConfigLayer::from_file(&path)?, |_| {
+                    Source::ProjectFile(path.clone())
+                })?;
             }
         }
 
-        // Fall back to default
-        Ok(Self::default())
+        // Profile overrides from the environment apply to the profile selected
+        // by the flags too, so they go on last
+        let (mut env, names) = ConfigLayer::from_env(std::env::vars());
+        let env_profile = ConfigLayer {
+            active_profile: env.active_profile.take(),
+            ..ConfigLayer::default()
+        };
+        let env_source = |key: &str| Source::Env(names[key].clone());
+        config.apply(env, env_source)?;
+        config.apply(flags, |key| Source::Flag(format!("--{}", key.replace('_', "-"))))?;
+        config.apply(env_profile, env_source)?;
+        config.apply_liquibase_properties(&std::env::vars().collect())?;
+
+        config.profile()?.database()?;
+        config.profile()?.redis_address()?;
+        Ok(config)
     }
 
-    /// Load configuration from a specific file
-    fn load_from_file(path: &Path) -> Result<Self> {
-        let content = std::fs::read_to_string(path)
-            .context(format!("Failed to read config file: {}", path.display()))?;
+    /// Overlay the values set in `layer`, recording `source(key)` for each one.
+    ///
+    /// Profile settings are keyed `profiles.<name>.<field>` in `sources`;
+    /// `source` is given `profile.<field>` for changes to the selected profile.
+    pub fn apply(&mut self, layer: ConfigLayer, source: impl Fn(&str) -> Source) -> Result<()> {
+        if let Some(repo_path) = layer.repo_path {
+            self.repo_path = repo_path;
+            self.sources.insert("repo_path".to_string(), source("repo_path"));
+        }
+        if let Some(profile) = layer.profile {
+            self.profile_name = profile;
+            self.sources.insert("profile".to_string(), source("profile"));
+        }
+        for (name, changes) in layer.profiles.unwrap_or_default() {
+            let keys = leaf_keys(&changes);
+            self.merge_profile(&name, changes)?;
+            for key in keys {
+                let source = source(&format!("profiles.{}.{}", name, key));
+                self.sources.insert(format!("profiles.{}.{}", name, key), source);
+            }
+        }
+        if let Some(changes) = layer.active_profile {
+            let name = self.profile_name.clone();
+            let keys = leaf_keys(&changes);
+            self.merge_profile(&name, changes)?;
+            for key in keys {
+                let source = source(&format!("profile.{}", key));
+                self.sources.insert(format!("profiles.{}.{}", name, key), source);
+            }
+        }
+        for (rule, severity) in layer.lint.unwrap_or_default() {
+            Rule::from_name(&rule)?;
+            let key = format!("lint.{}", rule);
+            self.sources.insert(key.clone(), source(&key));
+            self.lint.insert(rule, severity);
+        }
+        if let Some(changes) = layer.certs {
+            let keys = leaf_keys(&changes);
+            let mut value = toml::Value::try_from(&self.certs)?;
+            merge_tables(&mut value, changes);
+            self.certs = value.try_into().context("Invalid [certs] settings")?;
+            for key in keys {
+                let key = format!("certs.{}", key);
+                self.sources.insert(key.clone(), source(&key));
+            }
+        }
+        for (name, services) in layer.groups.unwrap_or_default() {
+            let key = format!("groups.{}", name);
+            self.sources.insert(key.clone(), source(&key));
+            self.groups.insert(name, services);
+        }
+        Ok(())
+    }
 
-        let config: Config = toml::from_str(&content)
-            .context("Failed to parse config file")?;
+    /// Take the `dev` profile's database settings from `db/liquibase.properties`
+    /// (with overrides from `vars`) wherever no other layer set them, so that
+    /// Liquibase run by hand and cwrdd-make connect to the same database.
+    pub fn apply_liquibase_properties(&mut self, vars: &BTreeMap<String, String>) -> Result<()> {
+        let Some(liquibase) = LiquibaseProperties::load(&self.db_path(), vars)? else {
+            return Ok(());
+        };
 
-        Ok(config)
+        let mut changes = toml::Table::new();
+        let mut sources = Vec::new();
+        for (field, key) in [
+            ("database_url", "url"),
+            ("database_user", "username"),
+            ("database_password", "password"),
+        ] {
+            let source_key = format!("profiles.{}.{}", DEFAULT_PROFILE, field);
+            let Some(property) = liquibase.properties.property(key) else {
+                continue;
+            };
+            if self.sources.contains_key(&source_key) {
+                continue;
+            }
+            let value = match key {
+                "url" => liquibase.database_url(key)?.unwrap_or_default(),
+                _ => property.value.clone(),
+            };
+            changes.insert(field.to_string(), toml::Value::String(value));
+            let source = match &property.env {
+                Some(var) => Source::Env(var.clone()),
+                None => Source::LiquibaseProperties(liquibase.location(property)),
+            };
+            sources.push((source_key, source));
+        }
+
+        if !changes.is_empty() {
+            self.merge_profile(DEFAULT_PROFILE, changes)?;
+            self.sources.extend(sources);
+        }
+        Ok(())
+    }
+
+    /// Apply partial settings to a profile. Unknown profiles start from `dev`,
+    /// but aren't disposable unless they say so.
+    fn merge_profile(&mut self, name: &str, changes: toml::Table) -> Result<()> {
+        let base = self.profiles.get(name).cloned().unwrap_or_else(|| Profile {
+            disposable: false,
+            ..Profile::dev()
+        });
+        let mut value = toml::Value::try_from(base)?;
+        merge_tables(&mut value, changes);
+        let profile = value
+            .try_into()
+            .context(format!("Invalid settings for profile '{}'", name))?;
+        self.profiles.insert(name.to_string(), profile);
+        Ok(())
+    }
+
+    /// The selected profile
+    pub fn profile(&self) -> Result<&Profile> {
+        match self.profiles.get(&self.profile_name) {
+            Some(profile) => Ok(profile),
+            None => bail!(
+                "Unknown profile '{}'. Available profiles: {}",
+                self.profile_name,
+                self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+
+    /// Every setting's effective value and where it came from.
+    ///
+    /// Settings of the selected profile are listed without the `profiles.<name>`
+    /// prefix, and passwords are redacted.
+    pub fn settings(&self) -> Vec<(String, String, Source)> {
+        let source = |key: &str| self.sources.get(key).cloned().unwrap_or(Source::Default);
+        let mut settings = vec![
+            (
+                "repo_path".to_string(),
+                self.repo_path.display().to_string(),
+                source("repo_path"),
+            ),
+            ("profile".to_string(), self.profile_name.clone(), source("profile")),
+        ];
+
+        if let Some(profile) = self.profiles.get(&self.profile_name) {
+            let value = toml::Value::try_from(profile).expect("profiles are always serializable");
+            for (key, value) in leaf_values(&value) {
+                let value = if key.contains("password") {
+                    REDACTED.to_string()
+                } else {
+                    value
+                };
+                let from = source(&format!("profiles.{}.{}", self.profile_name, key));
+                settings.push((key, value, from));
+            }
+        }
+
+        for rule in Rule::ALL {
+            let severity = self
+                .lint
+                .get(rule.name())
+                .copied()
+                .unwrap_or(rule.default_severity());
+            let key = format!("lint.{}", rule.name());
+            let from = source(&key);
+            settings.push((key, severity.to_string(), from));
+        }
+
+        let certs =
+            toml::Value::try_from(&self.certs).expect("cert settings are always serializable");
+        for (key, value) in leaf_values(&certs) {
+            let key = format!("certs.{}", key);
+            let from = source(&key);
+            settings.push((key, value, from));
+        }
+
+        for (name, services) in &self.groups {
+            let key = format!("groups.{}", name);
+            let from = source(&key);
+            settings.push((key, services.join(","), from));
+        }
+        settings
     }
 
     /// Get the path to the config file (next to the binary)
@@ -63,6 +452,16 @@ impl Config {
         Some(exe_dir.join(Self::CONFIG_FILE_NAME))
     }
 
+    /// Whether commands should be recorded rather than run
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run.is_some()
+    }
+
+    /// Create a task that honours this configuration's dry-run setting
+    pub fn task(&self, name: impl Into<String>, command: impl Into<String>) -> Task {
+        Task::new(name, command).dry_run(self.dry_run.clone())
+    }
+
     /// Get the path to the app directory
     pub fn app_path(&self) -> PathBuf {
         self.repo_path.join("app")
@@ -72,21 +471,151 @@ impl Config {
     pub fn db_path(&self) -> PathBuf {
         self.repo_path.join("db")
     }
+
+    /// Get the path to the database snapshots directory
+    pub fn snapshots_path(&self) -> PathBuf {
+        self.repo_path.join(".cwrdd/snapshots")
+    }
+
+    /// Get the path to the dev TLS certificate
+    pub fn cert_path(&self) -> PathBuf {
+        self.cert_paths().cert
+    }
+
+    /// Get the paths of the local CA and the dev TLS certificate
+    pub fn cert_paths(&self) -> CertPaths {
+        CertPaths::new(
+            &self.repo_path.join(".cwrdd/ca"),
+            &self.repo_path.join("config/certs"),
+        )
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             repo_path: expand_tilde(Self::DEFAULT_REPO_PATH),
+            profile_name: DEFAULT_PROFILE.to_string(),
+            profiles: Profile::builtin(),
+            lint: BTreeMap::new(),
+            certs: CertSettings::default(),
+            groups: builtin_groups(),
+            dry_run: None,
+            sources: BTreeMap::new(),
         }
     }
 }
 
+/// What was found by walking up from a directory
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ProjectDiscovery {
+    /// Root of the cwrdd repository containing the directory, if any
+    pub repo_root: Option<PathBuf>,
+    /// Nearest `.cwrdd-make.toml` between the directory and the repository root
+    pub config_file: Option<PathBuf>,
+}
+
+/// Walk up from `start` looking for the cwrdd repository root and a project config file.
+///
+/// The walk stops at the first directory that looks like the cwrdd repository
+/// (it has `compose.yaml` and `make/Cargo.toml`) or at a git root, so project
+/// files above the repository are never picked up.
+pub fn discover_project(start: &Path) -> ProjectDiscovery {
+    let mut found = ProjectDiscovery::default();
+
+    for dir in start.ancestors() {
+        let candidate = dir.join(Config::PROJECT_FILE_NAME);
+        if found.config_file.is_none() && candidate.is_file() {
+            found.config_file = Some(candidate);
+        }
+
+        if is_repo_root(dir) {
+            found.repo_root = Some(dir.to_path_buf());
+            return found;
+        }
+        if dir.join(".git").exists() {
+            return found;
+        }
+    }
+
+    // Not inside any repository, so a stray project file doesn't apply
+    ProjectDiscovery::default()
+}
+
+/// Whether `dir` looks like the root of the cwrdd repository
+fn is_repo_root(dir: &Path) -> bool {
+    dir.join("compose.yaml").is_file() && dir.join("make/Cargo.toml").is_file()
+}
+
+/// Path of the user config file, following the XDG base directory spec
+fn user_config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("cwrdd-make/config.toml"))
+}
+
+/// Recursively overlay `changes` onto `value`
+fn merge_tables(value: &mut toml::Value, changes: toml::Table) {
+    let toml::Value::Table(table) = value else {
+        *value = toml::Value::Table(changes);
+        return;
+    };
+    for (key, change) in changes {
+        match (table.get_mut(&key), change) {
+            (Some(existing @ toml::Value::Table(_)), toml::Value::Table(nested)) => {
+                merge_tables(existing, nested)
+            }
+            (_, change) => {
+                table.insert(key, change);
+            }
+        }
+    }
+}
+
+/// Dotted paths of every non-table value in `table`, e.g. `ports.app`
+fn leaf_keys(table: &toml::Table) -> Vec<String> {
+    leaf_values(&toml::Value::Table(table.clone()))
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/// Dotted paths and displayed values of every non-table value in `value`
+fn leaf_values(value: &toml::Value) -> Vec<(String, String)> {
+    let mut leaves = Vec::new();
+    if let toml::Value::Table(table) = value {
+        for (key, value) in table {
+            match value {
+                toml::Value::Table(_) => {
+                    for (nested, value) in leaf_values(value) {
+                        leaves.push((format!("{}.{}", key, nested), value));
+                    }
+                }
+                toml::Value::String(s) => leaves.push((key.clone(), s.clone())),
+                other => leaves.push((key.clone(), other.to_string())),
+            }
+        }
+    }
+    leaves
+}
+
+/// Expand `~` and resolve a relative path against `base`
+fn resolve_path(base: &Path, path: &Path) -> PathBuf {
+    let path = expand_tilde(&path.to_string_lossy());
+    if path.is_absolute() {
+        path
+    } else {
+        base.join(path)
+    }
+}
+
 /// Expand ~ to home directory
 fn expand_tilde(path: &str) -> PathBuf {
-    if path.starts_with("~/") {
+    if let Some(rest) = path.strip_prefix("~/") {
         if let Some(home) = std::env::var_os("HOME") {
-            return PathBuf::from(home).join(&path[2..]);
+            return PathBuf::from(home).join(rest);
         }
     }
     PathBuf::from(path)
@@ -114,4 +643,320 @@ mod tests {
         let app_path = config.app_path();
         assert!(app_path.ends_with("app"));
     }
+
+    fn fake_repo() -> tempfile::TempDir {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(repo.path().join("make")).unwrap();
+        std::fs::create_dir_all(repo.path().join("db/schema")).unwrap();
+        std::fs::write(repo.path().join("compose.yaml"), "services: {}\n").unwrap();
+        std::fs::write(repo.path().join("make/Cargo.toml"), "").unwrap();
+        repo
+    }
+
+    #[test]
+    fn test_liquibase_properties_fill_in_dev_database() {
+        let repo = fake_repo();
+        std::fs::write(
+            repo.path().join("db/liquibase.properties"),
+            "url = jdbc:postgresql://localhost:5433/cwrdd_dev\n\
+             username: liquibase_user\n\
+             password=from-file\n",
+        )
+        .unwrap();
+
+        let mut config = Config {
+            repo_path: repo.path().to_path_buf(),
+            ..Config::default()
+        };
+        let (env, names) = ConfigLayer::from_env([(
+            "CWRDD_DATABASE_USER".to_string(),
+            "env_user".to_string(),
+        )]);
+        config
+            .apply(env, |key| Source::Env(names[key].clone()))
+            .unwrap();
+        let vars = BTreeMap::from([("LIQUIBASE_PASSWORD".to_string(), "from-env".to_string())]);
+        config.apply_liquibase_properties(&vars).unwrap();
+
+        let db = config.profile().unwrap().database().unwrap();
+        assert_eq!(db.port, 5433);
+        assert_eq!(db.user, "env_user");
+        assert_eq!(db.password, "from-env");
+        assert_eq!(
+            setting(&config, "database_url").1.to_string(),
+            format!(
+                "liquibase properties {}:1",
+                repo.path().join("db/liquibase.properties").display()
+            )
+        );
+        assert_eq!(
+            setting(&config, "database_password").1,
+            Source::Env("LIQUIBASE_PASSWORD".to_string())
+        );
+        assert_eq!(config.profiles["test"], Profile::test());
+    }
+
+    #[test]
+    fn test_discover_project_from_subdirectory() {
+        let repo = fake_repo();
+        let project_file = repo.path().join(Config::PROJECT_FILE_NAME);
+        std::fs::write(&project_file, "").unwrap();
+
+        let found = discover_project(&repo.path().join("db/schema"));
+        assert_eq!(found.repo_root.as_deref(), Some(repo.path()));
+        assert_eq!(found.config_file, Some(project_file));
+    }
+
+    #[test]
+    fn test_discover_project_stops_at_git_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(Config::PROJECT_FILE_NAME), "").unwrap();
+        let checkout = dir.path().join("other");
+        std::fs::create_dir_all(checkout.join(".git")).unwrap();
+
+        // The project file sits above the git root, so it isn't used
+        assert_eq!(discover_project(&checkout), ProjectDiscovery::default());
+
+        let project_file = checkout.join(Config::PROJECT_FILE_NAME);
+        std::fs::write(&project_file, "").unwrap();
+        let found = discover_project(&checkout);
+        assert_eq!(found.repo_root, None);
+        assert_eq!(found.config_file, Some(project_file));
+    }
+
+    #[test]
+    fn test_project_file_relative_repo_path() {
+        let repo = fake_repo();
+        let project_file = repo.path().join("make").join(Config::PROJECT_FILE_NAME);
+        std::fs::write(&project_file, "repo_path = \"..\"\n").unwrap();
+
+        let layer = ConfigLayer::from_file(&project_file).unwrap();
+        assert_eq!(layer.repo_path, Some(repo.path().join("make/..")));
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.toml");
+        std::fs::write(&file, "repo_pth = \"/tmp\"\n").unwrap();
+
+        assert!(ConfigLayer::from_file(&file).is_err());
+    }
+
+    #[test]
+    fn test_lint_severities() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(Config::PROJECT_FILE_NAME);
+        let toml = "[lint]\nmissing-rollback = \"error\"\ncolumn-type-change = \"off\"\n";
+        std::fs::write(&file, toml).unwrap();
+
+        let mut config = Config::default();
+        config
+            .apply(ConfigLayer::from_file(&file).unwrap(), |_| {
+                Source::ProjectFile(file.clone())
+            })
+            .unwrap();
+        assert_eq!(config.lint["missing-rollback"], Severity::Error);
+        assert_eq!(setting(&config, "lint.column-type-change").0, "off");
+        let (value, source) = setting(&config, "lint.not-null-without-default");
+        assert_eq!((value.as_str(), source), ("error", Source::Default));
+
+        std::fs::write(&file, "[lint]\nmissing-rollbacks = \"error\"\n").unwrap();
+        let err = Config::default()
+            .apply(ConfigLayer::from_file(&file).unwrap(), |_| Source::Default)
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown lint rule 'missing-rollbacks'"));
+    }
+
+    #[test]
+    fn test_cert_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(Config::PROJECT_FILE_NAME);
+        std::fs::write(&file, "[certs]\nextra_hosts = [\"devbox.lan\"]\nleaf_days = 7\n").unwrap();
+
+        let mut config = Config::default();
+        config
+            .apply(ConfigLayer::from_file(&file).unwrap(), |_| {
+                Source::ProjectFile(file.clone())
+            })
+            .unwrap();
+        assert_eq!(config.certs.extra_hosts, ["devbox.lan"]);
+        assert_eq!(config.certs.leaf_days, 7);
+        assert!(config.certs.lan_ip);
+        assert_eq!(setting(&config, "certs.leaf_days").1, Source::ProjectFile(file.clone()));
+        assert_eq!(setting(&config, "certs.lan_ip").1, Source::Default);
+
+        std::fs::write(&file, "[certs]\nleaf_days = \"soon\"\n").unwrap();
+        assert!(Config::default()
+            .apply(ConfigLayer::from_file(&file).unwrap(), |_| Source::Default)
+            .is_err());
+    }
+
+    #[test]
+    fn test_service_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(Config::PROJECT_FILE_NAME);
+        std::fs::write(&file, "[groups]\ndata = [\"postgres\", \"redis\"]\n").unwrap();
+
+        let mut config = Config::default();
+        config
+            .apply(ConfigLayer::from_file(&file).unwrap(), |_| {
+                Source::ProjectFile(file.clone())
+            })
+            .unwrap();
+        assert_eq!(config.groups["data"], ["postgres", "redis"]);
+        assert_eq!(config.groups["observability"].len(), 6);
+        let (value, source) = setting(&config, "groups.data");
+        assert_eq!((value.as_str(), source), ("postgres,redis", Source::ProjectFile(file)));
+        assert_eq!(setting(&config, "groups.observability").1, Source::Default);
+    }
+
+    #[test]
+    fn test_layers_record_sources() {
+        let mut config = Config::default();
+        assert_eq!(config.settings()[0].2, Source::Default);
+
+        let (env, names) = ConfigLayer::from_env([
+            ("CWRDD_REPO_PATH".to_string(), "/from/env".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+        ]);
+        config
+            .apply(env, |key| Source::Env(names[key].clone()))
+            .unwrap();
+        assert_eq!(config.repo_path, PathBuf::from("/from/env"));
+        assert_eq!(
+            config.settings()[0].2,
+            Source::Env("CWRDD_REPO_PATH".to_string())
+        );
+
+        // Empty layers leave earlier values alone
+        config
+            .apply(ConfigLayer::default(), |_| Source::Flag("--unused".to_string()))
+            .unwrap();
+        assert_eq!(config.repo_path, PathBuf::from("/from/env"));
+
+        config
+            .apply(
+                ConfigLayer {
+                    repo_path: Some(PathBuf::from("/from/flag")),
+                    ..ConfigLayer::default()
+                },
+                |_| Source::Flag("--repo-path".to_string()),
+            )
+            .unwrap();
+        let (key, value, source) = &config.settings()[0];
+        assert_eq!(key, "repo_path");
+        assert_eq!(value, "/from/flag");
+        assert_eq!(source.to_string(), "--repo-path flag");
+    }
+
+    fn setting(config: &Config, key: &str) -> (String, Source) {
+        let (_, value, source) = config
+            .settings()
+            .into_iter()
+            .find(|(k, _, _)| k == key)
+            .unwrap();
+        (value, source)
+    }
+
+    #[test]
+    fn test_default_profile() {
+        let config = Config::default();
+        assert_eq!(config.profile_name, "dev");
+        let db = config.profile().unwrap().database().unwrap();
+        assert_eq!(db.name, "cwrdd_dev");
+        assert_eq!(setting(&config, "database_password").0, REDACTED);
+    }
+
+    #[test]
+    fn test_profile_file_changes_and_new_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(Config::PROJECT_FILE_NAME);
+        std::fs::write(
+            &file,
+            "profile = \"staging\"\n\
+             [profiles.test.ports]\napp = 9443\n\
+             [profiles.staging]\ndatabase_url = \"postgres://db:5433/cwrdd_staging\"\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        let source = Source::ProjectFile(file.clone());
+        config
+            .apply(ConfigLayer::from_file(&file).unwrap(), |_| source.clone())
+            .unwrap();
+
+        // Partial changes keep the rest of the built-in profile
+        let test = &config.profiles["test"];
+        assert_eq!(test.ports.app, 9443);
+        assert_eq!(test.ports.grafana, Profile::test().ports.grafana);
+
+        // New profiles start from dev
+        let staging = config.profile().unwrap();
+        assert_eq!(staging.database().unwrap().port, 5433);
+        assert_eq!(staging.database_user, "cwrdd_user");
+        assert!(!staging.disposable && test.disposable);
+        assert_eq!(setting(&config, "database_url").1, source);
+        assert_eq!(setting(&config, "ports.app").1, Source::Default);
+    }
+
+    #[test]
+    fn test_env_overrides_selected_profile() {
+        let (mut env, names) = ConfigLayer::from_env([
+            (
+                "CWRDD_DATABASE_URL".to_string(),
+                "postgres://ci-db:5432/cwrdd_ci".to_string(),
+            ),
+            ("CWRDD_PROFILE".to_string(), "dev".to_string()),
+        ]);
+        let active = ConfigLayer {
+            active_profile: env.active_profile.take(),
+            ..ConfigLayer::default()
+        };
+
+        let mut config = Config::default();
+        let source = |key: &str| Source::Env(names[key].clone());
+        config.apply(env, source).unwrap();
+        config
+            .apply(
+                ConfigLayer {
+                    profile: Some("ci".to_string()),
+                    ..ConfigLayer::default()
+                },
+                |_| Source::Flag("--profile".to_string()),
+            )
+            .unwrap();
+        config.apply(active, source).unwrap();
+
+        assert_eq!(config.profile_name, "ci");
+        assert_eq!(config.profile().unwrap().database().unwrap().host, "ci-db");
+        assert_eq!(
+            setting(&config, "database_url").1,
+            Source::Env("CWRDD_DATABASE_URL".to_string())
+        );
+        assert_eq!(config.profiles["dev"], Profile::dev());
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let config = Config {
+            profile_name: "staging".to_string(),
+            ..Config::default()
+        };
+        let err = config.profile().unwrap_err().to_string();
+        assert!(err.contains("Unknown profile 'staging'"));
+        assert!(err.contains("ci, dev, test"));
+    }
+
+    #[test]
+    fn test_invalid_profile_settings() {
+        let mut config = Config::default();
+        let mut changes = toml::Table::new();
+        changes.insert("databse_url".to_string(), "x".into());
+        let layer = ConfigLayer {
+            profiles: Some(BTreeMap::from([("dev".to_string(), changes)])),
+            ..ConfigLayer::default()
+        };
+        assert!(config.apply(layer, |_| Source::Default).is_err());
+    }
 }
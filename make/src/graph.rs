@@ -0,0 +1,322 @@
+//! Dependency-graph scheduling for [`Task`]s.
+//!
+//! A [`TaskGraph`] runs a set of tasks concurrently while respecting the
+//! dependencies each task declares with [`Task::depends_on`]. Independent
+//! branches run in parallel; when a task fails, only the tasks downstream of it
+//! are skipped and the rest of the graph carries on.
+
+use crate::task::Task;
+use anyhow::{bail, Result};
+use std::collections::{HashMap, VecDeque};
+use tokio::task::JoinSet;
+
+/// Outcome of a single task in a graph run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskState {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+/// A set of tasks with dependencies between them.
+///
+/// Tasks are identified by their name, so names must be unique within a graph.
+///
+/// # Example
+///
+/// ```no_run
+/// use cwrdd_make::graph::TaskGraph;
+/// use cwrdd_make::task::Task;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// TaskGraph::new()
+///     .task(Task::new("cargo build", "cargo").args(["build"]))
+///     .task(Task::new("podman build", "podman").args(["build", "."]))
+///     .task(
+///         Task::new("compose up", "podman-compose")
+///             .args(["up", "-d"])
+///             .depends_on(["cargo build", "podman build"]),
+///     )
+///     .run()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct TaskGraph {
+    tasks: Vec<Task>,
+}
+
+impl TaskGraph {
+    /// Create an empty graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a task to the graph
+    pub fn task(mut self, task: Task) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    /// Add a task to the graph in place
+    pub fn push(&mut self, task: Task) {
+        self.tasks.push(task);
+    }
+
+    /// Whether the graph has no tasks
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Check that task names are unique, every dependency exists and there are no cycles
+    pub fn validate(&self) -> Result<()> {
+        self.resolve().map(|_| ())
+    }
+
+    /// Resolve each task's dependencies to indices and compute a topological order,
+    /// validating the graph on the way
+    fn resolve(&self) -> Result<(Vec<Vec<usize>>, Vec<usize>)> {
+        let mut index = HashMap::new();
+        for (i, task) in self.tasks.iter().enumerate() {
+            if index.insert(task.name(), i).is_some() {
+                bail!("Duplicate task name in graph: '{}'", task.name());
+            }
+        }
+
+        let mut deps = Vec::with_capacity(self.tasks.len());
+        for task in &self.tasks {
+            let mut task_deps = Vec::new();
+            for dep in task.dependencies() {
+                match index.get(dep.as_str()) {
+                    Some(&i) => task_deps.push(i),
+                    None => bail!("Task '{}' depends on unknown task '{}'", task.name(), dep),
+                }
+            }
+            deps.push(task_deps);
+        }
+
+        // Kahn's algorithm: anything left unvisited is part of a cycle
+        let mut in_degree: Vec<usize> = deps.iter().map(|d| d.len()).collect();
+        let mut queue: VecDeque<usize> = (0..deps.len()).filter(|&i| in_degree[i] == 0).collect();
+        let mut order = Vec::with_capacity(deps.len());
+        while let Some(i) = queue.pop_front() {
+            order.push(i);
+            for (j, task_deps) in deps.iter().enumerate() {
+                for _ in task_deps.iter().filter(|&&d| d == i) {
+                    in_degree[j] -= 1;
+                    if in_degree[j] == 0 {
+                        queue.push_back(j);
+                    }
+                }
+            }
+        }
+
+        if order.len() != deps.len() {
+            let cyclic: Vec<_> = in_degree
+                .iter()
+                .enumerate()
+                .filter(|(_, &d)| d > 0)
+                .map(|(i, _)| format!("'{}'", self.tasks[i].name()))
+                .collect();
+            bail!("Dependency cycle between tasks: {}", cyclic.join(", "));
+        }
+
+        Ok((deps, order))
+    }
+
+    /// Run every task in the graph, starting each one as soon as its dependencies succeed.
+    ///
+    /// Returns an error naming the failed and skipped tasks if anything failed.
+    pub async fn run(self) -> Result<()> {
+        let (deps, order) = self.resolve()?;
+
+        // A dry run records the plan in a stable, dependency-respecting order
+        if self.tasks.iter().all(Task::is_dry_run) {
+            for &i in &order {
+                self.tasks[i].execute().await?;
+            }
+            return Ok(());
+        }
+
+        let names: Vec<String> = self.tasks.iter().map(|t| t.name().to_string()).collect();
+        let mut tasks: Vec<Option<Task>> = self.tasks.into_iter().map(Some).collect();
+        let mut state = vec![TaskState::Pending; tasks.len()];
+        let mut running = JoinSet::new();
+
+        loop {
+            // Skip anything downstream of a failure, then start whatever is ready.
+            // Walking in topological order lets skips cascade in a single pass.
+            for &i in &order {
+                if state[i] != TaskState::Pending {
+                    continue;
+                }
+                if let Some(&blocker) = deps[i]
+                    .iter()
+                    .find(|&&d| matches!(state[d], TaskState::Failed | TaskState::Skipped))
+                {
+                    crate::say!(
+                        "⏭️  Skipping '{}': dependency '{}' did not complete\n",
+                        names[i], names[blocker]
+                    );
+                    state[i] = TaskState::Skipped;
+                    tasks[i] = None;
+                } else if deps[i].iter().all(|&d| state[d] == TaskState::Succeeded) {
+                    if let Some(task) = tasks[i].take() {
+                        state[i] = TaskState::Running;
+                        running.spawn(async move { (i, task.execute().await) });
+                    }
+                }
+            }
+
+            let Some(joined) = running.join_next().await else {
+                break;
+            };
+            let (i, result) = joined?;
+            match result {
+                Ok(()) => state[i] = TaskState::Succeeded,
+                Err(e) => {
+                    eprintln!("❌ {}\n", e);
+                    state[i] = TaskState::Failed;
+                }
+            }
+        }
+
+        let with_state = |wanted: TaskState| -> Vec<String> {
+            names
+                .iter()
+                .zip(&state)
+                .filter(|(_, &s)| s == wanted)
+                .map(|(n, _)| format!("'{}'", n))
+                .collect()
+        };
+        let failed = with_state(TaskState::Failed);
+        if !failed.is_empty() {
+            let skipped = with_state(TaskState::Skipped);
+            if skipped.is_empty() {
+                bail!("{} task(s) failed: {}", failed.len(), failed.join(", "));
+            }
+            bail!(
+                "{} task(s) failed: {} (skipped: {})",
+                failed.len(),
+                failed.join(", "),
+                skipped.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn touch(name: &str, path: &std::path::Path) -> Task {
+        Task::new(name, "touch").args([path.to_string_lossy().to_string()])
+    }
+
+    #[tokio::test]
+    async fn test_independent_tasks_run_concurrently() {
+        let start = Instant::now();
+        let result = TaskGraph::new()
+            .task(Task::new("sleep a", "sleep").args(["0.5"]))
+            .task(Task::new("sleep b", "sleep").args(["0.5"]))
+            .run()
+            .await;
+        assert!(result.is_ok());
+        assert!(start.elapsed() < Duration::from_millis(900));
+    }
+
+    #[tokio::test]
+    async fn test_failure_skips_only_downstream_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let downstream = dir.path().join("downstream");
+        let independent = dir.path().join("independent");
+
+        let result = TaskGraph::new()
+            .task(Task::new("fail", "false"))
+            .task(touch("downstream", &downstream).depends_on(["fail"]))
+            .task(touch("independent", &independent))
+            .run()
+            .await;
+
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("'fail'"), "unexpected error: {}", err);
+        assert!(
+            err.contains("skipped: 'downstream'"),
+            "unexpected error: {}",
+            err
+        );
+        assert!(!downstream.exists());
+        assert!(independent.exists());
+    }
+
+    #[tokio::test]
+    async fn test_dependencies_run_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first");
+
+        let result = TaskGraph::new()
+            .task(
+                Task::new("check first", "test")
+                    .args(["-f".to_string(), first.to_string_lossy().to_string()])
+                    .depends_on(["first"]),
+            )
+            .task(touch("first", &first))
+            .run()
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_skips_cascade_through_chains() {
+        let dir = tempfile::tempdir().unwrap();
+        let last = dir.path().join("last");
+
+        let result = TaskGraph::new()
+            .task(touch("last", &last).depends_on(["middle"]))
+            .task(Task::new("middle", "true").depends_on(["fail"]))
+            .task(Task::new("fail", "false"))
+            .run()
+            .await;
+
+        let err = result.unwrap_err().to_string();
+        assert!(
+            err.contains("'middle'") && err.contains("'last'"),
+            "unexpected error: {}",
+            err
+        );
+        assert!(!last.exists());
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_dependency() {
+        let graph = TaskGraph::new().task(Task::new("a", "true").depends_on(["missing"]));
+        let err = graph.validate().unwrap_err().to_string();
+        assert!(err.contains("unknown task 'missing'"));
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicate_names() {
+        let graph = TaskGraph::new()
+            .task(Task::new("a", "true"))
+            .task(Task::new("a", "true"));
+        assert!(graph.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_cycles() {
+        let graph = TaskGraph::new()
+            .task(Task::new("a", "true").depends_on(["b"]))
+            .task(Task::new("b", "true").depends_on(["a"]))
+            .task(Task::new("c", "true"));
+        let err = graph.validate().unwrap_err().to_string();
+        assert!(err.contains("cycle"));
+        assert!(err.contains("'a'") && err.contains("'b'"));
+        assert!(!err.contains("'c'"));
+    }
+}
@@ -10,7 +10,7 @@
 //! - **Testing**: Run tests with cargo-nextest or cargo test
 //! - **Local Development**: Manage Docker/Podman containers for the full stack
 //! - **Database Migrations**: Apply, rollback, and manage database schema changes
-//! - **TLS Certificates**: Generate and trust self-signed certificates for local HTTPS
+//! - **TLS Certificates**: A local CA that issues and trusts certificates for local HTTPS
 //! - **Tool Installation**: Install required development dependencies
 //!
 //! ## Quick Start
@@ -23,7 +23,7 @@
 //! # Start the full development environment
 //! cwrdd-make up
 //!
-//! # Trust the self-signed certificate (optional, requires sudo)
+//! # Trust the local development CA (optional, requires sudo)
 //! cwrdd-make trust-cert
 //!
 //! # View available commands
@@ -35,20 +35,50 @@
 //! The tool is organized into several modules:
 //!
 //! - [`config`]: Configuration loading and management
+//! - [`ca`]: Local development certificate authority for the app's TLS certificate
+//! - [`changelog`]: Native reader for the Liquibase changelog and `databasechangelog`
+//! - [`properties`]: Java properties parser for `liquibase.properties`
+//! - [`profile`]: Environment profiles (dev, test, ci) with connection settings and ports
 //! - [`task`]: Task execution primitives for running shell commands
+//! - [`lint`]: Safety checks for pending changesets (`migrate lint`)
+//! - [`graph`]: Dependency-graph scheduling for running tasks concurrently
+//! - [`output`]: Human-readable and JSON (`--output json`) progress reporting
+//! - [`plan`]: Execution plans recorded by `--dry-run`
+//! - [`probe`]: Native readiness probes for PostgreSQL, Redis and the app
+//! - [`retry`]: Retry and backoff policies for tasks
+//! - [`services`]: Choosing compose services with `--only`, `--without` and groups
+//! - [`shutdown`]: Global cancellation and Ctrl-C handling for running tasks
 //! - [`tasks`]: Individual task implementations (build, test, compose, etc.)
 //!
 //! ## Configuration
 //!
-//! `cwrdd-make` looks for a `cwrdd-make-config.toml` file next to the binary.
-//! If not found, it uses sensible defaults pointing to `~/Projects/cwrdd`.
+//! `cwrdd-make` layers settings from defaults, config files, `CWRDD_*`
+//! environment variables and command-line flags; see [`config`] for the order.
+//! `cwrdd-make config show` prints the result. Without any configuration it
+//! uses the repository containing the current directory, or `~/Projects/cwrdd`.
 //!
 //! Example configuration:
 //!
 //! ```toml
 //! repo_path = "/home/user/Projects/cwrdd"
+//! profile = "test"
+//!
+//! [profiles.test]
+//! database_password = "hunter2"
 //! ```
 
+pub mod ca;
+pub mod changelog;
 pub mod config;
+pub mod lint;
+pub mod graph;
+pub mod output;
+pub mod plan;
+pub mod probe;
+pub mod profile;
+pub mod properties;
+pub mod retry;
+pub mod services;
+pub mod shutdown;
 pub mod task;
 pub mod tasks;
@@ -0,0 +1,769 @@
+//! Safety checks for changesets before they reach a database with live traffic.
+//!
+//! [`lint`] inspects XML changesets (Liquibase change elements and `<sql>`
+//! blocks) and formatted SQL changesets for operations that lock or rewrite
+//! tables, or can't be undone:
+//!
+//! | Rule | Flags | Default |
+//! |------|-------|---------|
+//! | `create-index-not-concurrently` | `CREATE INDEX` on an existing table without `CONCURRENTLY` | warning |
+//! | `not-null-without-default` | adding a `NOT NULL` column or constraint to an existing table without a default | error |
+//! | `column-type-change` | changing a column's type, which can rewrite the table | warning |
+//! | `drop-referenced-column` | dropping a column that `db/schema/` still defines | error |
+//! | `missing-rollback` | a changeset with no rollback that Liquibase can't roll back automatically | warning |
+//!
+//! Operations on a table created in the same changeset are ignored, since the
+//! table is still empty and nothing else can be using it. Severities can be
+//! changed, or a rule turned off, under `[lint]` in any config file.
+
+use crate::changelog::ChangeSet;
+use anyhow::{bail, Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+/// How seriously to treat a rule's findings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Not checked
+    Off,
+    /// Reported, but only fails `migrate lint --strict`
+    #[serde(alias = "warn")]
+    Warning,
+    /// Fails `migrate lint`
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Off => write!(f, "off"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A lint rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    CreateIndexNotConcurrently,
+    NotNullWithoutDefault,
+    ColumnTypeChange,
+    DropReferencedColumn,
+    MissingRollback,
+}
+
+impl Rule {
+    /// Every rule, in the order they are documented
+    pub const ALL: [Rule; 5] = [
+        Rule::CreateIndexNotConcurrently,
+        Rule::NotNullWithoutDefault,
+        Rule::ColumnTypeChange,
+        Rule::DropReferencedColumn,
+        Rule::MissingRollback,
+    ];
+
+    /// Name used in config files and output
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::CreateIndexNotConcurrently => "create-index-not-concurrently",
+            Rule::NotNullWithoutDefault => "not-null-without-default",
+            Rule::ColumnTypeChange => "column-type-change",
+            Rule::DropReferencedColumn => "drop-referenced-column",
+            Rule::MissingRollback => "missing-rollback",
+        }
+    }
+
+    /// Severity unless configured otherwise
+    pub fn default_severity(self) -> Severity {
+        match self {
+            Rule::NotNullWithoutDefault | Rule::DropReferencedColumn => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+
+    /// Look a rule up by name
+    pub fn from_name(name: &str) -> Result<Rule> {
+        match Rule::ALL.into_iter().find(|rule| rule.name() == name) {
+            Some(rule) => Ok(rule),
+            None => bail!(
+                "Unknown lint rule '{}'. Available rules: {}",
+                name,
+                Rule::ALL.map(Rule::name).join(", ")
+            ),
+        }
+    }
+}
+
+/// One problem found in a changeset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// Changeset in `filename::id::author` notation
+    pub changeset: String,
+    pub message: String,
+}
+
+/// Columns of each table defined in `db/schema/`, lower-cased
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaColumns(BTreeMap<String, BTreeSet<String>>);
+
+impl SchemaColumns {
+    /// Read the `CREATE TABLE` statements in every `.sql` file in `dir`
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut columns = SchemaColumns::default();
+        if !dir.exists() {
+            return Ok(columns);
+        }
+        for entry in std::fs::read_dir(dir).context(format!("Failed to read {}", dir.display()))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "sql") {
+                let sql = std::fs::read_to_string(&path)
+                    .context(format!("Failed to read {}", path.display()))?;
+                columns.add_sql(&sql);
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Add the tables created by `sql`
+    pub fn add_sql(&mut self, sql: &str) {
+        for statement in statements(sql) {
+            let words = words(&statement);
+            let Some(table) = created_table(&words) else {
+                continue;
+            };
+            let (Some(open), Some(close)) = (statement.find('('), statement.rfind(')')) else {
+                continue;
+            };
+            let columns = self.0.entry(table).or_default();
+            for item in split_top_level(&statement[open + 1..close]) {
+                let name = item.split_whitespace().next().unwrap_or_default();
+                let name = unquote(&name.to_lowercase());
+                let is_constraint = matches!(
+                    name.as_str(),
+                    "constraint" | "primary" | "unique" | "check" | "foreign" | "exclude" | "like"
+                );
+                if !name.is_empty() && !is_constraint {
+                    columns.insert(name);
+                }
+            }
+        }
+    }
+
+    fn contains(&self, table: &str, column: &str) -> bool {
+        self.0
+            .get(&unqualified(table))
+            .is_some_and(|columns| columns.contains(&column.to_lowercase()))
+    }
+}
+
+/// Check `changesets` against every rule that isn't turned off.
+///
+/// `severities` overrides [`Rule::default_severity`] by rule name.
+pub fn lint(
+    changesets: &[ChangeSet],
+    schema: &SchemaColumns,
+    severities: &BTreeMap<String, Severity>,
+) -> Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    for changeset in changesets {
+        let key = changeset.key();
+        let mut report = |rule: Rule, message: String| {
+            let severity = severities
+                .get(rule.name())
+                .copied()
+                .unwrap_or(rule.default_severity());
+            if severity != Severity::Off {
+                findings.push(Finding {
+                    rule: rule.name(),
+                    severity,
+                    changeset: key.clone(),
+                    message,
+                });
+            }
+        };
+
+        let problems = if changeset.filename.ends_with(".sql") {
+            check_sql_changeset(&changeset.source, schema)
+        } else {
+            check_xml_changeset(&changeset.source, schema)
+                .context(format!("Failed to lint {}", key))?
+        };
+        for (rule, message) in problems {
+            report(rule, message);
+        }
+    }
+    Ok(findings)
+}
+
+/// An element of a changeset, with its attributes, children and text
+#[derive(Debug, Default)]
+struct Node {
+    name: String,
+    attributes: BTreeMap<String, String>,
+    children: Vec<Node>,
+    text: String,
+}
+
+impl Node {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+}
+
+/// Parse an XML fragment into its root element
+fn parse_node(xml: &str) -> Result<Node> {
+    let mut reader = Reader::from_str(xml);
+    let mut stack: Vec<Node> = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => stack.push(node(&e)?),
+            Event::Empty(e) => {
+                let node = node(&e)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => return Ok(node),
+                }
+            }
+            Event::Text(text) => {
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&text.unescape()?);
+                }
+            }
+            Event::CData(text) => {
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&String::from_utf8_lossy(&text));
+                }
+            }
+            Event::End(_) => {
+                let node = stack.pop().context("Unbalanced XML")?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => return Ok(node),
+                }
+            }
+            Event::Eof => bail!("Unexpected end of XML"),
+            _ => {}
+        }
+    }
+}
+
+fn node(element: &BytesStart) -> Result<Node> {
+    let mut attributes = BTreeMap::new();
+    for attr in element.attributes() {
+        let attr = attr?;
+        attributes.insert(
+            String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned(),
+            attr.unescape_value()?.into_owned(),
+        );
+    }
+    Ok(Node {
+        name: String::from_utf8_lossy(element.local_name().as_ref()).into_owned(),
+        attributes,
+        ..Node::default()
+    })
+}
+
+/// Elements of a changeset that aren't changes
+const NOT_CHANGES: &[&str] = &["comment", "preConditions", "validCheckSum", "rollback"];
+
+/// Changes Liquibase can roll back without a `<rollback>` block
+const AUTO_ROLLBACK: &[&str] = &[
+    "addAutoIncrement",
+    "addColumn",
+    "addDefaultValue",
+    "addForeignKeyConstraint",
+    "addLookupTable",
+    "addNotNullConstraint",
+    "addPrimaryKey",
+    "addUniqueConstraint",
+    "createIndex",
+    "createSequence",
+    "createTable",
+    "createView",
+    "dropNotNullConstraint",
+    "renameColumn",
+    "renameSequence",
+    "renameTable",
+    "renameView",
+    "tagDatabase",
+];
+
+fn check_xml_changeset(source: &str, schema: &SchemaColumns) -> Result<Vec<(Rule, String)>> {
+    let changeset = parse_node(source)?;
+    let changes: Vec<&Node> = changeset
+        .children
+        .iter()
+        .filter(|child| !NOT_CHANGES.contains(&child.name.as_str()))
+        .collect();
+
+    // Tables created in this changeset are empty, so changes to them are safe
+    let mut created: BTreeSet<String> = changes
+        .iter()
+        .filter(|change| change.name == "createTable")
+        .filter_map(|change| change.attr("tableName"))
+        .map(unqualified)
+        .collect();
+    for change in changes.iter().filter(|change| change.name == "sql") {
+        for statement in statements(&change.text) {
+            created.extend(created_table(&words(&statement)));
+        }
+    }
+
+    let mut problems = Vec::new();
+    for change in &changes {
+        let table = change.attr("tableName").unwrap_or_default();
+        let is_new = created.contains(&unqualified(table));
+        match change.name.as_str() {
+            "createIndex" if !is_new => problems.push((
+                Rule::CreateIndexNotConcurrently,
+                format!(
+                    "createIndex {} on {} blocks writes while it builds; use <sql> with CREATE INDEX CONCURRENTLY (and runInTransaction=\"false\")",
+                    change.attr("indexName").unwrap_or("(unnamed)"),
+                    table
+                ),
+            )),
+            "addColumn" if !is_new => {
+                for column in change.children.iter().filter(|c| c.name == "column") {
+                    let not_null = column
+                        .children
+                        .iter()
+                        .any(|c| c.name == "constraints" && c.attr("nullable") == Some("false"));
+                    let has_default = column.attributes.keys().any(|k| k.starts_with("defaultValue"));
+                    if not_null && !has_default {
+                        problems.push((
+                            Rule::NotNullWithoutDefault,
+                            format!(
+                                "addColumn {}.{} is NOT NULL without a default, which fails if {} has rows",
+                                table,
+                                column.attr("name").unwrap_or_default(),
+                                table
+                            ),
+                        ));
+                    }
+                }
+            }
+            "addNotNullConstraint" if !is_new && change.attr("defaultNullValue").is_none() => {
+                problems.push((
+                    Rule::NotNullWithoutDefault,
+                    format!(
+                        "addNotNullConstraint on {}.{} has no defaultNullValue, so it fails on existing NULLs and scans {} under an exclusive lock",
+                        table,
+                        change.attr("columnName").unwrap_or_default(),
+                        table
+                    ),
+                ))
+            }
+            "modifyDataType" if !is_new => problems.push((
+                Rule::ColumnTypeChange,
+                format!(
+                    "modifyDataType {}.{} to {} can rewrite {} under an exclusive lock",
+                    table,
+                    change.attr("columnName").unwrap_or_default(),
+                    change.attr("newDataType").unwrap_or_default(),
+                    table
+                ),
+            )),
+            "dropColumn" => {
+                let mut columns: Vec<&str> = change.attr("columnName").into_iter().collect();
+                columns.extend(
+                    change
+                        .children
+                        .iter()
+                        .filter(|c| c.name == "column")
+                        .filter_map(|c| c.attr("name")),
+                );
+                for column in columns {
+                    if schema.contains(table, column) {
+                        problems.push(dropped_column_problem(table, column));
+                    }
+                }
+            }
+            "sql" => problems.extend(check_sql(&change.text, schema, &created)),
+            _ => {}
+        }
+    }
+
+    let has_rollback = changeset
+        .children
+        .iter()
+        .any(|child| child.name == "rollback");
+    if let Some(change) = changes
+        .iter()
+        .find(|change| !AUTO_ROLLBACK.contains(&change.name.as_str()))
+        .filter(|_| !has_rollback)
+    {
+        problems.push((
+            Rule::MissingRollback,
+            format!(
+                "no <rollback> block, and {} can't be rolled back automatically",
+                change.name
+            ),
+        ));
+    }
+    Ok(problems)
+}
+
+fn check_sql_changeset(source: &str, schema: &SchemaColumns) -> Vec<(Rule, String)> {
+    let (rollback, body): (Vec<&str>, Vec<&str>) = source
+        .lines()
+        .skip(1)
+        .partition(|line| line.trim_start().to_lowercase().starts_with("--rollback"));
+    let body = body.join("\n");
+
+    let created: BTreeSet<String> = statements(&body)
+        .iter()
+        .filter_map(|statement| created_table(&words(statement)))
+        .collect();
+    let mut problems = check_sql(&body, schema, &created);
+
+    if rollback.is_empty() && !statements(&body).is_empty() {
+        problems.push((
+            Rule::MissingRollback,
+            "no --rollback lines, and SQL changes can't be rolled back automatically".to_string(),
+        ));
+    }
+    problems
+}
+
+/// Check raw SQL; `created` holds tables created by the same changeset
+fn check_sql(sql: &str, schema: &SchemaColumns, created: &BTreeSet<String>) -> Vec<(Rule, String)> {
+    let mut problems = Vec::new();
+    for statement in statements(sql) {
+        let words = words(&statement);
+        let word = |i: usize| words.get(i).map(String::as_str).unwrap_or_default();
+
+        // CREATE [UNIQUE] INDEX [CONCURRENTLY] ... ON [ONLY] table
+        let index_at = if word(1) == "unique" { 2 } else { 1 };
+        if word(0) == "create" && word(index_at) == "index" {
+            let concurrently = word(index_at + 1) == "concurrently";
+            let on = words.iter().position(|w| w == "on").map(|i| i + 1);
+            let table = on
+                .map(|i| {
+                    if word(i) == "only" {
+                        word(i + 1)
+                    } else {
+                        word(i)
+                    }
+                })
+                .unwrap_or_default();
+            if !concurrently && !created.contains(&unqualified(table)) {
+                problems.push((
+                    Rule::CreateIndexNotConcurrently,
+                    format!(
+                        "CREATE INDEX on {} without CONCURRENTLY blocks writes while it builds",
+                        table
+                    ),
+                ));
+            }
+            continue;
+        }
+
+        // ALTER TABLE [IF EXISTS] [ONLY] table ...
+        if word(0) != "alter" || word(1) != "table" {
+            continue;
+        }
+        let mut at = 2;
+        if word(at) == "if" && word(at + 1) == "exists" {
+            at += 2;
+        }
+        if word(at) == "only" {
+            at += 1;
+        }
+        let table = word(at).to_string();
+        if created.contains(&unqualified(&table)) {
+            continue;
+        }
+
+        for action in split_top_level(&words[at + 1..].join(" ")) {
+            let action: Vec<&str> = action.split_whitespace().collect();
+            let has = |phrase: &[&str]| action.windows(phrase.len()).any(|w| w == phrase);
+            let first = action.first().copied().unwrap_or_default();
+
+            if first == "add"
+                && !has(&["constraint"])
+                && has(&["not", "null"])
+                && !has(&["default"])
+            {
+                problems.push((
+                    Rule::NotNullWithoutDefault,
+                    format!("ADD COLUMN on {} is NOT NULL without a default, which fails if {} has rows", table, table),
+                ));
+            }
+            if first == "alter" && (has(&["type"]) || has(&["set", "data", "type"])) {
+                problems.push((
+                    Rule::ColumnTypeChange,
+                    format!(
+                        "ALTER COLUMN ... TYPE on {} can rewrite the table under an exclusive lock",
+                        table
+                    ),
+                ));
+            }
+            if first == "alter" && has(&["set", "not", "null"]) {
+                problems.push((
+                    Rule::NotNullWithoutDefault,
+                    format!("SET NOT NULL on {} scans the table under an exclusive lock and fails on existing NULLs", table),
+                ));
+            }
+            if first == "drop" && action.get(1) == Some(&"column") {
+                let mut column = action.get(2).copied().unwrap_or_default();
+                if column == "if" {
+                    column = action.get(4).copied().unwrap_or_default();
+                }
+                let column = unquote(column);
+                if schema.contains(&table, &column) {
+                    problems.push(dropped_column_problem(&table, &column));
+                }
+            }
+        }
+    }
+    problems
+}
+
+fn dropped_column_problem(table: &str, column: &str) -> (Rule, String) {
+    (
+        Rule::DropReferencedColumn,
+        format!(
+            "drops {}.{}, which db/schema/ still defines; remove it from the schema first",
+            table, column
+        ),
+    )
+}
+
+/// The table name if `words` is a `CREATE TABLE` statement
+fn created_table(words: &[String]) -> Option<String> {
+    let word = |i: usize| words.get(i).map(String::as_str).unwrap_or_default();
+    let mut at = 1;
+    while matches!(word(at), "temp" | "temporary" | "unlogged") {
+        at += 1;
+    }
+    if word(0) != "create" || word(at) != "table" {
+        return None;
+    }
+    at += 1;
+    if word(at) == "if" && word(at + 1) == "not" && word(at + 2) == "exists" {
+        at += 3;
+    }
+    Some(unqualified(word(at))).filter(|table| !table.is_empty())
+}
+
+/// Split SQL into statements, dropping comments and ignoring `;` inside quotes and `$$` bodies
+fn statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut chars = sql.chars().peekable();
+    let (mut in_string, mut in_dollar) = (false, false);
+
+    while let Some(c) = chars.next() {
+        match c {
+            '-' if !in_string && !in_dollar && chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                current.push('\n');
+            }
+            '\'' if !in_dollar => {
+                in_string = !in_string;
+                current.push(c);
+            }
+            '$' if !in_string && chars.peek() == Some(&'$') => {
+                chars.next();
+                in_dollar = !in_dollar;
+                current.push_str("$$");
+            }
+            ';' if !in_string && !in_dollar => {
+                statements.push(std::mem::take(&mut current));
+            }
+            _ => current.push(c),
+        }
+    }
+    statements.push(current);
+    statements
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Lower-cased words of a statement, with parentheses and commas as separate words
+fn words(statement: &str) -> Vec<String> {
+    statement
+        .to_lowercase()
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .replace(',', " , ")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Split on commas that aren't inside parentheses
+fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    parts.push(current);
+    parts
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+fn unquote(name: &str) -> String {
+    name.trim_matches('"').to_string()
+}
+
+/// Lower-cased table name without its schema
+fn unqualified(table: &str) -> String {
+    let name = table.rsplit('.').next().unwrap_or(table);
+    unquote(&name.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "CREATE TABLE users (\n    id UUID PRIMARY KEY, -- the id\n    email VARCHAR(255) NOT NULL,\n    \"nickname\" TEXT,\n    CONSTRAINT users_email_check CHECK (email ~* '^.+@.+$')\n);\nCREATE INDEX idx_users_email ON users(email);\n";
+
+    fn changeset(filename: &str, source: &str) -> ChangeSet {
+        ChangeSet {
+            id: "1".to_string(),
+            author: "andy".to_string(),
+            filename: filename.to_string(),
+            checksum: String::new(),
+            source: source.to_string(),
+        }
+    }
+
+    fn rules(source: &str) -> Vec<&'static str> {
+        let mut schema = SchemaColumns::default();
+        schema.add_sql(SCHEMA);
+        let changesets = [changeset("migrations/1.xml", source)];
+        lint(&changesets, &schema, &BTreeMap::new())
+            .unwrap()
+            .into_iter()
+            .map(|finding| finding.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_schema_columns() {
+        let mut schema = SchemaColumns::default();
+        schema.add_sql(SCHEMA);
+        assert!(schema.contains("users", "email"));
+        assert!(schema.contains("public.users", "nickname"));
+        assert!(!schema.contains("users", "constraint"));
+        assert!(!schema.contains("users", "phone"));
+    }
+
+    #[test]
+    fn test_safe_changeset_has_no_findings() {
+        let source = r#"<changeSet id="1" author="andy">
+            <createTable tableName="orgs"><column name="id" type="uuid"/></createTable>
+            <createIndex tableName="orgs" indexName="idx_orgs_id"><column name="id"/></createIndex>
+            <addColumn tableName="users">
+                <column name="org_id" type="uuid"/>
+                <column name="active" type="boolean" defaultValueBoolean="true">
+                    <constraints nullable="false"/>
+                </column>
+            </addColumn>
+        </changeSet>"#;
+        assert!(rules(source).is_empty());
+    }
+
+    #[test]
+    fn test_xml_changes_are_flagged() {
+        let source = r#"<changeSet id="1" author="andy">
+            <createIndex tableName="users" indexName="idx_users_name"><column name="name"/></createIndex>
+            <addColumn tableName="users">
+                <column name="age" type="int"><constraints nullable="false"/></column>
+            </addColumn>
+            <modifyDataType tableName="users" columnName="email" newDataType="text"/>
+            <dropColumn tableName="users" columnName="nickname"/>
+        </changeSet>"#;
+        assert_eq!(
+            rules(source),
+            [
+                "create-index-not-concurrently",
+                "not-null-without-default",
+                "column-type-change",
+                "drop-referenced-column",
+                "missing-rollback",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sql_in_xml_is_checked() {
+        let source = r#"<changeSet id="1" author="andy" runInTransaction="false">
+            <sql>CREATE INDEX CONCURRENTLY idx_users_name ON users (name);</sql>
+            <sql><![CDATA[ALTER TABLE users ALTER COLUMN email TYPE text, DROP COLUMN IF EXISTS phone;]]></sql>
+            <rollback><sql>DROP INDEX idx_users_name;</sql></rollback>
+        </changeSet>"#;
+        assert_eq!(rules(source), ["column-type-change"]);
+    }
+
+    #[test]
+    fn test_formatted_sql_changeset() {
+        let mut schema = SchemaColumns::default();
+        schema.add_sql(SCHEMA);
+        let source = "--changeset andy:1\n\
+            CREATE INDEX idx_users_name ON ONLY users (name); -- not concurrent\n\
+            ALTER TABLE users ADD COLUMN age int NOT NULL;\n\
+            ALTER TABLE public.users DROP COLUMN \"email\";\n";
+        let findings = lint(
+            &[changeset("migrations/1.sql", source)],
+            &schema,
+            &BTreeMap::from([("missing-rollback".to_string(), Severity::Off)]),
+        )
+        .unwrap();
+        let rules: Vec<_> = findings.iter().map(|f| (f.rule, f.severity)).collect();
+        assert_eq!(
+            rules,
+            [
+                ("create-index-not-concurrently", Severity::Warning),
+                ("not-null-without-default", Severity::Error),
+                ("drop-referenced-column", Severity::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_statements_ignore_semicolons_in_bodies_and_comments() {
+        let sql = "CREATE FUNCTION f() RETURNS trigger AS $$ BEGIN NEW.a = 'x;y'; END; $$ LANGUAGE plpgsql;\n-- a; comment\nSELECT 1";
+        assert_eq!(statements(sql).len(), 2);
+    }
+
+    #[test]
+    fn test_rule_from_name() {
+        assert_eq!(
+            Rule::from_name("missing-rollback").unwrap(),
+            Rule::MissingRollback
+        );
+        let err = Rule::from_name("nope").unwrap_err();
+        assert!(err.to_string().contains("create-index-not-concurrently"));
+    }
+}
@@ -1,14 +1,32 @@
-mod config;
-mod task;
-mod tasks;
-
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use cwrdd_make::config::ConfigLayer;
+use cwrdd_make::output::{self, OutputFormat};
+use cwrdd_make::plan::Plan;
+use cwrdd_make::services::Selection;
+use cwrdd_make::{config, say, shutdown, tasks};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "cwrdd-make")]
 #[command(about = "Build tool for cwrdd development", long_about = None)]
 struct Cli {
+    /// Print every command that would run, without running anything
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Output format: emoji-decorated text, or one JSON event per line
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    output: OutputFormat,
+
+    /// Path to the cwrdd repository, overriding config files and CWRDD_REPO_PATH
+    #[arg(long, global = true)]
+    repo_path: Option<PathBuf>,
+
+    /// Environment profile (dev, test, ci or one from a config file)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -24,67 +42,302 @@ enum Commands {
         /// Force recreate containers even if already running
         #[arg(long)]
         recreate: bool,
+        #[command(flatten)]
+        services: Selection,
     },
     /// Stop local development environment
-    Down,
+    Down {
+        #[command(flatten)]
+        services: Selection,
+    },
+    /// Show the state, health, uptime and ports of every service
+    Status {
+        /// Keep refreshing until interrupted
+        #[arg(short, long)]
+        watch: bool,
+    },
     /// Show logs from development environment
     Logs {
-        /// Specific service to show logs for
-        service: Option<String>,
+        /// Services or groups to show logs for; all of them by default
+        service: Vec<String>,
         /// Follow log output
         #[arg(short, long)]
         follow: bool,
+        #[command(flatten)]
+        services: Selection,
     },
     /// Generate migration from schema diff
     MigrateDiff,
+    /// Create an empty changeset and include it in the changelog
+    MigrateNew {
+        /// Short description, used in the file name, e.g. "add organizations"
+        name: String,
+    },
+    /// Check that every migration file is included in the changelog
+    MigrateCheck,
     /// Apply pending migrations
-    Migrate,
+    Migrate {
+        #[command(subcommand)]
+        action: Option<MigrateAction>,
+    },
     /// Show migration status
     MigrateStatus,
-    /// Rollback last migration
-    Rollback,
+    /// Roll back migrations (the most recent changeset by default)
+    Rollback {
+        /// Number of changesets to roll back
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        count: u32,
+        /// Roll back everything applied after this tag (see `migrate tag`)
+        #[arg(long, conflicts_with_all = ["count", "to_date"])]
+        to_tag: Option<String>,
+        /// Roll back everything applied after this time, e.g. 2026-10-17 or "2026-10-17 09:30"
+        #[arg(long, conflicts_with = "count")]
+        to_date: Option<String>,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Snapshot and restore the local database
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
     /// Seed database with development data
-    Seed,
+    Seed {
+        /// Fixture set to load from db/seeds/ (repeatable); the profile's seed_sets by default
+        #[arg(long)]
+        set: Vec<String>,
+        /// Generate fake data, e.g. users=10000
+        #[arg(long, value_name = "KIND=COUNT")]
+        generate: Vec<String>,
+        /// RNG seed for generated data, to reproduce an earlier run
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Show which seed sets have been loaded instead of seeding
+        #[arg(long, conflicts_with_all = ["set", "generate", "seed"])]
+        status: bool,
+    },
     /// Install cwrdd-make to user's PATH
     Install,
     /// Install development tools (Podman, Liquibase, etc.)
     GetTools,
-    /// Trust the self-signed dev certificate locally
+    /// Trust the local development CA
     TrustCert,
-    /// Remove trust for the self-signed dev certificate
+    /// Remove trust for the local development CA
     UntrustCert,
+    /// Inspect the dev TLS certificate
+    Cert {
+        #[command(subcommand)]
+        action: CertAction,
+    },
     /// Build and open rustdoc documentation
     Doc {
         /// Open documentation in browser after building
         #[arg(long, short)]
         open: bool,
     },
+    /// Check tools, ports, certificates and the repository for problems
+    Doctor {
+        /// Exit with an error on warnings too, not just failures
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Inspect cwrdd-make's configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum MigrateAction {
+    /// Check pending changesets for locking, data-loss and rollback problems
+    Lint {
+        /// Lint every changeset, not just pending ones (no database needed)
+        #[arg(long)]
+        all: bool,
+        /// Exit with an error on warnings too, not just errors
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Tag the current database state so it can be rolled back to
+    Tag {
+        /// Tag name, e.g. v1.2
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Save the database so it can be restored later
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Snapshot {
+        /// Snapshot name, e.g. before-orgs
+        #[arg(required = true)]
+        name: Option<String>,
+        #[command(subcommand)]
+        action: Option<SnapshotAction>,
+    },
+    /// Replace the database with a snapshot
+    Restore {
+        /// Snapshot name
+        name: String,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// List snapshots
+    Snapshots,
+    /// Drop and recreate the database, then migrate and seed it
+    Reset {
+        /// Leave the database empty apart from migrations
+        #[arg(long)]
+        no_seed: bool,
+        /// Only apply migrations up to this <tagDatabase> tag
+        #[arg(long)]
+        to_tag: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotAction {
+    /// Delete a snapshot
+    Rm {
+        /// Snapshot name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CertAction {
+    /// Print the subject, SANs, fingerprint, expiry and trust status
+    Info,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print each effective setting and where it came from
+    Show,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    let command_name = matches.subcommand_name().unwrap_or_default().to_string();
+    output::set_format(cli.output);
+
+    // Forward Ctrl-C / SIGTERM to running tasks instead of orphaning them
+    shutdown::listen_for_signals()?;
+
+    let result = run(cli).await;
+    output::finish(&command_name, &result);
 
-    // Load configuration
-    let config = config::Config::load()?;
+    if let Err(e) = result {
+        if shutdown::is_cancelled() {
+            eprintln!("Error: {:?}", e);
+            std::process::exit(shutdown::INTERRUPTED_EXIT_CODE);
+        }
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// Load configuration and execute the selected command
+async fn run(cli: Cli) -> Result<()> {
+    let flags = ConfigLayer {
+        repo_path: cli.repo_path,
+        profile: cli.profile,
+        ..ConfigLayer::default()
+    };
+    let mut config = config::Config::load_with(flags)?;
+    if cli.dry_run {
+        say!("🔍 Dry run: commands are printed, not executed\n");
+        config.dry_run = Some(Plan::new());
+    }
+    let config = &config;
 
-    // Execute command
     match cli.command {
-        Commands::Build => tasks::build::run(&config).await?,
-        Commands::Test => tasks::test::run(&config).await?,
-        Commands::Up { recreate } => tasks::compose::up(&config, recreate).await?,
-        Commands::Down => tasks::compose::down(&config).await?,
-        Commands::Logs { service, follow } => tasks::compose::logs(&config, service, follow).await?,
-        Commands::MigrateDiff => tasks::migrate::diff(&config).await?,
-        Commands::Migrate => tasks::migrate::apply(&config).await?,
-        Commands::MigrateStatus => tasks::migrate::status(&config).await?,
-        Commands::Rollback => tasks::migrate::rollback(&config).await?,
-        Commands::Seed => tasks::migrate::seed(&config).await?,
-        Commands::Install => tasks::install::run(&config).await?,
-        Commands::GetTools => tasks::get_tools::run(&config).await?,
-        Commands::TrustCert => tasks::certs::trust(&config).await?,
-        Commands::UntrustCert => tasks::certs::untrust(&config).await?,
-        Commands::Doc { open } => tasks::doc::run(&config, open).await?,
+        Commands::Build => tasks::build::run(config).await?,
+        Commands::Test => tasks::test::run(config).await?,
+        Commands::Up { recreate, services } => {
+            tasks::compose::up(config, recreate, &services).await?
+        }
+        Commands::Down { services } => tasks::compose::down(config, &services).await?,
+        Commands::Status { watch } => tasks::status::run(config, watch).await?,
+        Commands::Logs {
+            service,
+            follow,
+            services,
+        } => tasks::compose::logs(config, service, &services, follow).await?,
+        Commands::MigrateDiff => tasks::migrate::diff(config).await?,
+        Commands::MigrateNew { name } => tasks::migrate::new(config, &name).await?,
+        Commands::MigrateCheck => tasks::migrate::check(config).await?,
+        Commands::Migrate { action } => match action {
+            None => tasks::migrate::apply(config).await?,
+            Some(MigrateAction::Lint { all, strict }) => {
+                tasks::migrate::lint(config, all, strict).await?
+            }
+            Some(MigrateAction::Tag { name }) => tasks::migrate::tag(config, &name).await?,
+        },
+        Commands::MigrateStatus => tasks::migrate::status(config).await?,
+        Commands::Rollback {
+            count,
+            to_tag,
+            to_date,
+            yes,
+        } => {
+            use tasks::migrate::{parse_rollback_date, RollbackTarget};
+            let target = match (to_tag, to_date) {
+                (Some(tag), _) => RollbackTarget::Tag(tag),
+                (_, Some(date)) => RollbackTarget::Date(parse_rollback_date(&date)?),
+                _ => RollbackTarget::Count(count),
+            };
+            tasks::migrate::rollback(config, target, yes).await?
+        }
+        Commands::Db { action } => match action {
+            DbAction::Snapshot {
+                action: Some(SnapshotAction::Rm { name }),
+                ..
+            } => tasks::snapshot::remove(config, &name).await?,
+            DbAction::Snapshot { name, .. } => {
+                let name = name.expect("clap requires a name without a subcommand");
+                tasks::snapshot::create(config, &name).await?
+            }
+            DbAction::Restore { name, yes } => tasks::snapshot::restore(config, &name, yes).await?,
+            DbAction::Snapshots => tasks::snapshot::list(config).await?,
+            DbAction::Reset { no_seed, to_tag } => {
+                tasks::reset::run(config, !no_seed, to_tag.as_deref()).await?
+            }
+        },
+        Commands::Seed { status: true, .. } => tasks::seed::status(config).await?,
+        Commands::Seed {
+            set,
+            generate,
+            seed,
+            ..
+        } => {
+            use tasks::seed::{Generate, SeedOptions};
+            let options = SeedOptions {
+                sets: set,
+                generate: generate.iter().map(|g| Generate::parse(g)).collect::<Result<_>>()?,
+                seed,
+            };
+            tasks::seed::run(config, &options).await?
+        }
+        Commands::Install => tasks::install::run(config).await?,
+        Commands::GetTools => tasks::get_tools::run(config).await?,
+        Commands::TrustCert => tasks::certs::trust(config).await?,
+        Commands::UntrustCert => tasks::certs::untrust(config).await?,
+        Commands::Cert { action } => match action {
+            CertAction::Info => tasks::certs::info(config).await?,
+        },
+        Commands::Doc { open } => tasks::doc::run(config, open).await?,
+        Commands::Doctor { strict } => tasks::doctor::run(config, strict).await?,
+        Commands::Config { action } => match action {
+            ConfigAction::Show => tasks::config::show(config).await?,
+        },
     }
 
     Ok(())
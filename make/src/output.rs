@@ -0,0 +1,285 @@
+//! Human-readable and machine-readable output for cwrdd-make.
+//!
+//! By default cwrdd-make prints emoji-decorated text. With `--output json` it
+//! instead writes one JSON object per line to stdout, so scripts can follow
+//! along:
+//!
+//! - `message`: a human-readable progress message
+//! - `task_started` / `task_finished`: a child process starting and exiting
+//! - `line`: one line of a task's output, with the stream it came from
+//! - `planned`: a command recorded by `--dry-run`
+//! - `update`: data a long-running command refreshes, e.g. `status --watch`
+//! - `result`: the final outcome of the command, with any data it reported
+//!
+//! Messages are printed with the [`say!`](crate::say) macro, which respects the
+//! selected format.
+
+use crate::plan::PlannedCommand;
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// How cwrdd-make reports progress
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Emoji-decorated text for people
+    #[default]
+    Human,
+    /// One JSON event per line on stdout
+    Json,
+}
+
+/// Which output stream a line came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn as_str(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+static RESULT_DATA: Mutex<Vec<(String, Value)>> = Mutex::new(Vec::new());
+
+/// Select the output format. Only the first call has any effect.
+pub fn set_format(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// The selected output format
+pub fn format() -> OutputFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Whether JSON output is selected
+pub fn is_json() -> bool {
+    format() == OutputFormat::Json
+}
+
+/// Print a human-readable message, or emit it as a `message` event
+pub fn message(text: String) {
+    if !is_json() {
+        println!("{}", text);
+        return;
+    }
+    let text = text.trim();
+    if !text.is_empty() {
+        emit(json!({ "event": "message", "text": text }));
+    }
+}
+
+/// Report that a task is starting
+pub fn task_started(name: &str, command: &str, args: &[String], working_dir: Option<&str>) {
+    if is_json() {
+        emit(task_started_event(name, command, args, working_dir));
+        return;
+    }
+    println!("🔧 Running: {}", name);
+    println!("   Command: {} {}", command, args.join(" "));
+    if let Some(dir) = working_dir {
+        println!("   Working directory: {}", dir);
+    }
+}
+
+/// Report a line of task output
+pub fn task_line(name: &str, stream: Stream, line: &str) {
+    if is_json() {
+        emit(json!({ "event": "line", "task": name, "stream": stream.as_str(), "line": line }));
+        return;
+    }
+    match stream {
+        Stream::Stdout => println!("   {}", line),
+        Stream::Stderr => eprintln!("   {}", line),
+    }
+}
+
+/// Report that a task has finished. Human output is left to the caller.
+pub fn task_finished(name: &str, exit_code: Option<i32>, duration: Duration, attempts: u32) {
+    if is_json() {
+        emit(task_finished_event(name, exit_code, duration, attempts));
+    }
+}
+
+/// Report a command recorded by `--dry-run`
+pub fn planned(step: &PlannedCommand) {
+    if is_json() {
+        let mut event = json!({ "event": "planned" });
+        if let (Value::Object(event), Value::Object(step)) = (&mut event, json!(step)) {
+            event.extend(step);
+        }
+        emit(event);
+        return;
+    }
+    step.print();
+}
+
+/// Ask a yes/no question on the terminal, defaulting to no.
+///
+/// Fails without asking if stdin isn't a terminal or JSON output is selected,
+/// since nobody can answer; callers should offer a `--yes` flag for scripts.
+pub async fn confirm(question: &str) -> anyhow::Result<bool> {
+    use std::io::IsTerminal;
+
+    if is_json() || !std::io::stdin().is_terminal() {
+        anyhow::bail!("Cannot ask for confirmation without a terminal; pass --yes to proceed");
+    }
+
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let answer = tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).map(|_| line)
+    });
+
+    tokio::select! {
+        answer = answer => Ok(matches!(answer??.trim().to_lowercase().as_str(), "y" | "yes")),
+        _ = crate::shutdown::token().cancelled() => anyhow::bail!("Cancelled"),
+    }
+}
+
+/// Report the latest value of data that keeps changing (JSON output only),
+/// e.g. each refresh of `status --watch`
+pub fn update(key: &str, value: impl Into<Value>) {
+    if is_json() {
+        emit(update_event(key, value.into()));
+    }
+}
+
+/// Attach a value to the final `result` event, e.g. the services `up` started
+pub fn record(key: &str, value: impl Into<Value>) {
+    RESULT_DATA
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((key.to_string(), value.into()));
+}
+
+/// Emit the final `result` event for `command` (JSON output only)
+pub fn finish(command: &str, result: &anyhow::Result<()>) {
+    if !is_json() {
+        return;
+    }
+    let data = std::mem::take(&mut *RESULT_DATA.lock().unwrap_or_else(|e| e.into_inner()));
+    emit(result_event(command, result, data.into_iter().collect()));
+}
+
+fn task_started_event(
+    name: &str,
+    command: &str,
+    args: &[String],
+    working_dir: Option<&str>,
+) -> Value {
+    let step = PlannedCommand::new(name, command, args, working_dir, &[], &[]);
+    json!({
+        "event": "task_started",
+        "task": name,
+        "command": command,
+        "args": step.args,
+        "working_dir": working_dir,
+    })
+}
+
+fn task_finished_event(
+    name: &str,
+    exit_code: Option<i32>,
+    duration: Duration,
+    attempts: u32,
+) -> Value {
+    json!({
+        "event": "task_finished",
+        "task": name,
+        "success": exit_code == Some(0),
+        "exit_code": exit_code,
+        "duration_ms": duration.as_millis() as u64,
+        "attempts": attempts,
+    })
+}
+
+fn update_event(key: &str, value: Value) -> Value {
+    json!({ "event": "update", "key": key, "value": value })
+}
+
+fn result_event(command: &str, result: &anyhow::Result<()>, data: Map<String, Value>) -> Value {
+    json!({
+        "event": "result",
+        "command": command,
+        "success": result.is_ok(),
+        "error": result.as_ref().err().map(|e| format!("{:#}", e)),
+        "data": data,
+    })
+}
+
+/// Write one event as a line of JSON
+fn emit(event: Value) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", event);
+}
+
+/// Print a message with `format!` syntax, respecting `--output`.
+///
+/// In human mode this is `println!`; in JSON mode it emits a `message` event.
+#[macro_export]
+macro_rules! say {
+    () => {
+        $crate::output::message(String::new())
+    };
+    ($($arg:tt)*) => {
+        $crate::output::message(format!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_events() {
+        let started = task_started_event(
+            "liquibase status",
+            "liquibase",
+            &["--password=secret".to_string(), "status".to_string()],
+            Some("/repo/db"),
+        );
+        assert_eq!(started["event"], "task_started");
+        assert_eq!(started["args"][0], "--password=********");
+        assert_eq!(started["working_dir"], "/repo/db");
+
+        let finished =
+            task_finished_event("liquibase status", Some(1), Duration::from_millis(1500), 2);
+        assert_eq!(finished["success"], false);
+        assert_eq!(finished["exit_code"], 1);
+        assert_eq!(finished["duration_ms"], 1500);
+        assert_eq!(finished["attempts"], 2);
+    }
+
+    #[test]
+    fn test_update_event() {
+        let event = update_event("services", json!([{ "service": "postgres" }]));
+        assert_eq!(event["event"], "update");
+        assert_eq!(event["key"], "services");
+        assert_eq!(event["value"][0]["service"], "postgres");
+    }
+
+    #[test]
+    fn test_result_event() {
+        let mut data = Map::new();
+        data.insert("pending_changesets".to_string(), json!(3));
+
+        let ok = result_event("migrate-status", &Ok(()), data);
+        assert_eq!(ok["success"], true);
+        assert!(ok["error"].is_null());
+        assert_eq!(ok["data"]["pending_changesets"], 3);
+
+        let failed = result_event("up", &Err(anyhow::anyhow!("boom")), Map::new());
+        assert_eq!(failed["success"], false);
+        assert_eq!(failed["error"], "boom");
+    }
+}
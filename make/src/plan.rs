@@ -0,0 +1,175 @@
+//! Execution plans for `--dry-run`.
+//!
+//! In dry-run mode every [`crate::task::Task`] is recorded in a shared [`Plan`]
+//! instead of being spawned. The plan keeps each command's arguments, working
+//! directory and environment in the order they would have run, with secrets
+//! redacted, and can be serialized to JSON.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Placeholder shown in place of secret values
+pub const REDACTED: &str = "********";
+
+/// Substrings that mark an environment variable or `--flag=value` argument as secret
+const SECRET_MARKERS: &[&str] = &["PASSWORD", "SECRET", "TOKEN", "CREDENTIAL", "API_KEY"];
+
+/// A command that would have been run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedCommand {
+    /// Task name
+    pub name: String,
+    /// Program to run
+    pub command: String,
+    /// Arguments, with secret values redacted
+    pub args: Vec<String>,
+    /// Working directory, if set
+    pub working_dir: Option<String>,
+    /// Environment variables, with secret values redacted
+    pub env: BTreeMap<String, String>,
+    /// Names of tasks this one depends on
+    pub depends_on: Vec<String>,
+}
+
+impl PlannedCommand {
+    /// Build a plan entry, redacting secrets from the arguments and environment
+    pub fn new(
+        name: &str,
+        command: &str,
+        args: &[String],
+        working_dir: Option<&str>,
+        env: &[(String, String)],
+        depends_on: &[String],
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            args: args.iter().map(|arg| redact_arg(arg)).collect(),
+            working_dir: working_dir.map(str::to_string),
+            env: env
+                .iter()
+                .map(|(key, value)| {
+                    let value = if is_secret(key) {
+                        REDACTED.to_string()
+                    } else {
+                        value.clone()
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+            depends_on: depends_on.to_vec(),
+        }
+    }
+
+    /// Print the entry in the same shape as a running task's banner
+    pub fn print(&self) {
+        println!("📝 Would run: {}", self.name);
+        println!("   Command: {} {}", self.command, self.args.join(" "));
+        if let Some(ref dir) = self.working_dir {
+            println!("   Working directory: {}", dir);
+        }
+        for (key, value) in &self.env {
+            println!("   Env: {}={}", key, value);
+        }
+        if !self.depends_on.is_empty() {
+            println!("   After: {}", self.depends_on.join(", "));
+        }
+        println!();
+    }
+}
+
+/// The ordered list of commands a dry run would execute.
+///
+/// Cloning a plan shares the underlying list, so every task created from the
+/// same [`crate::config::Config`] records into one place.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    steps: Arc<Mutex<Vec<PlannedCommand>>>,
+}
+
+impl Plan {
+    /// Create an empty plan
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record and print a planned command
+    pub fn record(&self, step: PlannedCommand) {
+        crate::output::planned(&step);
+        self.steps
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(step);
+    }
+
+    /// The commands recorded so far, in order
+    pub fn steps(&self) -> Vec<PlannedCommand> {
+        self.steps
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Serialize the plan as a JSON array of commands
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.steps()).expect("plan is always serializable")
+    }
+}
+
+/// Whether an environment variable or flag name refers to a secret
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_uppercase().replace('-', "_");
+    SECRET_MARKERS.iter().any(|marker| key.contains(marker))
+}
+
+/// Redact the value of `--password=...` style arguments
+fn redact_arg(arg: &str) -> String {
+    match arg.split_once('=') {
+        Some((key, _)) if key.starts_with('-') && is_secret(key) => {
+            format!("{}={}", key, REDACTED)
+        }
+        _ => arg.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_secret_env_and_args() {
+        let step = PlannedCommand::new(
+            "psql",
+            "psql",
+            &["--password=hunter2".to_string(), "-d".to_string()],
+            Some("/tmp"),
+            &[
+                ("PGPASSWORD".to_string(), "hunter2".to_string()),
+                ("PGUSER".to_string(), "cwrdd_user".to_string()),
+            ],
+            &[],
+        );
+        assert_eq!(step.args, ["--password=********", "-d"]);
+        assert_eq!(step.env["PGPASSWORD"], REDACTED);
+        assert_eq!(step.env["PGUSER"], "cwrdd_user");
+    }
+
+    #[test]
+    fn test_plan_json() {
+        let plan = Plan::new();
+        plan.record(PlannedCommand::new(
+            "echo",
+            "echo",
+            &["hi".to_string()],
+            None,
+            &[],
+            &[],
+        ));
+
+        let json: serde_json::Value = serde_json::from_str(&plan.to_json()).unwrap();
+        assert_eq!(json[0]["command"], "echo");
+        assert_eq!(json[0]["args"][0], "hi");
+        assert!(json[0]["working_dir"].is_null());
+    }
+}
@@ -0,0 +1,268 @@
+//! Native readiness probes for the services in the development environment.
+//!
+//! Each probe connects to a service the way a client would, using the host and
+//! port from the selected [`crate::profile::Profile`], so it works regardless
+//! of container names or the container runtime:
+//!
+//! - [`postgres`]: a full startup handshake (including authentication) followed
+//!   by `SELECT 1` against the target database
+//! - [`redis`]: a `PING`, expecting `PONG`
+//! - [`https`]: a `GET /` over TLS, accepting only the dev certificate
+//!
+//! Every probe gives up after [`PROBE_TIMEOUT`]; combine them with a
+//! [`crate::retry::RetryPolicy`] to wait for a service to come up.
+
+use crate::profile::Database;
+use anyhow::{bail, Context, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+/// Upper bound on a single probe, so an unresponsive port doesn't hang
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Check that PostgreSQL accepts connections to `db` and can run a query
+pub async fn postgres(db: &Database) -> Result<()> {
+    with_timeout(async {
+        let client = db.connect().await?;
+
+        let row = client
+            .query_one("SELECT 1", &[])
+            .await
+            .context("PostgreSQL did not answer SELECT 1")?;
+        let one: i32 = row.get(0);
+        if one != 1 {
+            bail!("PostgreSQL answered SELECT 1 with {}", one);
+        }
+
+        Ok(())
+    })
+    .await
+}
+
+/// Check that Redis at `host:port` answers `PING` with `PONG`
+pub async fn redis(host: &str, port: u16) -> Result<()> {
+    with_timeout(async {
+        let stream = TcpStream::connect((host, port))
+            .await
+            .context(format!("Failed to connect to Redis at {}:{}", host, port))?;
+        let mut stream = BufReader::new(stream);
+
+        stream.get_mut().write_all(b"*1\r\n$4\r\nPING\r\n").await?;
+        let mut reply = String::new();
+        stream.read_line(&mut reply).await?;
+
+        check_redis_reply(&reply)
+    })
+    .await
+}
+
+/// Check that an HTTPS server at `host:port` answers `GET /`, returning the status code.
+///
+/// The server must present exactly the certificate in `cert_path`; any response
+/// below 500 counts as ready.
+pub async fn https(host: &str, port: u16, cert_path: &Path) -> Result<u16> {
+    let cert = CertificateDer::from_pem_file(cert_path)
+        .context(format!("Failed to read certificate: {}", cert_path.display()))?;
+    let verifier = PinnedCertVerifier::new(cert.into_owned());
+    let tls = ClientConfig::builder_with_provider(verifier.provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host.to_string())?;
+
+    with_timeout(async {
+        let tcp = TcpStream::connect((host, port))
+            .await
+            .context(format!("Failed to connect to {}:{}", host, port))?;
+        let tls = TlsConnector::from(Arc::new(tls))
+            .connect(server_name, tcp)
+            .await
+            .context("TLS handshake failed")?;
+        let mut stream = BufReader::new(tls);
+
+        let request = format!(
+            "GET / HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: cwrdd-make\r\nConnection: close\r\n\r\n",
+            host, port
+        );
+        stream.get_mut().write_all(request.as_bytes()).await?;
+        let mut status_line = String::new();
+        stream.read_line(&mut status_line).await?;
+
+        let status = parse_status_line(&status_line)?;
+        if status >= 500 {
+            bail!("GET / returned HTTP {}", status);
+        }
+        Ok(status)
+    })
+    .await
+}
+
+/// Run `probe`, failing if it takes longer than [`PROBE_TIMEOUT`]
+async fn with_timeout<T>(probe: impl Future<Output = Result<T>>) -> Result<T> {
+    match tokio::time::timeout(PROBE_TIMEOUT, probe).await {
+        Ok(result) => result,
+        Err(_) => bail!("No answer within {}s", PROBE_TIMEOUT.as_secs()),
+    }
+}
+
+/// Check a RESP reply to `PING`
+fn check_redis_reply(reply: &str) -> Result<()> {
+    match reply.trim_end() {
+        "+PONG" => Ok(()),
+        "" => bail!("Redis closed the connection without replying"),
+        error if error.starts_with('-') => bail!("Redis replied with an error: {}", &error[1..]),
+        other => bail!("Unexpected reply to PING: {}", other),
+    }
+}
+
+/// Extract the status code from an HTTP/1.x status line
+fn parse_status_line(line: &str) -> Result<u16> {
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some(version), Some(code)) if version.starts_with("HTTP/") => code
+            .parse()
+            .context(format!("Invalid HTTP status line: {}", line.trim())),
+        _ => bail!("Invalid HTTP status line: {}", line.trim()),
+    }
+}
+
+/// Accepts exactly one certificate, checking handshake signatures as usual.
+///
+/// Pinning the dev certificate is stricter than trusting the local CA that
+/// issued it, and works whether or not that CA is trusted on this machine.
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    cert: CertificateDer<'static>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl PinnedCertVerifier {
+    fn new(cert: CertificateDer<'static>) -> Self {
+        Self {
+            cert,
+            provider: Arc::new(rustls::crypto::ring::default_provider()),
+        }
+    }
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if end_entity.as_ref() == self.cert.as_ref() {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "server did not present the dev certificate".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_check_redis_reply() {
+        assert!(check_redis_reply("+PONG\r\n").is_ok());
+        assert!(check_redis_reply("").is_err());
+
+        let err = check_redis_reply("-NOAUTH Authentication required.\r\n").unwrap_err();
+        assert!(err.to_string().contains("NOAUTH"));
+    }
+
+    #[test]
+    fn test_parse_status_line() {
+        assert_eq!(parse_status_line("HTTP/1.1 200 OK\r\n").unwrap(), 200);
+        assert_eq!(parse_status_line("HTTP/1.1 404 Not Found").unwrap(), 404);
+        assert!(parse_status_line("SSH-2.0-OpenSSH_9.6").is_err());
+        assert!(parse_status_line("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redis_probe_against_fake_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 14];
+            socket.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request, b"*1\r\n$4\r\nPING\r\n");
+            socket.write_all(b"+PONG\r\n").await.unwrap();
+        });
+
+        redis("127.0.0.1", port).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_probes_fail_on_closed_port() {
+        // Bind and drop a listener to find a port nothing is listening on
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+
+        assert!(redis("127.0.0.1", port).await.is_err());
+        let db = Database {
+            host: "127.0.0.1".to_string(),
+            port,
+            name: "cwrdd_dev".to_string(),
+            user: "cwrdd_user".to_string(),
+            password: "cwrdd_password".to_string(),
+        };
+        assert!(postgres(&db).await.is_err());
+    }
+}
@@ -0,0 +1,423 @@
+//! Environment profiles for cwrdd-make.
+//!
+//! A [`Profile`] holds everything that differs between environments: the
+//! database connection, the Redis URL, the prefix used for container, volume
+//! and network names, and the host ports services are published on. Three
+//! profiles are built in:
+//!
+//! - `dev`: the default local environment (`cwrdd_dev` on port 5432)
+//! - `test`: a separate stack that can run next to `dev`, with every host port
+//!   shifted by 10000
+//! - `ci`: a throwaway database for CI runners, on the standard ports
+//!
+//! Profiles can be changed or added in any config file under `[profiles.<name>]`;
+//! a new profile starts from the `dev` settings, except that it isn't
+//! `disposable` unless it says so. The selected profile is
+//! passed to `podman-compose` as `CWRDD_*` variables that `compose.yaml`
+//! interpolates.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Name of the profile used when none is selected
+pub const DEFAULT_PROFILE: &str = "dev";
+
+/// Settings for one environment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// PostgreSQL URL, e.g. `postgres://localhost:5432/cwrdd_dev`
+    pub database_url: String,
+    /// PostgreSQL user
+    pub database_user: String,
+    /// PostgreSQL password
+    pub database_password: String,
+    /// Redis URL, e.g. `redis://localhost:6379`
+    pub redis_url: String,
+    /// Prefix for container, volume and network names, e.g. `cwrdd` gives `cwrdd-postgres`
+    pub container_prefix: String,
+    /// Host ports the other services are published on
+    pub ports: Ports,
+    /// Whether the database may be dropped and rebuilt by `db reset`
+    #[serde(default)]
+    pub disposable: bool,
+    /// Fixture sets from `db/seeds/` that `seed`, `up` and `db reset` load
+    #[serde(default = "default_seed_sets")]
+    pub seed_sets: Vec<String>,
+}
+
+fn default_seed_sets() -> Vec<String> {
+    vec!["dev".to_string()]
+}
+
+/// Host ports for services that aren't described by a URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Ports {
+    /// cwrdd app (HTTPS)
+    pub app: u16,
+    /// Grafana UI
+    pub grafana: u16,
+    /// Prometheus UI
+    pub prometheus: u16,
+    /// Alloy UI
+    pub alloy: u16,
+    /// OTLP gRPC receiver on Alloy
+    pub otlp_grpc: u16,
+    /// OTLP HTTP receiver on Alloy
+    pub otlp_http: u16,
+    /// Tempo HTTP API
+    pub tempo: u16,
+    /// OTLP gRPC receiver on Tempo
+    pub tempo_otlp_grpc: u16,
+    /// OTLP HTTP receiver on Tempo
+    pub tempo_otlp_http: u16,
+    /// Loki HTTP API
+    pub loki: u16,
+    /// Pyroscope HTTP API
+    pub pyroscope: u16,
+}
+
+/// Connection details for a PostgreSQL database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Database {
+    /// Host name or address
+    pub host: String,
+    /// Port
+    pub port: u16,
+    /// Database name
+    pub name: String,
+    /// User name
+    pub user: String,
+    /// Password
+    pub password: String,
+}
+
+impl Database {
+    /// JDBC URL for Liquibase
+    pub fn jdbc_url(&self) -> String {
+        format!("jdbc:postgresql://{}:{}/{}", self.host, self.port, self.name)
+    }
+
+    /// Connection arguments for Liquibase. The password goes in the environment;
+    /// see [`Database::liquibase_env`].
+    pub fn liquibase_args(&self) -> Vec<String> {
+        vec![
+            format!("--url={}", self.jdbc_url()),
+            format!("--username={}", self.user),
+        ]
+    }
+
+    /// Environment for Liquibase, keeping the password out of argv (and so out of `ps`)
+    pub fn liquibase_env(&self) -> Vec<(String, String)> {
+        vec![("LIQUIBASE_COMMAND_PASSWORD".to_string(), self.password.clone())]
+    }
+
+    /// Whether the host is this machine, where the compose stack publishes its ports
+    pub fn is_local(&self) -> bool {
+        matches!(self.host.as_str(), "localhost" | "127.0.0.1" | "[::1]")
+    }
+
+    /// Connection arguments for psql. The password goes in `PGPASSWORD`.
+    pub fn psql_args(&self) -> Vec<String> {
+        vec![
+            "-h".to_string(),
+            self.host.clone(),
+            "-p".to_string(),
+            self.port.to_string(),
+            "-U".to_string(),
+            self.user.clone(),
+            "-d".to_string(),
+            self.name.clone(),
+        ]
+    }
+
+    /// Open a client connection, driving it on a background task until the client is dropped
+    pub async fn connect(&self) -> Result<tokio_postgres::Client> {
+        let mut config = tokio_postgres::Config::new();
+        config
+            .host(&self.host)
+            .port(self.port)
+            .user(&self.user)
+            .password(&self.password)
+            .dbname(&self.name)
+            .application_name("cwrdd-make")
+            .connect_timeout(Duration::from_secs(5));
+
+        let (client, connection) = config
+            .connect(tokio_postgres::NoTls)
+            .await
+            .context(format!("Failed to connect to PostgreSQL at {}:{}", self.host, self.port))?;
+        tokio::spawn(connection);
+        Ok(client)
+    }
+}
+
+impl Profile {
+    /// The default local development environment
+    pub fn dev() -> Self {
+        Self {
+            database_url: "postgres://localhost:5432/cwrdd_dev".to_string(),
+            database_user: "cwrdd_user".to_string(),
+            database_password: "cwrdd_password".to_string(),
+            redis_url: "redis://localhost:6379".to_string(),
+            container_prefix: "cwrdd".to_string(),
+            ports: Ports {
+                app: 8443,
+                grafana: 3000,
+                prometheus: 9090,
+                alloy: 12345,
+                otlp_grpc: 4319,
+                otlp_http: 4320,
+                tempo: 3200,
+                tempo_otlp_grpc: 4317,
+                tempo_otlp_http: 4318,
+                loki: 3100,
+                pyroscope: 4040,
+            },
+            disposable: true,
+            seed_sets: default_seed_sets(),
+        }
+    }
+
+    /// A test environment that runs alongside `dev` without port clashes
+    pub fn test() -> Self {
+        let dev = Self::dev();
+        let shift = |port: u16| port + 10000;
+        Self {
+            database_url: "postgres://localhost:15432/cwrdd_test".to_string(),
+            redis_url: "redis://localhost:16379".to_string(),
+            container_prefix: "cwrdd-test".to_string(),
+            ports: Ports {
+                app: shift(dev.ports.app),
+                grafana: shift(dev.ports.grafana),
+                prometheus: shift(dev.ports.prometheus),
+                alloy: shift(dev.ports.alloy),
+                otlp_grpc: shift(dev.ports.otlp_grpc),
+                otlp_http: shift(dev.ports.otlp_http),
+                tempo: shift(dev.ports.tempo),
+                tempo_otlp_grpc: shift(dev.ports.tempo_otlp_grpc),
+                tempo_otlp_http: shift(dev.ports.tempo_otlp_http),
+                loki: shift(dev.ports.loki),
+                pyroscope: shift(dev.ports.pyroscope),
+            },
+            ..dev
+        }
+    }
+
+    /// A throwaway environment for CI runners
+    pub fn ci() -> Self {
+        Self {
+            database_url: "postgres://localhost:5432/cwrdd_ci".to_string(),
+            container_prefix: "cwrdd-ci".to_string(),
+            ..Self::dev()
+        }
+    }
+
+    /// The built-in profiles, by name
+    pub fn builtin() -> BTreeMap<String, Profile> {
+        BTreeMap::from([
+            ("dev".to_string(), Self::dev()),
+            ("test".to_string(), Self::test()),
+            ("ci".to_string(), Self::ci()),
+        ])
+    }
+
+    /// Parse the database URL and combine it with the credentials
+    pub fn database(&self) -> Result<Database> {
+        let (host, port, path) = parse_url(&self.database_url, &["postgres", "postgresql"], 5432)
+            .context(format!("Invalid database_url: {}", self.database_url))?;
+
+        let name = path.split('?').next().unwrap_or_default();
+        if name.is_empty() {
+            bail!(
+                "Invalid database_url: {} (no database name)",
+                self.database_url
+            );
+        }
+
+        Ok(Database {
+            host,
+            port,
+            name: name.to_string(),
+            user: self.database_user.clone(),
+            password: self.database_password.clone(),
+        })
+    }
+
+    /// Host and port from the Redis URL
+    pub fn redis_address(&self) -> Result<(String, u16)> {
+        let (host, port, _) = parse_url(&self.redis_url, &["redis"], 6379)
+            .context(format!("Invalid redis_url: {}", self.redis_url))?;
+        Ok((host, port))
+    }
+
+    /// Name of the container running `service` in this profile
+    pub fn container(&self, service: &str) -> String {
+        format!("{}-{}", self.container_prefix, service)
+    }
+
+    /// Every host port published by the compose stack, with the service that publishes it
+    pub fn service_ports(&self) -> Result<Vec<(&'static str, u16)>> {
+        let db = self.database()?;
+        let (_, redis_port) = self.redis_address()?;
+        let ports = &self.ports;
+
+        Ok(vec![
+            ("app", ports.app),
+            ("postgres", db.port),
+            ("redis", redis_port),
+            ("tempo", ports.tempo),
+            ("tempo", ports.tempo_otlp_grpc),
+            ("tempo", ports.tempo_otlp_http),
+            ("loki", ports.loki),
+            ("prometheus", ports.prometheus),
+            ("pyroscope", ports.pyroscope),
+            ("alloy", ports.alloy),
+            ("alloy", ports.otlp_grpc),
+            ("alloy", ports.otlp_http),
+            ("grafana", ports.grafana),
+        ])
+    }
+
+    /// Variables passed to `podman-compose` so `compose.yaml` uses this profile
+    pub fn compose_env(&self) -> Result<Vec<(String, String)>> {
+        let db = self.database()?;
+        let (_, redis_port) = self.redis_address()?;
+        let ports = &self.ports;
+
+        let vars = [
+            ("COMPOSE_PROJECT_NAME", self.container_prefix.clone()),
+            ("CWRDD_CONTAINER_PREFIX", self.container_prefix.clone()),
+            ("CWRDD_POSTGRES_DB", db.name),
+            ("CWRDD_POSTGRES_USER", db.user),
+            ("CWRDD_POSTGRES_PASSWORD", db.password),
+            ("CWRDD_POSTGRES_PORT", db.port.to_string()),
+            ("CWRDD_REDIS_PORT", redis_port.to_string()),
+            ("CWRDD_APP_PORT", ports.app.to_string()),
+            ("CWRDD_GRAFANA_PORT", ports.grafana.to_string()),
+            ("CWRDD_PROMETHEUS_PORT", ports.prometheus.to_string()),
+            ("CWRDD_ALLOY_PORT", ports.alloy.to_string()),
+            ("CWRDD_OTLP_GRPC_PORT", ports.otlp_grpc.to_string()),
+            ("CWRDD_OTLP_HTTP_PORT", ports.otlp_http.to_string()),
+            ("CWRDD_TEMPO_PORT", ports.tempo.to_string()),
+            ("CWRDD_TEMPO_OTLP_GRPC_PORT", ports.tempo_otlp_grpc.to_string()),
+            ("CWRDD_TEMPO_OTLP_HTTP_PORT", ports.tempo_otlp_http.to_string()),
+            ("CWRDD_LOKI_PORT", ports.loki.to_string()),
+            ("CWRDD_PYROSCOPE_PORT", ports.pyroscope.to_string()),
+        ];
+        Ok(vars
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect())
+    }
+}
+
+/// Split `scheme://host[:port][/path]` into host, port and path
+fn parse_url(url: &str, schemes: &[&str], default_port: u16) -> Result<(String, u16, String)> {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        bail!("expected {}://host:port", schemes[0]);
+    };
+    if !schemes.contains(&scheme) {
+        bail!("unsupported scheme '{}', expected {}", scheme, schemes.join(" or "));
+    }
+    if rest.contains('@') {
+        bail!("credentials belong in their own settings, not in the URL");
+    }
+
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse()
+                .context(format!("invalid port '{}'", port))?;
+            (host, port)
+        }
+        None => (authority, default_port),
+    };
+    if host.is_empty() {
+        bail!("missing host");
+    }
+
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dev_database() {
+        let db = Profile::dev().database().unwrap();
+        assert_eq!(db.host, "localhost");
+        assert_eq!(db.port, 5432);
+        assert_eq!(db.name, "cwrdd_dev");
+        assert_eq!(db.jdbc_url(), "jdbc:postgresql://localhost:5432/cwrdd_dev");
+        assert_eq!(db.liquibase_args()[1], "--username=cwrdd_user");
+    }
+
+    #[test]
+    fn test_database_is_local() {
+        assert!(Profile::dev().database().unwrap().is_local());
+        for host in ["127.0.0.1", "[::1]"] {
+            let profile = Profile {
+                database_url: format!("postgres://{}:5432/cwrdd_dev", host),
+                ..Profile::dev()
+            };
+            assert!(profile.database().unwrap().is_local(), "{}", host);
+        }
+        let profile = Profile {
+            database_url: "postgres://db.internal:5432/cwrdd_dev".to_string(),
+            ..Profile::dev()
+        };
+        assert!(!profile.database().unwrap().is_local());
+    }
+
+    #[test]
+    fn test_database_url_with_params_and_default_port() {
+        let profile = Profile {
+            database_url: "postgresql://db.internal/cwrdd_test?sslmode=require".to_string(),
+            ..Profile::dev()
+        };
+        let db = profile.database().unwrap();
+        assert_eq!(db.host, "db.internal");
+        assert_eq!(db.port, 5432);
+        assert_eq!(db.name, "cwrdd_test");
+    }
+
+    #[test]
+    fn test_invalid_urls() {
+        for url in [
+            "localhost:5432/cwrdd_dev",
+            "mysql://localhost/cwrdd_dev",
+            "postgres://localhost:port/cwrdd_dev",
+            "postgres://localhost:5432",
+            "postgres://user:pw@localhost/cwrdd_dev",
+        ] {
+            let profile = Profile {
+                database_url: url.to_string(),
+                ..Profile::dev()
+            };
+            assert!(profile.database().is_err(), "{} should be rejected", url);
+        }
+    }
+
+    #[test]
+    fn test_profiles_do_not_clash() {
+        let dev = Profile::dev();
+        let test = Profile::test();
+        assert_ne!(dev.container("postgres"), test.container("postgres"));
+        assert_eq!(test.container("postgres"), "cwrdd-test-postgres");
+
+        let dev_env: BTreeMap<_, _> = dev.compose_env().unwrap().into_iter().collect();
+        let test_env: BTreeMap<_, _> = test.compose_env().unwrap().into_iter().collect();
+        for (key, value) in &dev_env {
+            if key.ends_with("_PORT") {
+                assert_ne!(value, &test_env[key], "{} clashes", key);
+            }
+        }
+        assert_eq!(test_env["CWRDD_REDIS_PORT"], "16379");
+    }
+}
@@ -0,0 +1,423 @@
+//! Java `.properties` files, as used for `db/liquibase.properties`.
+//!
+//! [`Properties::parse`] follows the `java.util.Properties` format: `key=value`,
+//! `key: value` or `key value`; `#` and `!` comments; backslash escapes
+//! (including `\uXXXX`); and lines continued with a trailing backslash. Keys
+//! are matched exactly, so `url` never picks up `referenceUrl` or `urlParams`.
+//!
+//! [`LiquibaseProperties`] reads the connection settings out of
+//! `liquibase.properties`. Each setting can be overridden from the
+//! environment, as Liquibase itself allows: `referenceUrl` by
+//! `LIQUIBASE_COMMAND_REFERENCE_URL` or `LIQUIBASE_REFERENCE_URL`, and by
+//! `CWRDD_REFERENCE_URL`, which wins over both.
+
+use crate::profile::{Database, Profile};
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Where `liquibase.properties` lives, relative to the db directory
+pub const LIQUIBASE_PROPERTIES_FILE: &str = "liquibase.properties";
+
+/// A value and where it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub value: String,
+    /// Line the entry starts on, 1-based
+    pub line: usize,
+    /// Environment variable that overrode the file, if any
+    pub env: Option<String>,
+}
+
+/// The entries of a properties file. Later entries for a key replace earlier ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Properties(BTreeMap<String, Property>);
+
+impl Properties {
+    /// Parse properties file content
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut properties = Properties::default();
+        let mut lines = content.lines().enumerate();
+
+        while let Some((index, line)) = lines.next() {
+            let start = index + 1;
+            let first = line.trim_start();
+            if first.is_empty() || first.starts_with('#') || first.starts_with('!') {
+                continue;
+            }
+
+            // Join continuation lines, dropping the leading whitespace of each
+            let mut logical = first.to_string();
+            while ends_with_continuation(&logical) {
+                logical.pop();
+                match lines.next() {
+                    Some((_, next)) => logical.push_str(next.trim_start()),
+                    None => break,
+                }
+            }
+
+            let (key, value) = split_entry(&logical);
+            let key = unescape(key).context(format!("line {}: invalid key", start))?;
+            if key.is_empty() {
+                bail!("line {}: missing key before '{}'", start, logical.trim());
+            }
+            let value =
+                unescape(value).context(format!("line {}: invalid value for '{}'", start, key))?;
+            properties.0.insert(
+                key,
+                Property {
+                    value,
+                    line: start,
+                    env: None,
+                },
+            );
+        }
+        Ok(properties)
+    }
+
+    /// The value of `key`, if set
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|property| property.value.as_str())
+    }
+
+    /// The value of `key` and where it came from, if set
+    pub fn property(&self, key: &str) -> Option<&Property> {
+        self.0.get(key)
+    }
+
+    /// Override values from `LIQUIBASE_COMMAND_<KEY>`, `LIQUIBASE_<KEY>` and
+    /// `CWRDD_<KEY>` variables, in increasing order of precedence.
+    ///
+    /// `<KEY>` is the key in upper snake case, so `changeLogFile` is
+    /// `CHANGE_LOG_FILE`. Only keys named in `keys` are looked up.
+    pub fn apply_env<'a>(
+        &mut self,
+        keys: impl IntoIterator<Item = &'a str>,
+        vars: &BTreeMap<String, String>,
+    ) {
+        for key in keys {
+            let suffix = env_suffix(key);
+            let overrides = ["LIQUIBASE_COMMAND_", "LIQUIBASE_", "CWRDD_"]
+                .map(|prefix| format!("{}{}", prefix, suffix));
+            for var in overrides {
+                if let Some(value) = vars.get(&var) {
+                    let line = self.0.get(key).map(|p| p.line).unwrap_or(0);
+                    self.0.insert(
+                        key.to_string(),
+                        Property {
+                            value: value.clone(),
+                            line,
+                            env: Some(var),
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Connection settings from `liquibase.properties`
+#[derive(Debug, Clone)]
+pub struct LiquibaseProperties {
+    /// The file the settings were read from
+    pub path: PathBuf,
+    pub properties: Properties,
+}
+
+impl LiquibaseProperties {
+    /// Keys that hold connection settings
+    pub const CONNECTION_KEYS: [&'static str; 6] = [
+        "url",
+        "username",
+        "password",
+        "referenceUrl",
+        "referenceUsername",
+        "referencePassword",
+    ];
+
+    /// Read `liquibase.properties` from `db_path`, if it exists, applying
+    /// overrides from `vars`
+    pub fn load(db_path: &Path, vars: &BTreeMap<String, String>) -> Result<Option<Self>> {
+        let path = db_path.join(LIQUIBASE_PROPERTIES_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content =
+            std::fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+        let mut properties =
+            Properties::parse(&content).context(format!("Invalid {}", path.display()))?;
+        properties.apply_env(Self::CONNECTION_KEYS, vars);
+        Ok(Some(Self { path, properties }))
+    }
+
+    /// The database Liquibase migrates (`url`, `username` and `password`)
+    pub fn database(&self) -> Result<Option<Database>> {
+        self.connection("url", "username", "password")
+    }
+
+    /// The database `diffChangeLog` compares against (`referenceUrl` and friends)
+    pub fn reference_database(&self) -> Result<Option<Database>> {
+        self.connection("referenceUrl", "referenceUsername", "referencePassword")
+    }
+
+    /// `postgres://` form of the JDBC URL in `key`, as profiles expect
+    pub fn database_url(&self, key: &str) -> Result<Option<String>> {
+        let Some(property) = self.properties.property(key) else {
+            return Ok(None);
+        };
+        let url = property
+            .value
+            .strip_prefix("jdbc:")
+            .filter(|url| url.starts_with("postgresql://"))
+            .map(|url| url.replacen("postgresql://", "postgres://", 1));
+        match url {
+            Some(url) => Ok(Some(url)),
+            None => bail!(
+                "{}: {} must be a jdbc:postgresql://host:port/database URL, not '{}'",
+                self.location(property),
+                key,
+                property.value
+            ),
+        }
+    }
+
+    fn connection(&self, url: &str, user: &str, password: &str) -> Result<Option<Database>> {
+        let Some(database_url) = self.database_url(url)? else {
+            return Ok(None);
+        };
+        let value = |key: &str| self.properties.get(key).unwrap_or_default().to_string();
+        let profile = Profile {
+            database_url,
+            database_user: value(user),
+            database_password: value(password),
+            ..Profile::dev()
+        };
+        let property = self.properties.property(url).expect("url was just read");
+        let db =
+            profile
+                .database()
+                .context(format!("{}: invalid {}", self.location(property), url))?;
+        Ok(Some(db))
+    }
+
+    /// `path:line`, or the variable that overrode the file
+    pub fn location(&self, property: &Property) -> String {
+        match &property.env {
+            Some(var) => format!("environment variable {}", var),
+            None => format!("{}:{}", self.path.display(), property.line),
+        }
+    }
+}
+
+/// Whether a line ends with an odd number of backslashes
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1
+}
+
+/// Split a logical line into its raw key and value
+fn split_entry(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '=' | ':' | ' ' | '\t' | '\u{c}' => {
+                let key = &line[..i];
+                let mut rest = line[i..].trim_start_matches([' ', '\t', '\u{c}']);
+                if c != '=' && c != ':' {
+                    if let Some(after) = rest.strip_prefix(['=', ':']) {
+                        rest = after;
+                    }
+                } else {
+                    rest = &rest[1..];
+                }
+                return (key, rest.trim_start_matches([' ', '\t', '\u{c}']));
+            }
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+/// Resolve backslash escapes
+fn unescape(raw: &str) -> Result<String> {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => value.push('\t'),
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('f') => value.push('\u{c}'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let code = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 4)
+                    .and_then(char::from_u32);
+                match code {
+                    Some(c) => value.push(c),
+                    None => bail!("malformed \\u escape '\\u{}'", hex),
+                }
+            }
+            Some(other) => value.push(other),
+            None => {}
+        }
+    }
+    Ok(value)
+}
+
+/// `changeLogFile` -> `CHANGE_LOG_FILE`
+fn env_suffix(key: &str) -> String {
+    let mut suffix = String::new();
+    for (i, c) in key.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            suffix.push('_');
+        }
+        suffix.push(c.to_ascii_uppercase());
+    }
+    suffix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_separators_comments_and_exact_keys() {
+        let properties = Properties::parse(
+            "# comment\n\
+             ! also a comment\n\
+             \n\
+             url: jdbc:postgresql://localhost:5432/cwrdd_dev\n\
+             urlParams=ssl=true\n\
+             referenceUrl = jdbc:postgresql://localhost:5432/other\n\
+             username cwrdd_user\n\
+             empty=\n",
+        )
+        .unwrap();
+        assert_eq!(
+            properties.get("url"),
+            Some("jdbc:postgresql://localhost:5432/cwrdd_dev")
+        );
+        assert_eq!(properties.get("urlParams"), Some("ssl=true"));
+        assert_eq!(
+            properties.get("referenceUrl"),
+            Some("jdbc:postgresql://localhost:5432/other")
+        );
+        assert_eq!(properties.get("username"), Some("cwrdd_user"));
+        assert_eq!(properties.get("empty"), Some(""));
+        assert_eq!(properties.property("username").unwrap().line, 7);
+    }
+
+    #[test]
+    fn test_escapes_and_continuations() {
+        let properties = Properties::parse(
+            "key\\:with\\=separators = value\\\\\n\
+             password = p\\u00e4ss\\tword\n\
+             url = jdbc:postgresql://localhost:5432/\\\n     cwrdd_dev\n\
+             last = ends with backslash\\",
+        )
+        .unwrap();
+        assert_eq!(properties.get("key:with=separators"), Some("value\\"));
+        assert_eq!(properties.get("password"), Some("päss\tword"));
+        assert_eq!(
+            properties.get("url"),
+            Some("jdbc:postgresql://localhost:5432/cwrdd_dev")
+        );
+        assert_eq!(properties.get("last"), Some("ends with backslash"));
+    }
+
+    #[test]
+    fn test_errors_report_line_numbers() {
+        let err = Properties::parse("url=x\n\npassword=\\u12\n").unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "line 3: invalid value for 'password': malformed \\u escape '\\u12'"
+        );
+        let err = Properties::parse("a=b\n = nothing\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 2: missing key"));
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let mut properties = Properties::parse("referenceUrl=a\nusername=file\n").unwrap();
+        let vars = BTreeMap::from([
+            ("LIQUIBASE_REFERENCE_URL".to_string(), "b".to_string()),
+            (
+                "LIQUIBASE_COMMAND_USERNAME".to_string(),
+                "liquibase".to_string(),
+            ),
+            ("CWRDD_USERNAME".to_string(), "cwrdd".to_string()),
+        ]);
+        properties.apply_env(["referenceUrl", "username", "url"], &vars);
+        assert_eq!(properties.get("referenceUrl"), Some("b"));
+        assert_eq!(properties.get("username"), Some("cwrdd"));
+        assert_eq!(properties.get("url"), None);
+        let username = properties.property("username").unwrap();
+        assert_eq!(username.env.as_deref(), Some("CWRDD_USERNAME"));
+        assert_eq!(username.line, 2);
+    }
+
+    #[test]
+    fn test_liquibase_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(LIQUIBASE_PROPERTIES_FILE),
+            "url: jdbc:postgresql://db.local:5433/cwrdd_dev?ssl=false\n\
+             username: cwrdd_user\n\
+             password: secret\n\
+             referenceUrl: jdbc:postgresql://localhost:5432/reference\n",
+        )
+        .unwrap();
+
+        let liquibase = LiquibaseProperties::load(dir.path(), &BTreeMap::new())
+            .unwrap()
+            .unwrap();
+        let db = liquibase.database().unwrap().unwrap();
+        assert_eq!((db.host.as_str(), db.port), ("db.local", 5433));
+        assert_eq!(
+            (db.name.as_str(), db.password.as_str()),
+            ("cwrdd_dev", "secret")
+        );
+        assert_eq!(
+            liquibase.reference_database().unwrap().unwrap().name,
+            "reference"
+        );
+
+        let vars = BTreeMap::from([(
+            "LIQUIBASE_COMMAND_URL".to_string(),
+            "jdbc:mysql://localhost/x".to_string(),
+        )]);
+        let liquibase = LiquibaseProperties::load(dir.path(), &vars)
+            .unwrap()
+            .unwrap();
+        let err = liquibase.database().unwrap_err().to_string();
+        assert!(err.starts_with("environment variable LIQUIBASE_COMMAND_URL: url must be"));
+
+        assert!(
+            LiquibaseProperties::load(&dir.path().join("missing"), &vars)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_repo_liquibase_properties_parse() {
+        let db_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../db");
+        let liquibase = LiquibaseProperties::load(&db_path, &BTreeMap::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            liquibase.database().unwrap().unwrap(),
+            Profile::dev().database().unwrap()
+        );
+    }
+}
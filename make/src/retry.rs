@@ -0,0 +1,259 @@
+//! Retry policies for [`crate::task::Task`].
+//!
+//! A [`RetryPolicy`] re-runs a failing task with fixed or exponential backoff,
+//! optional jitter and an overall deadline. Quiet policies suppress the usual
+//! per-attempt output, which suits readiness probes that are expected to fail a
+//! few times before the service comes up.
+//!
+//! Policies apply to tasks through [`crate::task::Task::retry`], and to any
+//! other fallible async operation through [`RetryPolicy::run`].
+
+use crate::shutdown;
+use crate::task::format_duration;
+use anyhow::{bail, Context, Result};
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+/// How long to wait between attempts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backoff {
+    /// Wait the same amount of time after every failed attempt
+    Fixed(Duration),
+    /// Double the wait after every failed attempt, up to `max`
+    Exponential {
+        /// Wait after the first failed attempt
+        initial: Duration,
+        /// Upper bound on any single wait
+        max: Duration,
+    },
+}
+
+/// When and how often to retry a failing task.
+///
+/// # Example
+///
+/// ```no_run
+/// use cwrdd_make::retry::RetryPolicy;
+/// use cwrdd_make::task::Task;
+/// use std::time::Duration;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// Task::new("check postgres", "pg_isready")
+///     .retry(
+///         RetryPolicy::fixed(30, Duration::from_secs(2))
+///             .deadline(Duration::from_secs(60))
+///             .quiet(),
+///     )
+///     .execute()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    jitter: f64,
+    deadline: Option<Duration>,
+    quiet: bool,
+}
+
+impl RetryPolicy {
+    /// Retry up to `max_attempts` times in total, waiting `delay` between attempts
+    pub fn fixed(max_attempts: u32, delay: Duration) -> Self {
+        Self::new(max_attempts, Backoff::Fixed(delay))
+    }
+
+    /// Retry up to `max_attempts` times in total, doubling the wait from
+    /// `initial` up to `max` after each failure
+    pub fn exponential(max_attempts: u32, initial: Duration, max: Duration) -> Self {
+        Self::new(max_attempts, Backoff::Exponential { initial, max })
+    }
+
+    fn new(max_attempts: u32, backoff: Backoff) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            backoff,
+            jitter: 0.0,
+            deadline: None,
+            quiet: false,
+        }
+    }
+
+    /// Randomise each wait by up to `fraction` (0.0 to 1.0) in either direction
+    pub fn jitter(mut self, fraction: f64) -> Self {
+        self.jitter = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Stop retrying once `deadline` has passed since the first attempt started
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Don't print the banner or output of each attempt, only the final result
+    pub fn quiet(mut self) -> Self {
+        self.quiet = true;
+        self
+    }
+
+    /// Maximum number of attempts, including the first
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Overall deadline, if any
+    pub fn overall_deadline(&self) -> Option<Duration> {
+        self.deadline
+    }
+
+    /// Whether per-attempt output is suppressed
+    pub fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// How long to wait after failed attempt number `attempt` (starting at 1)
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        let base = match self.backoff {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+                initial.saturating_mul(factor).min(max)
+            }
+        };
+
+        if self.jitter == 0.0 {
+            return base;
+        }
+        let spread = self.jitter * (2.0 * random_unit() - 1.0);
+        base.mul_f64((1.0 + spread).max(0.0))
+    }
+}
+
+impl RetryPolicy {
+    /// Call `attempt` until it succeeds, the attempts run out or the deadline
+    /// passes. `name` identifies the operation in messages and errors.
+    pub async fn run<T, F, Fut>(&self, name: &str, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let started = Instant::now();
+        let mut number = 1;
+
+        loop {
+            let error = match attempt().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            if number >= self.max_attempts {
+                return Err(error).context(format!("'{}' failed after {} attempts", name, number));
+            }
+
+            let delay = self.delay_after(number);
+            if let Some(deadline) = self.deadline {
+                if started.elapsed() + delay > deadline {
+                    return Err(error).context(format!(
+                        "'{}' gave up after {} attempts (deadline {})",
+                        name,
+                        number,
+                        format_duration(deadline)
+                    ));
+                }
+            }
+
+            if !self.quiet {
+                crate::say!(
+                    "   ↻ '{}' failed (attempt {}/{}), retrying in {}",
+                    name,
+                    number,
+                    self.max_attempts,
+                    format_duration(delay)
+                );
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown::token().cancelled() => bail!("'{}' was cancelled", name),
+            }
+            number += 1;
+        }
+    }
+}
+
+/// A random number in `[0, 1)`, good enough for spreading out retries
+fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_backoff() {
+        let policy = RetryPolicy::fixed(5, Duration::from_secs(2));
+        assert_eq!(policy.delay_after(1), Duration::from_secs(2));
+        assert_eq!(policy.delay_after(4), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_exponential_backoff_is_capped() {
+        let policy =
+            RetryPolicy::exponential(10, Duration::from_millis(100), Duration::from_secs(1));
+        assert_eq!(policy.delay_after(1), Duration::from_millis(100));
+        assert_eq!(policy.delay_after(2), Duration::from_millis(200));
+        assert_eq!(policy.delay_after(4), Duration::from_millis(800));
+        assert_eq!(policy.delay_after(5), Duration::from_secs(1));
+        assert_eq!(policy.delay_after(64), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_jitter_stays_in_range() {
+        let policy = RetryPolicy::fixed(3, Duration::from_secs(10)).jitter(0.2);
+        for _ in 0..100 {
+            let delay = policy.delay_after(1);
+            assert!(delay >= Duration::from_secs(8) && delay <= Duration::from_secs(12));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_until_success() {
+        let policy = RetryPolicy::fixed(5, Duration::from_millis(1)).quiet();
+        let mut calls = 0;
+        let value = policy
+            .run("flaky", || {
+                calls += 1;
+                let calls = calls;
+                async move {
+                    if calls < 3 {
+                        bail!("not yet");
+                    }
+                    Ok(calls)
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 3);
+    }
+
+    #[tokio::test]
+    async fn test_run_gives_up() {
+        let policy = RetryPolicy::fixed(2, Duration::from_millis(1)).quiet();
+        let err = policy
+            .run("broken", || async { Err::<(), _>(anyhow::anyhow!("connection refused")) })
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "'broken' failed after 2 attempts");
+        assert_eq!(err.root_cause().to_string(), "connection refused");
+    }
+
+    #[test]
+    fn test_at_least_one_attempt() {
+        assert_eq!(RetryPolicy::fixed(0, Duration::ZERO).max_attempts(), 1);
+    }
+}
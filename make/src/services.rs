@@ -0,0 +1,319 @@
+//! Choosing which compose services `up`, `down` and `logs` act on.
+//!
+//! `--only` and `--without` take comma-separated service names from
+//! `compose.yaml`, or names of service groups. Groups come from `[groups]` in
+//! any config file; `observability` is built in:
+//!
+//! ```toml
+//! [groups]
+//! data = ["postgres", "redis"]
+//! ```
+//!
+//! `up` also starts everything the chosen services list under `depends_on`,
+//! so `up --only app` brings up PostgreSQL and Redis as well, and refuses to
+//! `--without` a service that a started one depends on.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The Grafana stack, which most tasks don't need
+pub const OBSERVABILITY: [&str; 6] = [
+    "tempo",
+    "loki",
+    "prometheus",
+    "pyroscope",
+    "alloy",
+    "grafana",
+];
+
+/// Service groups available without any configuration
+pub fn builtin_groups() -> BTreeMap<String, Vec<String>> {
+    BTreeMap::from([(
+        "observability".to_string(),
+        OBSERVABILITY.iter().map(|s| s.to_string()).collect(),
+    )])
+}
+
+/// `--only` and `--without`, shared by `up`, `down` and `logs`
+#[derive(Debug, Clone, Default, PartialEq, Eq, clap::Args)]
+pub struct Selection {
+    /// Only these services or groups, comma-separated (e.g. postgres,redis)
+    #[arg(long, value_delimiter = ',')]
+    pub only: Vec<String>,
+    /// Skip these services or groups, comma-separated (e.g. observability)
+    #[arg(long, value_delimiter = ',')]
+    pub without: Vec<String>,
+}
+
+impl Selection {
+    /// Whether neither `--only` nor `--without` was given
+    pub fn is_all(&self) -> bool {
+        self.only.is_empty() && self.without.is_empty()
+    }
+}
+
+/// The services in `compose.yaml` and what each depends on, in file order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComposeFile {
+    pub services: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Deserialize)]
+struct RawFile {
+    #[serde(default)]
+    services: serde_yaml::Mapping,
+}
+
+#[derive(Deserialize)]
+struct RawService {
+    #[serde(default)]
+    depends_on: Option<DependsOn>,
+}
+
+/// `depends_on` is either a list of names or a map of name to condition
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DependsOn {
+    List(Vec<String>),
+    Map(BTreeMap<String, serde_yaml::Value>),
+}
+
+impl ComposeFile {
+    /// Read `compose.yaml`
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).context(format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let raw: RawFile = serde_yaml::from_str(content)?;
+        let mut services = Vec::new();
+        for (name, service) in raw.services {
+            let Some(name) = name.as_str().map(str::to_string) else {
+                bail!("Service names must be strings");
+            };
+            let service: RawService =
+                serde_yaml::from_value(service).context(format!("Invalid service '{}'", name))?;
+            let depends_on = match service.depends_on {
+                None => Vec::new(),
+                Some(DependsOn::List(names)) => names,
+                Some(DependsOn::Map(names)) => names.into_keys().collect(),
+            };
+            services.push((name, depends_on));
+        }
+        Ok(Self { services })
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.services.iter().any(|(service, _)| service == name)
+    }
+
+    fn depends_on(&self, name: &str) -> &[String] {
+        self.services
+            .iter()
+            .find(|(service, _)| service == name)
+            .map(|(_, deps)| deps.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Expand service and group names into service names
+    fn expand(
+        &self,
+        names: &[String],
+        groups: &BTreeMap<String, Vec<String>>,
+    ) -> Result<Vec<String>> {
+        let mut services = Vec::new();
+        for name in names {
+            let members = match groups.get(name) {
+                Some(members) => members.clone(),
+                None => vec![name.clone()],
+            };
+            for member in members {
+                if !self.contains(&member) {
+                    let known: Vec<&str> = self.services.iter().map(|(s, _)| s.as_str()).collect();
+                    let groups: Vec<&str> = groups.keys().map(String::as_str).collect();
+                    bail!(
+                        "Unknown service or group '{}'\nServices: {}\nGroups: {}",
+                        member,
+                        known.join(", "),
+                        groups.join(", ")
+                    );
+                }
+                services.push(member);
+            }
+        }
+        Ok(services)
+    }
+
+    /// The services `selection` picks, in file order. With `with_dependencies`,
+    /// everything they depend on (directly or not) is included too, and it is
+    /// an error for `--without` to exclude any of those.
+    pub fn select(
+        &self,
+        selection: &Selection,
+        groups: &BTreeMap<String, Vec<String>>,
+        with_dependencies: bool,
+    ) -> Result<Vec<String>> {
+        let mut chosen = if selection.only.is_empty() {
+            self.services.iter().map(|(s, _)| s.clone()).collect()
+        } else {
+            self.expand(&selection.only, groups)?
+        };
+        let without = self.expand(&selection.without, groups)?;
+        chosen.retain(|service| !without.contains(service));
+
+        if with_dependencies {
+            let mut pending = chosen.clone();
+            while let Some(service) = pending.pop() {
+                for dep in self.depends_on(&service) {
+                    if without.contains(dep) {
+                        bail!(
+                            "{} depends on {}, which --without excludes; exclude {} as well, \
+                             or stop excluding {}",
+                            service,
+                            dep,
+                            service,
+                            dep
+                        );
+                    }
+                    if !chosen.contains(dep) {
+                        chosen.push(dep.clone());
+                        pending.push(dep.clone());
+                    }
+                }
+            }
+        }
+
+        if chosen.is_empty() {
+            bail!("No services selected");
+        }
+        Ok(self
+            .services
+            .iter()
+            .map(|(s, _)| s.clone())
+            .filter(|s| chosen.contains(s))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSE: &str = "
+services:
+  app:
+    image: cwrdd-app:local
+    depends_on:
+      postgres:
+        condition: service_healthy
+      redis:
+        condition: service_healthy
+  postgres:
+    image: postgres
+  redis:
+    image: redis
+  tempo: {}
+  loki: {}
+  prometheus: {}
+  pyroscope: {}
+  alloy:
+    depends_on:
+      - tempo
+      - loki
+  grafana:
+    depends_on: [prometheus]
+";
+
+    fn select(only: &[&str], without: &[&str], deps: bool) -> Result<Vec<String>> {
+        let selection = Selection {
+            only: only.iter().map(|s| s.to_string()).collect(),
+            without: without.iter().map(|s| s.to_string()).collect(),
+        };
+        let mut groups = builtin_groups();
+        groups.insert(
+            "data".to_string(),
+            vec!["postgres".to_string(), "redis".to_string()],
+        );
+        ComposeFile::parse(COMPOSE)
+            .unwrap()
+            .select(&selection, &groups, deps)
+    }
+
+    #[test]
+    fn test_parse() {
+        let compose = ComposeFile::parse(COMPOSE).unwrap();
+        assert_eq!(compose.services.len(), 9);
+        assert_eq!(compose.services[0].0, "app");
+        assert_eq!(compose.depends_on("app"), ["postgres", "redis"]);
+        assert_eq!(compose.depends_on("alloy"), ["tempo", "loki"]);
+        assert!(compose.depends_on("postgres").is_empty());
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(select(&[], &[], true).unwrap().len(), 9);
+        assert_eq!(
+            select(&["postgres", "redis"], &[], true).unwrap(),
+            ["postgres", "redis"]
+        );
+        assert_eq!(
+            select(&["data"], &[], false).unwrap(),
+            ["postgres", "redis"]
+        );
+        assert_eq!(
+            select(&[], &["observability"], true).unwrap(),
+            ["app", "postgres", "redis"]
+        );
+    }
+
+    #[test]
+    fn test_select_pulls_in_dependencies() {
+        assert_eq!(
+            select(&["app"], &[], true).unwrap(),
+            ["app", "postgres", "redis"]
+        );
+        assert_eq!(select(&["app"], &[], false).unwrap(), ["app"]);
+        assert_eq!(
+            select(&["alloy"], &[], true).unwrap(),
+            ["tempo", "loki", "alloy"]
+        );
+        assert_eq!(select(&[], &["postgres"], false).unwrap().len(), 8);
+        assert_eq!(
+            select(&[], &["app", "postgres"], true).unwrap(),
+            [
+                "redis",
+                "tempo",
+                "loki",
+                "prometheus",
+                "pyroscope",
+                "alloy",
+                "grafana"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_rejects_excluded_dependencies() {
+        let err = select(&[], &["postgres"], true).unwrap_err().to_string();
+        assert!(err.starts_with("app depends on postgres"), "{}", err);
+        let err = select(&["alloy"], &["tempo"], true)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("alloy depends on tempo"), "{}", err);
+    }
+
+    #[test]
+    fn test_select_errors() {
+        let err = select(&["postgress"], &[], true).unwrap_err().to_string();
+        assert!(
+            err.contains("Unknown service or group 'postgress'"),
+            "{}",
+            err
+        );
+        assert!(select(&["data"], &["postgres", "redis"], false).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+//! Process-wide cancellation for cwrdd-make.
+//!
+//! A single [`CancellationToken`] is shared by every [`crate::task::Task`]. When
+//! cwrdd-make receives SIGINT or SIGTERM the token is cancelled, and each running
+//! task forwards the signal to its child's process group before escalating to
+//! SIGKILL. A second signal exits immediately.
+
+use anyhow::Result;
+use nix::sys::signal::Signal;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::OnceLock;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
+
+/// Exit code used when cwrdd-make is interrupted (128 + SIGINT)
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+/// Get the global cancellation token
+pub fn token() -> &'static CancellationToken {
+    TOKEN.get_or_init(CancellationToken::new)
+}
+
+/// Whether a shutdown has been requested
+pub fn is_cancelled() -> bool {
+    token().is_cancelled()
+}
+
+/// The signal that triggered the shutdown, if any
+pub fn received_signal() -> Option<Signal> {
+    Signal::try_from(RECEIVED.load(Ordering::SeqCst)).ok()
+}
+
+/// Cancel the global token from SIGINT and SIGTERM.
+///
+/// Must be called from within the Tokio runtime.
+pub fn listen_for_signals() -> Result<()> {
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+
+    tokio::spawn(async move {
+        loop {
+            let received = tokio::select! {
+                _ = sigint.recv() => Signal::SIGINT,
+                _ = sigterm.recv() => Signal::SIGTERM,
+            };
+
+            if is_cancelled() {
+                eprintln!("\n⛔ Received {} again, exiting immediately", received);
+                std::process::exit(INTERRUPTED_EXIT_CODE);
+            }
+
+            RECEIVED.store(received as i32, Ordering::SeqCst);
+            eprintln!(
+                "\n⚠️  Received {}, stopping running tasks (send it again to force exit)...",
+                received
+            );
+            token().cancel();
+        }
+    });
+
+    Ok(())
+}
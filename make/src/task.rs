@@ -1,13 +1,25 @@
 //! Task execution primitives for cwrdd-make.
 //!
 //! This module provides the [`Task`] struct for defining and executing shell commands
-//! with streaming output, working directory support, and environment variables.
+//! with streaming or captured output, working directory support, and environment variables.
 
+use crate::output::{self, Stream};
+use crate::plan::{Plan, PlannedCommand};
+use crate::retry::RetryPolicy;
+use crate::shutdown;
 use anyhow::{bail, Context, Result};
+use nix::sys::signal::{kill, killpg, Signal};
+use nix::unistd::Pid;
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio_util::sync::CancellationToken;
+
+/// How long a terminated task gets to exit after SIGTERM before it is sent SIGKILL
+const TERMINATE_GRACE: Duration = Duration::from_secs(5);
 
 /// Represents a shell command that can be executed asynchronously.
 ///
@@ -15,7 +27,11 @@ use tokio::process::Command;
 /// - Custom arguments
 /// - Working directory
 /// - Environment variables
-/// - Streaming stdout/stderr output
+/// - Streaming stdout/stderr output, or capturing it (see [`Task::capture`])
+/// - Dependencies on other tasks (see [`crate::graph::TaskGraph`])
+/// - Timeouts and cancellation (see [`Task::timeout`] and [`crate::shutdown`])
+/// - Retries with backoff (see [`Task::retry`])
+/// - Dry runs that record the command instead of running it (see [`Task::dry_run`])
 ///
 /// # Example
 ///
@@ -37,6 +53,13 @@ pub struct Task {
     args: Vec<String>,
     working_dir: Option<String>,
     env_vars: Vec<(String, String)>,
+    depends_on: Vec<String>,
+    stream: bool,
+    timeout: Option<Duration>,
+    interactive: bool,
+    cancel: Option<CancellationToken>,
+    retry: Option<RetryPolicy>,
+    plan: Option<Plan>,
 }
 
 impl Task {
@@ -48,9 +71,26 @@ impl Task {
             args: Vec::new(),
             working_dir: None,
             env_vars: Vec::new(),
+            depends_on: Vec::new(),
+            stream: false,
+            timeout: None,
+            interactive: false,
+            cancel: None,
+            retry: None,
+            plan: None,
         }
     }
 
+    /// Get the task name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the names of the tasks this task depends on
+    pub fn dependencies(&self) -> &[String] {
+        &self.depends_on
+    }
+
     /// Add arguments to the task
     pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
         self.args = args.into_iter().map(|s| s.into()).collect();
@@ -69,17 +109,294 @@ impl Task {
         self
     }
 
-    /// Execute the task
+    /// Add several environment variables to the task
+    pub fn envs<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env_vars
+            .extend(vars.into_iter().map(|(key, value)| (key.into(), value.into())));
+        self
+    }
+
+    /// Declare tasks (by name) that must complete successfully before this one
+    /// starts when run as part of a [`crate::graph::TaskGraph`]
+    pub fn depends_on(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.depends_on.extend(names.into_iter().map(|s| s.into()));
+        self
+    }
+
+    /// Echo output lines while capturing them (see [`Task::capture`])
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Kill the task if it runs longer than `timeout`.
+    ///
+    /// The child's process group is sent SIGTERM, then SIGKILL if it hasn't
+    /// exited after a grace period.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep the child in cwrdd-make's process group so it can read from the
+    /// terminal (e.g. `sudo` password prompts).
+    ///
+    /// By default each child gets its own process group so that the whole tree
+    /// can be signalled on timeout or cancellation. Interactive children receive
+    /// Ctrl-C from the terminal directly, but only the child itself is signalled
+    /// on timeout.
+    pub fn interactive(mut self) -> Self {
+        self.interactive = true;
+        self
+    }
+
+    /// Cancel the task with `token` instead of the global [`shutdown::token`]
+    pub fn cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Re-run the task according to `policy` while it fails
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Record the task in `plan` instead of running it, when a plan is given.
+    ///
+    /// Usually set via [`crate::config::Config::task`] from the `--dry-run` flag.
+    pub fn dry_run(mut self, plan: Option<Plan>) -> Self {
+        self.plan = plan;
+        self
+    }
+
+    /// Whether the task will be recorded rather than run
+    pub fn is_dry_run(&self) -> bool {
+        self.plan.is_some()
+    }
+
+    /// Execute the task, streaming its output and failing on a non-zero exit code.
+    ///
+    /// With a quiet [`RetryPolicy`], output is only shown if the final attempt fails.
     pub async fn execute(&self) -> Result<()> {
-        println!("🔧 Running: {}", self.name);
-        println!("   Command: {} {}", self.command, self.args.join(" "));
+        if self.record_dry_run().is_some() {
+            return Ok(());
+        }
+
+        let quiet = self.retry.as_ref().is_some_and(RetryPolicy::is_quiet);
+        let output = self.run_reported(!quiet, quiet, !quiet).await?;
+
+        if !output.success() {
+            if quiet {
+                if !output::is_json() {
+                    self.print_banner();
+                }
+                for line in output.stdout.lines() {
+                    output::task_line(&self.name, Stream::Stdout, line);
+                }
+                for line in output.stderr.lines() {
+                    output::task_line(&self.name, Stream::Stderr, line);
+                }
+            }
+            bail!(
+                "Task '{}' failed with exit code: {}{}",
+                self.name,
+                output.exit_code.unwrap_or(-1),
+                attempts_suffix(output.attempts)
+            );
+        }
+
+        crate::say!(
+            "✅ {}: completed successfully{}\n",
+            self.name,
+            attempts_suffix(output.attempts)
+        );
+        Ok(())
+    }
+
+    /// Execute the task and capture its output.
+    ///
+    /// Output is not printed unless [`Task::stream`] is enabled. A non-zero exit
+    /// code is not an error; check [`TaskOutput::success`] or
+    /// [`TaskOutput::exit_code`] instead.
+    ///
+    /// ```no_run
+    /// use cwrdd_make::task::Task;
+    ///
+    /// # async fn example() -> anyhow::Result<()> {
+    /// let output = Task::new("podman version", "podman")
+    ///     .args(["--version"])
+    ///     .capture()
+    ///     .await?;
+    /// println!("{}", output.stdout.trim());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn capture(&self) -> Result<TaskOutput> {
+        if let Some(output) = self.record_dry_run() {
+            return Ok(output);
+        }
+        self.run_reported(self.stream, true, self.stream).await
+    }
+
+    /// Start the task and return without waiting for it, e.g. to open a browser.
+    ///
+    /// The child gets its own process group and no stdio, so it keeps running
+    /// after cwrdd-make exits and Ctrl-C in the terminal doesn't reach it. A
+    /// background thread waits for it so it isn't left a zombie if it exits
+    /// first. Timeouts, retries and cancellation don't apply.
+    pub fn spawn_detached(&self) -> Result<()> {
+        if self.record_dry_run().is_some() {
+            return Ok(());
+        }
+        self.print_banner();
+
+        use std::os::unix::process::CommandExt;
+        let mut cmd = std::process::Command::new(&self.command);
+        cmd.args(&self.args)
+            .envs(self.env_vars.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .process_group(0);
+        if let Some(ref dir) = self.working_dir {
+            cmd.current_dir(dir);
+        }
+        let mut child = cmd.spawn()
+            .context(format!("Failed to spawn command: {}", self.command))?;
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+
+    /// Run the task's attempts, reporting its start (as a banner when
+    /// `banner` is set, or always as a JSON event) and its finish
+    async fn run_reported(&self, stream: bool, capture: bool, banner: bool) -> Result<TaskOutput> {
+        if banner || output::is_json() {
+            self.print_banner();
+        }
+
+        let started = Instant::now();
+        let result = self.run_attempts(stream, capture).await;
+        match result {
+            Ok(ref out) => {
+                output::task_finished(&self.name, out.exit_code, out.duration, out.attempts)
+            }
+            Err(_) => output::task_finished(&self.name, None, started.elapsed(), 1),
+        }
+        result
+    }
+
+    /// Spawn the task once, or repeatedly according to its [`RetryPolicy`].
+    ///
+    /// Returns the output of the last attempt.
+    async fn run_attempts(&self, stream: bool, capture: bool) -> Result<TaskOutput> {
+        let Some(ref policy) = self.retry else {
+            return self.spawn(stream, capture).await;
+        };
+        let cancel = self.cancel.as_ref().unwrap_or_else(|| shutdown::token());
+        let started = Instant::now();
+        let mut attempt = 1;
+
+        loop {
+            let result = self.spawn(stream, capture).await.map(|mut output| {
+                output.attempts = attempt;
+                output
+            });
+            let failed = match result {
+                Ok(ref output) => !output.success(),
+                Err(_) => !cancel.is_cancelled(),
+            };
+            if !failed || attempt == 1 && policy.max_attempts() == 1 {
+                return result;
+            }
+            if attempt >= policy.max_attempts() {
+                return result.with_context(|| {
+                    format!("Task '{}' failed after {} attempts", self.name, attempt)
+                });
+            }
+
+            let delay = policy.delay_after(attempt);
+            if let Some(deadline) = policy.overall_deadline() {
+                if started.elapsed() + delay > deadline {
+                    return result.with_context(|| {
+                        format!(
+                            "Task '{}' gave up after {} attempts (deadline {})",
+                            self.name,
+                            attempt,
+                            format_duration(deadline)
+                        )
+                    });
+                }
+            }
+
+            if !policy.is_quiet() {
+                crate::say!(
+                    "   ↻ '{}' failed (attempt {}/{}), retrying in {}",
+                    self.name,
+                    attempt,
+                    policy.max_attempts(),
+                    format_duration(delay)
+                );
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = cancel.cancelled() => bail!("Task '{}' was cancelled", self.name),
+            }
+            attempt += 1;
+        }
+    }
+
+    /// In dry-run mode, record the task in the plan and return the empty,
+    /// successful output it stands in for
+    fn record_dry_run(&self) -> Option<TaskOutput> {
+        let plan = self.plan.as_ref()?;
+        plan.record(PlannedCommand::new(
+            &self.name,
+            &self.command,
+            &self.args,
+            self.working_dir.as_deref(),
+            &self.env_vars,
+            &self.depends_on,
+        ));
+        Some(TaskOutput {
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            duration: Duration::ZERO,
+            attempts: 1,
+        })
+    }
+
+    /// Print the command being run
+    fn print_banner(&self) {
+        output::task_started(
+            &self.name,
+            &self.command,
+            &self.args,
+            self.working_dir.as_deref(),
+        );
+    }
+
+    /// Spawn the process and wait for it. Every child process cwrdd-make runs goes
+    /// through here, apart from [`Task::spawn_detached`].
+    async fn spawn(&self, stream: bool, capture: bool) -> Result<TaskOutput> {
+        let cancel = self.cancel.as_ref().unwrap_or_else(|| shutdown::token());
+        if cancel.is_cancelled() {
+            bail!("Task '{}' was cancelled before it started", self.name);
+        }
+
+        let started = Instant::now();
 
         let mut cmd = Command::new(&self.command);
         cmd.args(&self.args);
 
         if let Some(ref dir) = self.working_dir {
             cmd.current_dir(dir);
-            println!("   Working directory: {}", dir);
         }
 
         for (key, value) in &self.env_vars {
@@ -90,53 +407,185 @@ impl Task {
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
+        if !self.interactive {
+            cmd.process_group(0);
+        }
+        cmd.kill_on_drop(true);
+
         let mut child = cmd
             .spawn()
             .context(format!("Failed to spawn command: {}", self.command))?;
 
-        // Stream stdout
-        if let Some(stdout) = child.stdout.take() {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
+        let stdout = child
+            .stdout
+            .take()
+            .map(|out| tokio::spawn(read_lines(out, self.echo(stream, Stream::Stdout), capture)));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|err| tokio::spawn(read_lines(err, self.echo(stream, Stream::Stderr), capture)));
+
+        // Wait for command to complete, unless it times out or is cancelled first
+        let deadline = async {
+            match self.timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        let stopped = tokio::select! {
+            status = child.wait() => Ok(status.context("Failed to wait for command")?),
+            _ = deadline => Err(format!(
+                "Task '{}' timed out after {}",
+                self.name,
+                format_duration(self.timeout.unwrap_or_default())
+            )),
+            _ = cancel.cancelled() => Err(format!("Task '{}' was cancelled", self.name)),
+        };
 
-            tokio::spawn(async move {
-                while let Ok(Some(line)) = lines.next_line().await {
-                    println!("   {}", line);
+        let status = match stopped {
+            Ok(status) => status,
+            Err(message) => {
+                let signal = if cancel.is_cancelled() {
+                    shutdown::received_signal().unwrap_or(Signal::SIGTERM)
+                } else {
+                    Signal::SIGTERM
+                };
+                self.terminate(&mut child, signal).await;
+                // Output is discarded, so don't wait on pipes a stray grandchild may hold open
+                for handle in [stdout, stderr].into_iter().flatten() {
+                    handle.abort();
                 }
-            });
-        }
+                bail!(message);
+            }
+        };
 
-        // Stream stderr
-        if let Some(stderr) = child.stderr.take() {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
+        // Drain the readers so no trailing output is lost
+        let stdout = match stdout {
+            Some(handle) => handle.await?,
+            None => String::new(),
+        };
+        let stderr = match stderr {
+            Some(handle) => handle.await?,
+            None => String::new(),
+        };
 
-            tokio::spawn(async move {
-                while let Ok(Some(line)) = lines.next_line().await {
-                    eprintln!("   {}", line);
-                }
-            });
-        }
+        Ok(TaskOutput {
+            stdout,
+            stderr,
+            exit_code: status.code(),
+            duration: started.elapsed(),
+            attempts: 1,
+        })
+    }
 
-        // Wait for command to complete
-        let status = child
-            .wait()
-            .await
-            .context("Failed to wait for command")?;
+    /// Where to echo a child's output stream, if `stream` is set
+    fn echo(&self, stream: bool, which: Stream) -> Option<(String, Stream)> {
+        stream.then(|| (self.name.clone(), which))
+    }
 
-        if !status.success() {
-            bail!(
-                "Task '{}' failed with exit code: {}",
-                self.name,
-                status.code().unwrap_or(-1)
-            );
+    /// Send `signal` to the child (and its process group), escalating to SIGKILL
+    /// if it hasn't exited after [`TERMINATE_GRACE`]
+    async fn terminate(&self, child: &mut Child, signal: Signal) {
+        let Some(pid) = child.id().map(|id| Pid::from_raw(id as i32)) else {
+            // Already reaped
+            return;
+        };
+        let send = |signal: Signal| {
+            let _ = if self.interactive {
+                kill(pid, signal)
+            } else {
+                killpg(pid, signal)
+            };
+        };
+
+        send(signal);
+        if tokio::time::timeout(TERMINATE_GRACE, child.wait())
+            .await
+            .is_ok()
+        {
+            if !self.interactive {
+                // The leader exited; make sure nothing else in its group outlives it
+                send(Signal::SIGKILL);
+            }
+            return;
         }
 
-        println!("✅ {}: completed successfully\n", self.name);
-        Ok(())
+        eprintln!(
+            "   ⚠️  '{}' did not exit within {} of {}, sending SIGKILL",
+            self.name,
+            format_duration(TERMINATE_GRACE),
+            signal
+        );
+        send(Signal::SIGKILL);
+        let _ = child.wait().await;
+    }
+}
+
+/// Output captured from a finished [`Task`]
+#[derive(Debug, Clone)]
+pub struct TaskOutput {
+    /// Everything the process wrote to stdout
+    pub stdout: String,
+    /// Everything the process wrote to stderr
+    pub stderr: String,
+    /// Exit code, or `None` if the process was terminated by a signal
+    pub exit_code: Option<i32>,
+    /// Wall-clock time from spawn to exit (of the last attempt, when retried)
+    pub duration: Duration,
+    /// How many times the task was run
+    pub attempts: u32,
+}
+
+impl TaskOutput {
+    /// Whether the process exited with code 0
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
     }
 }
 
+/// Format a duration for messages, e.g. `90s` becomes `1m 30s`
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 60, secs % 60) {
+        (0, 0) => format!("{}ms", duration.as_millis()),
+        (0, s) => format!("{}s", s),
+        (m, 0) => format!("{}m", m),
+        (m, s) => format!("{}m {}s", m, s),
+    }
+}
+
+/// Describe the number of attempts for messages, or nothing for a single attempt
+fn attempts_suffix(attempts: u32) -> String {
+    if attempts > 1 {
+        format!(" (after {} attempts)", attempts)
+    } else {
+        String::new()
+    }
+}
+
+/// Read a child's output line by line, optionally echoing (as the given task
+/// and stream) and collecting it
+async fn read_lines(
+    reader: impl AsyncRead + Unpin,
+    echo: Option<(String, Stream)>,
+    capture: bool,
+) -> String {
+    let mut lines = BufReader::new(reader).lines();
+    let mut captured = String::new();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some((ref name, stream)) = echo {
+            output::task_line(name, stream, &line);
+        }
+        if capture {
+            captured.push_str(&line);
+            captured.push('\n');
+        }
+    }
+
+    captured
+}
+
 /// Run a sequence of tasks
 pub async fn run_tasks(tasks: Vec<Task>) -> Result<()> {
     for task in tasks {
@@ -146,12 +595,17 @@ pub async fn run_tasks(tasks: Vec<Task>) -> Result<()> {
 }
 
 /// Check if a command exists on the system
+///
+/// Searches `PATH` directly rather than spawning `which`.
 pub fn command_exists(command: &str) -> bool {
-    std::process::Command::new("which")
-        .arg(command)
-        .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false)
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| {
+        std::fs::metadata(dir.join(command))
+            .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    })
 }
 
 /// Verify working directory exists
@@ -176,6 +630,196 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_capture_output() {
+        let output = Task::new("test capture", "sh")
+            .args(["-c", "echo out; echo err >&2; exit 3"])
+            .capture()
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(output.exit_code, Some(3));
+        assert!(!output.success());
+    }
+
+    #[tokio::test]
+    async fn test_capture_while_streaming() {
+        let output = Task::new("test stream", "echo")
+            .args(["hello"])
+            .env("UNUSED", "value")
+            .stream(true)
+            .capture()
+            .await
+            .unwrap();
+        assert!(output.success());
+        assert_eq!(output.stdout.trim(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_failing_task_errors() {
+        let result = Task::new("test false", "false").execute().await;
+        assert!(result.unwrap_err().to_string().contains("exit code: 1"));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_kills_process_group() {
+        let started = Instant::now();
+        // The backgrounded sleep holds stdout open, so this only returns
+        // promptly if the whole process group is killed
+        let result = Task::new("test timeout", "sh")
+            .args(["-c", "sleep 30 & wait"])
+            .timeout(Duration::from_millis(200))
+            .capture()
+            .await;
+
+        let err = result.unwrap_err().to_string();
+        assert_eq!(err, "Task 'test timeout' timed out after 200ms");
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_escalates_to_sigkill() {
+        let started = Instant::now();
+        let result = Task::new("test ignore term", "sh")
+            .args(["-c", "trap '' TERM; sleep 30"])
+            .timeout(Duration::from_millis(200))
+            .execute()
+            .await;
+
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+        assert!(started.elapsed() >= TERMINATE_GRACE);
+        assert!(started.elapsed() < TERMINATE_GRACE + Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation() {
+        let token = CancellationToken::new();
+        let task = Task::new("test cancel", "sleep")
+            .args(["30"])
+            .cancel_token(token.clone());
+
+        let canceller = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            token.cancel();
+        });
+        let result = task.execute().await;
+        canceller.await.unwrap();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Task 'test cancel' was cancelled"
+        );
+        // Once cancelled, the task refuses to start again
+        assert!(task
+            .execute()
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("before it started"));
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_success() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        // Fails on the first attempt, succeeds once the marker exists
+        let script = format!("test -f {0} || {{ touch {0}; exit 1; }}", marker.display());
+
+        let output = Task::new("test retry", "sh")
+            .args(["-c".to_string(), script])
+            .retry(RetryPolicy::fixed(3, Duration::from_millis(10)).quiet())
+            .capture()
+            .await
+            .unwrap();
+        assert!(output.success());
+        assert_eq!(output.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_attempts() {
+        let result = Task::new("test always fails", "false")
+            .retry(RetryPolicy::exponential(
+                3,
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+            ))
+            .execute()
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Task 'test always fails' failed with exit code: 1 (after 3 attempts)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_respects_deadline() {
+        let started = Instant::now();
+        let output = Task::new("test deadline", "false")
+            .retry(
+                RetryPolicy::fixed(100, Duration::from_millis(100))
+                    .deadline(Duration::from_millis(250)),
+            )
+            .capture()
+            .await
+            .unwrap();
+        assert!(!output.success());
+        assert!(output.attempts < 5);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_records_instead_of_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        let plan = Plan::new();
+
+        let task = Task::new("touch marker", "touch")
+            .args([marker.to_string_lossy().to_string()])
+            .env("PGPASSWORD", "secret")
+            .dry_run(Some(plan.clone()));
+        task.execute().await.unwrap();
+        let output = task.capture().await.unwrap();
+
+        assert!(output.success());
+        assert!(!marker.exists());
+        let steps = plan.steps();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].command, "touch");
+        assert_eq!(steps[0].env["PGPASSWORD"], crate::plan::REDACTED);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_detached_does_not_wait() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        let started = Instant::now();
+        Task::new("sleep then touch", "sh")
+            .args(["-c", &format!("sleep 0.3 && touch {}", marker.display())])
+            .spawn_detached()
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_millis(250));
+        assert!(!marker.exists());
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(marker.exists());
+
+        let plan = Plan::new();
+        Task::new("open docs", "xdg-open")
+            .dry_run(Some(plan.clone()))
+            .spawn_detached()
+            .unwrap();
+        assert_eq!(plan.steps()[0].command, "xdg-open");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(120)), "2m");
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m 30s");
+    }
+
     #[test]
     fn test_command_exists() {
         assert!(command_exists("echo"));
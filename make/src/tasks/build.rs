@@ -1,11 +1,29 @@
 use crate::config::Config;
+use crate::say;
 use crate::task::{command_exists, run_tasks, verify_directory, Task};
 use anyhow::{bail, Result};
 
 /// Run the build task
 pub async fn run(config: &Config) -> Result<()> {
-    println!("🏗️  Building cwrdd application\n");
+    say!("🏗️  Building cwrdd application\n");
 
+    let build = build_task(config)?;
+
+    say!("Repository path: {}", config.repo_path.display());
+    say!("App path: {}\n", config.app_path().display());
+
+    // Create build tasks
+    let tasks = vec![build];
+
+    // Run tasks
+    run_tasks(tasks).await?;
+
+    say!("🎉 Build completed successfully!");
+    Ok(())
+}
+
+/// Create the `cargo build` task for the app, checking cargo and the app directory exist
+pub fn build_task(config: &Config) -> Result<Task> {
     // Verify cargo exists
     if !command_exists("cargo") {
         bail!("cargo is not installed. Please install Rust: https://rustup.rs/");
@@ -15,21 +33,9 @@ pub async fn run(config: &Config) -> Result<()> {
     let app_path = config.app_path();
     verify_directory(&app_path)?;
 
-    println!("Repository path: {}", config.repo_path.display());
-    println!("App path: {}\n", app_path.display());
-
-    // Create build tasks
-    let tasks = vec![
-        Task::new("cargo build", "cargo")
-            .args(["build", "--workspace"])
-            .working_dir(app_path.to_string_lossy().to_string()),
-    ];
-
-    // Run tasks
-    run_tasks(tasks).await?;
-
-    println!("🎉 Build completed successfully!");
-    Ok(())
+    Ok(config.task("cargo build", "cargo")
+        .args(["build", "--workspace"])
+        .working_dir(app_path.to_string_lossy().to_string()))
 }
 
 #[cfg(test)]
@@ -46,9 +52,32 @@ mod tests {
     async fn test_build_with_nonexistent_directory() {
         let config = Config {
             repo_path: PathBuf::from("/nonexistent-directory-12345"),
+            ..Config::default()
         };
 
         let result = run(&config).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_dry_run_records_build() {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir(repo.path().join("app")).unwrap();
+        let plan = crate::plan::Plan::new();
+        let config = Config {
+            repo_path: repo.path().to_path_buf(),
+            dry_run: Some(plan.clone()),
+            ..Config::default()
+        };
+
+        run(&config).await.unwrap();
+
+        let steps = plan.steps();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].args, ["build", "--workspace"]);
+        assert_eq!(
+            steps[0].working_dir.as_deref(),
+            Some(repo.path().join("app").to_str().unwrap())
+        );
+    }
 }
@@ -1,64 +1,673 @@
+//! Dev TLS certificates (`trust-cert`, `untrust-cert`, `cert info`, and the
+//! check in `up`).
+//!
+//! The app's certificate is issued by the local development CA (see
+//! [`crate::ca`]), which is the only thing the system trust store needs. `up`
+//! reissues the certificate when it has expired or no longer covers the names
+//! in `[certs]`, and warns when it or the CA expires within `warn_days`.
+
+use crate::ca::{parse_pem, Authority, CertPaths, CA_NAME};
 use crate::config::Config;
-use crate::task::Task;
-use anyhow::{bail, Result};
+use crate::output;
+use crate::say;
+use crate::task::command_exists;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use x509_parser::extensions::GeneralName;
 
-/// Trust the self-signed certificate locally (Linux only)
-pub async fn trust(config: &Config) -> Result<()> {
-    println!("🔐 Trusting self-signed certificate locally\n");
+/// Where `trust-cert` installs the CA on Debian-based systems
+const SYSTEM_CA_PATH: &str = "/usr/local/share/ca-certificates/cwrdd-dev-ca.crt";
 
-    let cert_path = config.repo_path.join("config/certs/cert.pem");
+/// Where older versions installed the self-signed dev certificate
+const LEGACY_SYSTEM_CERT_PATH: &str = "/usr/local/share/ca-certificates/cwrdd-dev.crt";
 
-    if !cert_path.exists() {
-        bail!(
-            "Certificate not found at {:?}\n\
-             Run 'cwrdd-make up' first to generate certificates.",
-            cert_path
-        );
+/// Details read from a PEM certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CertificateInfo {
+    /// Subject distinguished name
+    pub subject: String,
+    /// Issuer distinguished name
+    pub issuer: String,
+    /// DNS names and IP addresses the certificate covers
+    pub subject_alt_names: Vec<String>,
+    /// SHA-256 of the DER encoding, as colon-separated hex
+    pub fingerprint: String,
+    /// Start of the validity period
+    pub not_before: DateTime<Utc>,
+    /// End of the validity period
+    pub not_after: DateTime<Utc>,
+}
+
+/// Read the first certificate in a PEM file
+pub fn read_certificate(path: &Path) -> Result<CertificateInfo> {
+    let pem = std::fs::read(path)
+        .context(format!("Failed to read certificate: {}", path.display()))?;
+    parse_certificate(&pem).context(format!("Invalid certificate: {}", path.display()))
+}
+
+/// Parse the first certificate in PEM text
+pub fn parse_certificate(pem: &[u8]) -> Result<CertificateInfo> {
+    let pem = parse_pem(pem)?;
+    let cert = pem.parse_x509().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let mut subject_alt_names = Vec::new();
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        for name in &san.value.general_names {
+            match name {
+                GeneralName::DNSName(dns) => subject_alt_names.push(dns.to_string()),
+                GeneralName::IPAddress(bytes) => {
+                    let ip = match bytes.len() {
+                        4 => <[u8; 4]>::try_from(*bytes).ok().map(IpAddr::from),
+                        16 => <[u8; 16]>::try_from(*bytes).ok().map(IpAddr::from),
+                        _ => None,
+                    };
+                    subject_alt_names.extend(ip.map(|ip| ip.to_string()));
+                }
+                _ => {}
+            }
+        }
     }
 
-    let dest_path = "/usr/local/share/ca-certificates/cwrdd-dev.crt";
+    let timestamp = |time: x509_parser::time::ASN1Time| {
+        DateTime::from_timestamp(time.timestamp(), 0).context("Certificate date is out of range")
+    };
+    let fingerprint = Sha256::digest(&pem.contents)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":");
+    Ok(CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        subject_alt_names,
+        fingerprint,
+        not_before: timestamp(cert.validity().not_before)?,
+        not_after: timestamp(cert.validity().not_after)?,
+    })
+}
+
+/// Why `cert` has to be reissued for `names` at `now`, if it does
+pub fn renewal_reason(
+    cert: &CertificateInfo,
+    names: &[String],
+    now: DateTime<Utc>,
+) -> Option<String> {
+    if cert.not_after <= now {
+        return Some(format!("expired on {}", cert.not_after.format("%Y-%m-%d")));
+    }
+
+    let mut covered: Vec<&String> = cert.subject_alt_names.iter().collect();
+    let mut wanted: Vec<&String> = names.iter().collect();
+    covered.sort();
+    wanted.sort();
+    (covered != wanted).then(|| {
+        format!(
+            "covers {} but [certs] asks for {}",
+            cert.subject_alt_names.join(", "),
+            names.join(", ")
+        )
+    })
+}
+
+/// A warning if `not_after` is past or within `warn_days` of `now`
+pub fn expiry_warning(
+    not_after: DateTime<Utc>,
+    warn_days: u32,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let days_left = (not_after - now).num_days();
+    let expiry = not_after.format("%Y-%m-%d");
+    if not_after <= now {
+        Some(format!("expired on {}", expiry))
+    } else if days_left < i64::from(warn_days) {
+        Some(format!("expires on {} ({} days left)", expiry, days_left))
+    } else {
+        None
+    }
+}
 
-    println!("📋 Installing certificate to system trust store...");
-    println!("   This requires sudo access.\n");
+/// Make sure the local CA exists and the dev certificate was issued by it,
+/// creating or reissuing them as needed. Returns whether a certificate was
+/// (or in a dry run, would be) issued, so a running app can be restarted.
+pub fn ensure(config: &Config) -> Result<bool> {
+    let paths = config.cert_paths();
+    let Some(authority) = authority(config, &paths)? else {
+        let names = config.certs.subject_alt_names();
+        say!("📝 Would issue a dev certificate for {}\n", names.join(", "));
+        return Ok(true);
+    };
 
-    // Copy cert to ca-certificates directory
-    let copy_task = Task::new("copy cert", "sudo")
-        .args(["cp", cert_path.to_str().unwrap(), dest_path]);
-    copy_task.execute().await?;
+    if paths.cert.exists() && paths.key.exists() {
+        let cert = std::fs::read_to_string(&paths.cert)
+            .context(format!("Failed to read certificate: {}", paths.cert.display()))?;
+        let now = Utc::now();
+        if !authority.issued(&cert).unwrap_or(false) {
+            say!("🔐 {} wasn't issued by the local CA, replacing it", paths.cert.display());
+        } else {
+            let info = parse_certificate(cert.as_bytes())?;
+            let names = config.certs.subject_alt_names();
+            match renewal_reason(&info, &names, now) {
+                Some(reason) => say!("🔐 The dev certificate {}, reissuing it", reason),
+                None => {
+                    if let Some(warning) =
+                        expiry_warning(info.not_after, config.certs.warn_days, now)
+                    {
+                        say!(
+                            "⚠️  The dev certificate {}; `up` reissues it once it expires, \
+                             or delete {} to reissue it now",
+                            warning,
+                            paths.cert.display()
+                        );
+                    }
+                    say!("✅ TLS certificates already exist\n");
+                    output::record("certificate_issued", false);
+                    return Ok(false);
+                }
+            }
+        }
+    }
 
-    // Update ca-certificates
-    let update_task = Task::new("update ca-certificates", "sudo")
-        .args(["update-ca-certificates"]);
-    update_task.execute().await?;
+    issue(config, &paths, &authority)?;
+    say!();
+    Ok(true)
+}
 
-    println!("\n✅ Certificate trusted system-wide!");
-    println!("\n⚠️  Note: Some browsers maintain their own certificate stores.");
-    println!("   For Firefox: Settings > Privacy & Security > Certificates > View Certificates > Import");
-    println!("   For Chrome/Chromium: The system store should work, restart browser if needed.");
-    println!("\n   Certificate location: {:?}", cert_path);
+/// Issue a new dev certificate from `authority` for the configured names
+fn issue(config: &Config, paths: &CertPaths, authority: &Authority) -> Result<()> {
+    let names = config.certs.subject_alt_names();
+    if config.is_dry_run() {
+        say!("📝 Would issue a dev certificate for {}", names.join(", "));
+        return Ok(());
+    }
 
+    let (cert, key) = authority.issue(&names, config.certs.leaf_days, Utc::now())?;
+    crate::ca::write_file(&paths.key, &key, true)?;
+    crate::ca::write_file(&paths.cert, &cert, false)?;
+    output::record("certificate_issued", true);
+    say!(
+        "🔐 Issued dev certificate for {} (valid {} days)",
+        names.join(", "),
+        config.certs.leaf_days
+    );
     Ok(())
 }
 
-/// Remove trust for the self-signed certificate
-pub async fn untrust(_config: &Config) -> Result<()> {
-    println!("🔓 Removing trust for self-signed certificate\n");
+/// The local CA, created first if it doesn't exist yet; `None` in a dry run that would create it
+fn authority(config: &Config, paths: &CertPaths) -> Result<Option<Authority>> {
+    if paths.ca_cert.exists() && paths.ca_key.exists() {
+        let authority = Authority::load(&paths.ca_cert, &paths.ca_key)?;
+        let ca = parse_certificate(authority.cert_pem().as_bytes())?;
+        if let Some(warning) = expiry_warning(ca.not_after, config.certs.warn_days, Utc::now()) {
+            say!(
+                "⚠️  The local development CA {}. Delete {} and run `cwrdd-make trust-cert` \
+                 to replace it",
+                warning,
+                dir(&paths.ca_cert)
+            );
+        }
+        return Ok(Some(authority));
+    }
+    if config.is_dry_run() {
+        say!("📝 Would create the local development CA in {}", dir(&paths.ca_cert));
+        return Ok(None);
+    }
+
+    let authority = Authority::generate(Utc::now())?;
+    authority.save(&paths.ca_cert, &paths.ca_key)?;
+    output::record("ca_created", true);
+    say!("🔐 Created the local development CA in {}", dir(&paths.ca_cert));
+    say!("   Run `cwrdd-make trust-cert` once so browsers accept certificates it issues");
+    Ok(Some(authority))
+}
+
+fn dir(path: &Path) -> String {
+    path.parent().unwrap_or(path).display().to_string()
+}
 
-    let dest_path = "/usr/local/share/ca-certificates/cwrdd-dev.crt";
+/// What happened in one trust store: whether it trusts the CA (`cert info`),
+/// or whether adding or removing it worked (`trust-cert`, `untrust-cert`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoreResult {
+    /// Name of the trust store
+    pub store: String,
+    pub ok: bool,
+    /// What was found or done, or what to do about it
+    pub detail: String,
+}
 
-    if std::path::Path::new(dest_path).exists() {
-        let remove_task = Task::new("remove cert", "sudo")
-            .args(["rm", dest_path]);
-        remove_task.execute().await?;
+impl StoreResult {
+    fn new(store: &str, ok: bool, detail: impl Into<String>) -> Self {
+        Self {
+            store: store.to_string(),
+            ok,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// An NSS certificate database (`cert9.db`), as used by Firefox and Chromium
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NssStore {
+    /// Browser and profile, e.g. `Firefox (abcd.default-release)`
+    pub name: String,
+    /// Directory containing `cert9.db`
+    pub dir: PathBuf,
+}
+
+impl NssStore {
+    /// `-d` argument for certutil
+    fn database(&self) -> String {
+        format!("sql:{}", self.dir.display())
+    }
+}
+
+/// The NSS databases under `home`: Chromium's shared `~/.pki/nssdb` and every
+/// Firefox profile, including the snap and flatpak installs
+pub fn nss_stores(home: &Path) -> Vec<NssStore> {
+    let mut stores = Vec::new();
+    let chromium = home.join(".pki/nssdb");
+    if chromium.join("cert9.db").is_file() {
+        stores.push(NssStore {
+            name: "Chromium (~/.pki/nssdb)".to_string(),
+            dir: chromium,
+        });
+    }
+
+    for (browser, profiles) in [
+        ("Firefox", ".mozilla/firefox"),
+        ("Firefox snap", "snap/firefox/common/.mozilla/firefox"),
+        ("Firefox flatpak", ".var/app/org.mozilla.firefox/.mozilla/firefox"),
+    ] {
+        let Ok(entries) = std::fs::read_dir(home.join(profiles)) else {
+            continue;
+        };
+        let mut dirs: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|dir| dir.join("cert9.db").is_file())
+            .collect();
+        dirs.sort();
+        stores.extend(dirs.into_iter().map(|dir| {
+            let profile = dir.file_name().unwrap_or_default().to_string_lossy();
+            NssStore {
+                name: format!("{} ({})", browser, profile),
+                dir,
+            }
+        }));
+    }
+    stores
+}
+
+fn home_nss_stores() -> Vec<NssStore> {
+    std::env::var_os("HOME")
+        .map(|home| nss_stores(Path::new(&home)))
+        .unwrap_or_default()
+}
+
+const CERTUTIL_MISSING: &str =
+    "certutil not found; install libnss3-tools (Debian/Ubuntu) or nss-tools (Fedora)";
+
+/// The CA's fingerprint in `store`, if it holds a certificate under the CA's name
+async fn nss_fingerprint(config: &Config, store: &NssStore) -> Option<String> {
+    let output = config.task(format!("certutil list {}", store.name), "certutil")
+        .args(["-d", &store.database(), "-L", "-n", CA_NAME, "-a"])
+        .capture()
+        .await
+        .ok()?;
+    if !output.success() {
+        return None;
+    }
+    parse_certificate(output.stdout.as_bytes())
+        .ok()
+        .map(|cert| cert.fingerprint)
+}
+
+/// Which trust stores have the CA in `ca_pem`
+pub async fn trust_status(config: &Config, ca_pem: &str) -> Result<Vec<StoreResult>> {
+    let fingerprint = parse_certificate(ca_pem.as_bytes())?.fingerprint;
+    let mut results = vec![match std::fs::read_to_string(SYSTEM_CA_PATH) {
+        Ok(installed) if installed.trim() == ca_pem.trim() => {
+            StoreResult::new("system", true, format!("installed at {}", SYSTEM_CA_PATH))
+        }
+        Ok(_) => StoreResult::new(
+            "system",
+            false,
+            "a different cwrdd CA is installed; run `cwrdd-make trust-cert`",
+        ),
+        Err(_) => StoreResult::new("system", false, "not installed; run `cwrdd-make trust-cert`"),
+    }];
+
+    let certutil = command_exists("certutil");
+    for store in home_nss_stores() {
+        let result = if !certutil {
+            StoreResult::new(&store.name, false, CERTUTIL_MISSING)
+        } else {
+            match nss_fingerprint(config, &store).await {
+                Some(found) if found == fingerprint => {
+                    StoreResult::new(&store.name, true, "trusted")
+                }
+                Some(_) => StoreResult::new(
+                    &store.name,
+                    false,
+                    "a different cwrdd CA is installed; run `cwrdd-make trust-cert`",
+                ),
+                None => StoreResult::new(
+                    &store.name,
+                    false,
+                    "not installed; run `cwrdd-make trust-cert`",
+                ),
+            }
+        };
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Print one line per store, record them, and fail if any store failed
+fn report(results: &[StoreResult]) -> Result<()> {
+    say!();
+    for result in results {
+        let icon = if result.ok { "✅" } else { "❌" };
+        say!("{} {}: {}", icon, result.store, result.detail);
+    }
+    output::record("stores", serde_json::to_value(results)?);
+
+    let failed = results.iter().filter(|r| !r.ok).count();
+    if failed > 0 {
+        bail!("{} of {} trust store(s) failed", failed, results.len());
+    }
+    Ok(())
+}
 
-        let update_task = Task::new("update ca-certificates", "sudo")
-            .args(["update-ca-certificates", "--fresh"]);
-        update_task.execute().await?;
+/// Print the subject, SANs, fingerprint, expiry and trust of the dev certificate and CA
+pub async fn info(config: &Config) -> Result<()> {
+    let paths = config.cert_paths();
+    let now = Utc::now();
+    let warn_days = config.certs.warn_days;
 
-        println!("✅ Certificate removed from system trust store");
+    say!("🔐 Dev certificate: {}\n", paths.cert.display());
+    let cert = if paths.cert.exists() {
+        let cert = read_certificate(&paths.cert)?;
+        print_certificate(&cert, warn_days, now);
+        match renewal_reason(&cert, &config.certs.subject_alt_names(), now) {
+            Some(reason) => say!("   ⚠️  It {}; `up` will reissue it", reason),
+            None => say!("   ✅ Matches [certs]"),
+        }
+        Some(cert)
     } else {
-        println!("ℹ️  Certificate was not installed in system trust store");
+        say!("   Not generated yet; `cwrdd-make up` creates it");
+        None
+    };
+
+    say!("\n🏛️  Local CA: {}\n", paths.ca_cert.display());
+    let (ca, trust) = if paths.ca_cert.exists() {
+        let pem = std::fs::read_to_string(&paths.ca_cert)
+            .context(format!("Failed to read CA certificate: {}", paths.ca_cert.display()))?;
+        let ca = parse_certificate(pem.as_bytes())
+            .context(format!("Invalid certificate: {}", paths.ca_cert.display()))?;
+        print_certificate(&ca, warn_days, now);
+        if let Some(cert) = &cert {
+            if cert.issuer == ca.subject {
+                say!("   ✅ Issued the dev certificate");
+            } else {
+                say!("   ⚠️  Didn't issue the dev certificate; `up` will reissue it");
+            }
+        }
+
+        let trust = trust_status(config, &pem).await?;
+        say!();
+        for store in &trust {
+            let icon = if store.ok { "✅" } else { "❌" };
+            say!("   {} {} trust store: {}", icon, store.store, store.detail);
+        }
+        (Some(ca), trust)
+    } else {
+        say!("   Not created yet; `cwrdd-make up` or `cwrdd-make trust-cert` creates it");
+        (None, Vec::new())
+    };
+
+    output::record("certificate", serde_json::to_value(&cert)?);
+    output::record("ca", serde_json::to_value(&ca)?);
+    output::record("trust", serde_json::to_value(&trust)?);
+    Ok(())
+}
+
+fn print_certificate(cert: &CertificateInfo, warn_days: u32, now: DateTime<Utc>) {
+    say!("   Subject:      {}", cert.subject);
+    say!("   Issuer:       {}", cert.issuer);
+    if !cert.subject_alt_names.is_empty() {
+        say!("   SANs:         {}", cert.subject_alt_names.join(", "));
     }
+    say!("   SHA-256:      {}", cert.fingerprint);
+    say!(
+        "   Valid:        {} to {}",
+        cert.not_before.format("%Y-%m-%d %H:%M UTC"),
+        cert.not_after.format("%Y-%m-%d %H:%M UTC")
+    );
+    match expiry_warning(cert.not_after, warn_days, now) {
+        Some(warning) => say!("   ⚠️  {}", warning[..1].to_uppercase() + &warning[1..]),
+        None => say!("   Expires in {} days", (cert.not_after - now).num_days()),
+    }
+}
+
+/// Trust the local development CA in the system store and every browser NSS database (Linux only)
+pub async fn trust(config: &Config) -> Result<()> {
+    say!("🔐 Trusting the local development CA\n");
+
+    let paths = config.cert_paths();
+    if authority(config, &paths)?.is_none() {
+        return Ok(());
+    }
+
+    say!("📋 Installing {} to the system trust store...", CA_NAME);
+    say!("   This requires sudo access.\n");
+    let mut results = vec![match trust_system(config, &paths.ca_cert).await {
+        Ok(()) => StoreResult::new("system", true, format!("installed at {}", SYSTEM_CA_PATH)),
+        Err(e) => StoreResult::new("system", false, format!("{:#}", e)),
+    }];
 
+    let stores = home_nss_stores();
+    let certutil = command_exists("certutil");
+    for store in &stores {
+        let result = if !certutil {
+            StoreResult::new(&store.name, false, CERTUTIL_MISSING)
+        } else {
+            match trust_nss(config, store, &paths.ca_cert).await {
+                Ok(()) => StoreResult::new(&store.name, true, "trusted"),
+                Err(e) => StoreResult::new(&store.name, false, format!("{:#}", e)),
+            }
+        };
+        results.push(result);
+    }
+
+    report(&results)?;
+    if stores.is_empty() {
+        say!("\nℹ️  No Firefox or Chromium certificate databases found.");
+    }
+    say!("   Browsers started for the first time later need `cwrdd-make trust-cert` again;");
+    say!("   restart any that are open. Reissued dev certificates are trusted automatically.");
+    say!("\n   CA certificate: {}", paths.ca_cert.display());
     Ok(())
 }
+
+async fn trust_system(config: &Config, ca_cert: &Path) -> Result<()> {
+    config.task("copy CA", "sudo")
+        .args(["cp", ca_cert.to_str().unwrap(), SYSTEM_CA_PATH])
+        .interactive()
+        .execute()
+        .await?;
+
+    if Path::new(LEGACY_SYSTEM_CERT_PATH).exists() {
+        config.task("remove old cert", "sudo")
+            .args(["rm", LEGACY_SYSTEM_CERT_PATH])
+            .interactive()
+            .execute()
+            .await?;
+    }
+
+    config.task("update ca-certificates", "sudo")
+        .args(["update-ca-certificates"])
+        .interactive()
+        .execute()
+        .await
+}
+
+/// Add the CA to `store` as a trusted issuer of server certificates,
+/// replacing any older cwrdd CA
+async fn trust_nss(config: &Config, store: &NssStore, ca_cert: &Path) -> Result<()> {
+    if nss_fingerprint(config, store).await.is_some() {
+        untrust_nss(config, store).await?;
+    }
+    config.task(format!("certutil add {}", store.name), "certutil")
+        .args([
+            "-d",
+            &store.database(),
+            "-A",
+            "-t",
+            "C,,",
+            "-n",
+            CA_NAME,
+            "-i",
+            ca_cert.to_str().unwrap(),
+        ])
+        .execute()
+        .await
+}
+
+async fn untrust_nss(config: &Config, store: &NssStore) -> Result<()> {
+    config.task(format!("certutil delete {}", store.name), "certutil")
+        .args(["-d", &store.database(), "-D", "-n", CA_NAME])
+        .execute()
+        .await
+}
+
+/// Remove the local development CA from the system store and every browser NSS database
+pub async fn untrust(config: &Config) -> Result<()> {
+    say!("🔓 Removing trust for the local development CA\n");
+
+    let installed: Vec<&str> = [SYSTEM_CA_PATH, LEGACY_SYSTEM_CERT_PATH]
+        .into_iter()
+        .filter(|path| Path::new(path).exists())
+        .collect();
+    let mut results = vec![if installed.is_empty() {
+        StoreResult::new("system", true, "not installed")
+    } else {
+        match untrust_system(config, &installed).await {
+            Ok(()) => StoreResult::new("system", true, "removed"),
+            Err(e) => StoreResult::new("system", false, format!("{:#}", e)),
+        }
+    }];
+
+    let certutil = command_exists("certutil");
+    for store in home_nss_stores() {
+        let result = if !certutil {
+            StoreResult::new(&store.name, false, CERTUTIL_MISSING)
+        } else if nss_fingerprint(config, &store).await.is_none() {
+            StoreResult::new(&store.name, true, "not installed")
+        } else {
+            match untrust_nss(config, &store).await {
+                Ok(()) => StoreResult::new(&store.name, true, "removed"),
+                Err(e) => StoreResult::new(&store.name, false, format!("{:#}", e)),
+            }
+        };
+        results.push(result);
+    }
+
+    report(&results)
+}
+
+async fn untrust_system(config: &Config, installed: &[&str]) -> Result<()> {
+    for path in installed {
+        config.task("remove cert", "sudo")
+            .args(["rm", path])
+            .interactive()
+            .execute()
+            .await?;
+    }
+
+    config.task("update ca-certificates", "sudo")
+        .args(["update-ca-certificates", "--fresh"])
+        .interactive()
+        .execute()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn issued(names: &[&str], days: u32, now: DateTime<Utc>) -> CertificateInfo {
+        let ca = Authority::generate(now).unwrap();
+        let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+        let (cert, _) = ca.issue(&names, days, now).unwrap();
+        parse_certificate(cert.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_certificate() {
+        let now = Utc::now();
+        let cert = issued(&["cwrdd.localhost", "127.0.0.1", "::1"], 30, now);
+        assert_eq!(cert.subject, "CN=cwrdd.localhost, O=cwrdd-dev");
+        assert_eq!(cert.issuer, format!("CN={}, O=cwrdd-dev", CA_NAME));
+        assert_eq!(cert.subject_alt_names, ["cwrdd.localhost", "127.0.0.1", "::1"]);
+        assert_eq!(cert.fingerprint.split(':').count(), 32);
+        assert_eq!((cert.not_after - now).num_days(), 29);
+    }
+
+    #[test]
+    fn test_renewal_reason() {
+        let now = Utc::now();
+        let cert = issued(&["localhost", "127.0.0.1"], 30, now);
+        let names = ["127.0.0.1", "localhost"].map(String::from);
+        assert_eq!(renewal_reason(&cert, &names, now), None);
+
+        let later = now + Duration::days(31);
+        assert!(renewal_reason(&cert, &names, later).unwrap().starts_with("expired on"));
+
+        let more = ["localhost", "127.0.0.1", "devbox.lan"].map(String::from);
+        let reason = renewal_reason(&cert, &more, now).unwrap();
+        assert!(reason.contains("asks for localhost, 127.0.0.1, devbox.lan"));
+    }
+
+    #[test]
+    fn test_nss_stores() {
+        let home = tempfile::tempdir().unwrap();
+        let db = |dir: &str| {
+            let dir = home.path().join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("cert9.db"), "").unwrap();
+        };
+        db(".pki/nssdb");
+        db(".mozilla/firefox/b2.default-release");
+        db(".mozilla/firefox/a1.work");
+        db("snap/firefox/common/.mozilla/firefox/c3.default");
+        std::fs::create_dir_all(home.path().join(".mozilla/firefox/Crash Reports")).unwrap();
+
+        let names: Vec<String> = nss_stores(home.path()).into_iter().map(|s| s.name).collect();
+        assert_eq!(
+            names,
+            [
+                "Chromium (~/.pki/nssdb)",
+                "Firefox (a1.work)",
+                "Firefox (b2.default-release)",
+                "Firefox snap (c3.default)",
+            ]
+        );
+        assert!(nss_stores(&home.path().join("nobody")).is_empty());
+    }
+
+    #[test]
+    fn test_expiry_warning() {
+        let now = Utc::now();
+        assert_eq!(expiry_warning(now + Duration::days(30), 14, now), None);
+        let soon = expiry_warning(now + Duration::days(10) + Duration::hours(1), 14, now);
+        assert!(soon.unwrap().ends_with("(10 days left)"));
+        assert!(expiry_warning(now - Duration::days(1), 14, now)
+            .unwrap()
+            .starts_with("expired on"));
+    }
+}
@@ -1,44 +1,44 @@
+use crate::changelog;
 use crate::config::Config;
-use crate::task::{command_exists, Task};
+use crate::graph::TaskGraph;
+use crate::output;
+use crate::probe;
+use crate::retry::RetryPolicy;
+use crate::say;
+use crate::services::{ComposeFile, Selection};
+use crate::task::command_exists;
 use anyhow::{bail, Context, Result};
 use std::time::Duration;
-use tokio::time::sleep;
 
-/// Ensure self-signed TLS certificates exist
-async fn ensure_certs(config: &Config) -> Result<()> {
-    let certs_dir = config.repo_path.join("config/certs");
-    let cert_path = certs_dir.join("cert.pem");
-    let key_path = certs_dir.join("key.pem");
+/// The services `selection` picks from compose.yaml; `up` passes
+/// `with_dependencies` to also start whatever they depend on
+pub fn select(
+    config: &Config,
+    selection: &Selection,
+    with_dependencies: bool,
+) -> Result<Vec<String>> {
+    ComposeFile::load(&config.repo_path.join("compose.yaml"))?
+        .select(selection, &config.groups, with_dependencies)
+}
 
-    if cert_path.exists() && key_path.exists() {
-        println!("✅ TLS certificates already exist\n");
-        return Ok(());
+/// Compose arguments naming the selected services; none when everything is selected,
+/// so compose acts on the whole file as before
+fn service_args(selection: &Selection, services: &[String]) -> Vec<String> {
+    if selection.is_all() {
+        Vec::new()
+    } else {
+        services.to_vec()
     }
-
-    println!("🔐 Generating self-signed TLS certificates...");
-
-    // Create certs directory if it doesn't exist
-    std::fs::create_dir_all(&certs_dir)?;
-
-    let cert_task = Task::new("generate certs", "openssl")
-        .args([
-            "req", "-x509", "-newkey", "rsa:4096",
-            "-keyout", key_path.to_str().unwrap(),
-            "-out", cert_path.to_str().unwrap(),
-            "-days", "365",
-            "-nodes",
-            "-subj", "/CN=localhost/O=cwrdd-dev",
-            "-addext", "subjectAltName=DNS:localhost,IP:127.0.0.1",
-        ]);
-    cert_task.execute().await?;
-
-    println!("✅ TLS certificates generated\n");
-    Ok(())
 }
 
-/// Start the local development environment
-pub async fn up(config: &Config, recreate: bool) -> Result<()> {
-    println!("🚀 Starting local development environment\n");
+/// Start the local development environment, or the services picked by `selection`
+pub async fn up(config: &Config, recreate: bool, selection: &Selection) -> Result<()> {
+    say!("🚀 Starting local development environment (profile: {})\n", config.profile_name);
+    let services = select(config, selection, true)?;
+    let selected = |service: &str| services.iter().any(|s| s == service);
+    if !selection.is_all() {
+        say!("📦 Services: {}\n", services.join(", "));
+    }
 
     // Check if podman-compose is installed
     if !command_exists("podman-compose") {
@@ -48,98 +48,198 @@ pub async fn up(config: &Config, recreate: bool) -> Result<()> {
         );
     }
 
-    // Ensure TLS certificates exist
-    ensure_certs(config).await?;
+    let cert_issued = selected("app") && crate::tasks::certs::ensure(config)?;
+    start_graph(config, recreate, selection, &services, cert_issued)?.run().await?;
 
-    // Build the Rust application first
-    println!("🏗️  Building cwrdd application...");
-    crate::tasks::build::run(config).await?;
+    output::record("services", services.clone());
 
-    // Build the Docker image
-    println!("\n🐳 Building Docker image...");
-    let app_path = config.app_path();
-    let docker_build = Task::new("docker build", "podman")
-        .args(["build", "-t", "cwrdd-app:local", "."])
-        .working_dir(app_path.to_string_lossy().to_string());
-    docker_build.execute().await?;
+    say!("\n⏳ Waiting for services to be ready...");
+    wait_until_ready(config, &services).await?;
 
-    // Navigate to repo root and start compose
-    let repo_path = &config.repo_path;
-    
-    println!("📦 Starting containers...");
-    let args = if recreate {
-        vec!["up", "-d", "--force-recreate"]
-    } else {
-        vec!["up", "-d", "--no-recreate"]
-    };
-    let compose_up = Task::new("compose up", "podman-compose")
-        .args(args)
-        .working_dir(repo_path.to_string_lossy().to_string());
-    
-    compose_up.execute().await?;
-
-    println!("\n⏳ Waiting for PostgreSQL to be ready...");
-    
-    // Wait for PostgreSQL to be healthy
-    let max_retries = 30;
-    let mut retries = 0;
-    
-    while retries < max_retries {
-        sleep(Duration::from_secs(2)).await;
-        
-        let check = Task::new("check postgres", "podman")
-            .args([
-                "exec",
-                "cwrdd-postgres",
-                "pg_isready",
-                "-U", "cwrdd_user",
-                "-d", "cwrdd_dev"
-            ]);
-        
-        if check.execute().await.is_ok() {
-            println!("✅ PostgreSQL is ready!\n");
-            break;
-        }
-        
-        retries += 1;
-        if retries == max_retries {
-            bail!("PostgreSQL did not become ready in time. Check logs with: podman-compose logs postgres");
-        }
+    if !selected("postgres") {
+        say!("✅ Selected services are ready!\n");
+        print_access_info(config, &services)?;
+        return Ok(());
     }
 
     // Check if migrations are needed
-    println!("🔍 Checking database migration status...");
+    say!("🔍 Checking database migration status...");
     
     let needs_migration = check_needs_migration(config).await?;
     
+    output::record("migrations_applied", needs_migration);
     if needs_migration {
-        println!("📝 Running database migrations...");
+        say!("📝 Running database migrations...");
         crate::tasks::migrate::apply(config).await?;
     } else {
-        println!("✅ Database is up to date\n");
+        say!("✅ Database is up to date\n");
     }
 
-    // Check if database needs seeding
-    println!("🔍 Checking if database needs seeding...");
-    
-    let needs_seed = check_needs_seed().await?;
+    // Seed the configured sets that haven't been loaded, or have changed since
+    say!("🔍 Checking seed history...");
+
+    let pending_sets = crate::tasks::seed::pending_sets(config).await?;
+
+    output::record("seeded", pending_sets.clone());
+    if pending_sets.is_empty() {
+        say!("✅ Seed data is up to date\n");
+    } else {
+        let options = crate::tasks::seed::SeedOptions {
+            sets: pending_sets,
+            ..Default::default()
+        };
+        crate::tasks::seed::run(config, &options).await?;
+        say!();
+    }
+
+    say!("✅ Development environment is ready!\n");
+    print_access_info(config, &services)?;
+
+    Ok(())
+}
+
+/// Build the app if it is selected, then start the selected services.
+/// `cert_issued` restarts the app, which only reads its certificate at startup.
+fn start_graph(
+    config: &Config,
+    recreate: bool,
+    selection: &Selection,
+    services: &[String],
+    cert_issued: bool,
+) -> Result<TaskGraph> {
+    let profile = config.profile()?;
+    let selected = |service: &str| services.iter().any(|s| s == service);
+
+    // The Rust build and the image build are independent, so they run
+    // concurrently; containers only start once both have succeeded.
+    // Without the app there is nothing to build.
+    let mut graph = TaskGraph::new();
+    let mut compose_deps = Vec::new();
+
+    if selected("app") {
+        say!("🏗️  Building cwrdd application and Docker image...");
+        graph.push(crate::tasks::build::build_task(config)?);
+
+        let app_path = config.app_path();
+        graph.push(
+            config.task("podman build", "podman")
+                .args(["build", "-t", "cwrdd-app:local", "."])
+                .working_dir(app_path.to_string_lossy().to_string()),
+        );
+        compose_deps = vec!["cargo build", "podman build"];
+    }
+
+    // Navigate to repo root and start compose
+    let repo_path = &config.repo_path;
     
-    if needs_seed {
-        println!("🌱 Seeding database with development data...");
-        crate::tasks::migrate::seed(config).await?;
+    let mut args = if recreate {
+        vec!["up".to_string(), "-d".to_string(), "--force-recreate".to_string()]
     } else {
-        println!("✅ Database already has seed data\n");
+        vec!["up".to_string(), "-d".to_string(), "--no-recreate".to_string()]
+    };
+    args.extend(service_args(selection, services));
+    graph.push(
+        config.task("compose up", "podman-compose")
+            .args(args)
+            .working_dir(repo_path.to_string_lossy().to_string())
+            .envs(profile.compose_env()?)
+            .depends_on(compose_deps),
+    );
+
+    // --no-recreate leaves a running app serving the certificate it started with
+    if cert_issued && !recreate {
+        graph.push(
+            config.task("restart app", "podman-compose")
+                .args(["restart", "app"])
+                .working_dir(repo_path.to_string_lossy().to_string())
+                .envs(profile.compose_env()?)
+                .depends_on(vec!["compose up"]),
+        );
+    }
+
+    Ok(graph)
+}
+
+/// Wait for whichever of PostgreSQL, Redis and the app were started to answer
+/// native readiness probes
+async fn wait_until_ready(config: &Config, services: &[String]) -> Result<()> {
+    let profile = config.profile()?;
+    let db = profile.database()?;
+    let (redis_host, redis_port) = profile.redis_address()?;
+    let app_port = profile.ports.app;
+    let cert_path = config.cert_path();
+    let selected = |service: &str| services.iter().any(|s| s == service);
+
+    if config.is_dry_run() {
+        if selected("postgres") {
+            say!("📝 Would wait for PostgreSQL at {}:{}", db.host, db.port);
+        }
+        if selected("redis") {
+            say!("📝 Would wait for Redis at {}:{}", redis_host, redis_port);
+        }
+        if selected("app") {
+            say!("📝 Would wait for the app at https://localhost:{}", app_port);
+        }
+        say!();
+        return Ok(());
     }
 
-    println!("✅ Development environment is ready!\n");
-    print_access_info();
+    if selected("postgres") {
+        readiness_policy()
+            .run("check postgres", || probe::postgres(&db))
+            .await
+            .context("PostgreSQL did not become ready in time. Check logs with: cwrdd-make logs postgres")?;
+        say!("✅ PostgreSQL is ready!");
+    }
+
+    if selected("redis") {
+        readiness_policy()
+            .run("check redis", || probe::redis(&redis_host, redis_port))
+            .await
+            .context("Redis did not become ready in time. Check logs with: cwrdd-make logs redis")?;
+        say!("✅ Redis is ready!");
+    }
+
+    if selected("app") {
+        readiness_policy()
+            .run("check app", || probe::https("localhost", app_port, &cert_path))
+            .await
+            .context("The cwrdd app did not become ready in time. Check logs with: cwrdd-make logs app")?;
+        say!("✅ cwrdd app is ready!");
+    }
+    say!();
 
     Ok(())
 }
 
-/// Stop the local development environment
-pub async fn down(config: &Config) -> Result<()> {
-    println!("🛑 Stopping local development environment\n");
+/// Readiness probe policy for services started by compose: poll every 2 seconds
+/// for up to a minute, without printing each failed probe
+fn readiness_policy() -> RetryPolicy {
+    RetryPolicy::fixed(30, Duration::from_secs(2))
+        .jitter(0.1)
+        .deadline(Duration::from_secs(60))
+        .quiet()
+}
+
+/// List the services defined in compose.yaml
+pub(crate) async fn services(config: &Config) -> Result<Vec<String>> {
+    let output = config.task("list services", "podman-compose")
+        .args(["config", "--services"])
+        .working_dir(config.repo_path.to_string_lossy().to_string())
+        .envs(config.profile()?.compose_env()?)
+        .capture()
+        .await?;
+
+    if !output.success() {
+        bail!("Failed to list compose services: {}", output.stderr.trim());
+    }
+    Ok(output.stdout.lines().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+}
+
+/// Stop the local development environment, or the services picked by `selection`
+pub async fn down(config: &Config, selection: &Selection) -> Result<()> {
+    say!("🛑 Stopping local development environment\n");
+    let services = select(config, selection, false)?;
 
     // Check if podman-compose is installed
     if !command_exists("podman-compose") {
@@ -151,19 +251,32 @@ pub async fn down(config: &Config) -> Result<()> {
 
     let repo_path = &config.repo_path;
     
-    let compose_down = Task::new("compose down", "podman-compose")
-        .args(["down"])
-        .working_dir(repo_path.to_string_lossy().to_string());
+    let mut args = vec!["down".to_string()];
+    args.extend(service_args(selection, &services));
+    let compose_down = config.task("compose down", "podman-compose")
+        .args(args)
+        .working_dir(repo_path.to_string_lossy().to_string())
+        .envs(config.profile()?.compose_env()?);
     
     compose_down.execute().await?;
 
-    println!("✅ Development environment stopped\n");
+    if selection.is_all() {
+        say!("✅ Development environment stopped\n");
+    } else {
+        say!("✅ Stopped {}\n", services.join(", "));
+    }
 
     Ok(())
 }
 
-/// Show logs from the development environment
-pub async fn logs(config: &Config, service: Option<String>, follow: bool) -> Result<()> {
+/// Show logs from the development environment. `services` are names or groups,
+/// like `--only`.
+pub async fn logs(
+    config: &Config,
+    services: Vec<String>,
+    selection: &Selection,
+    follow: bool,
+) -> Result<()> {
     if !command_exists("podman-compose") {
         bail!(
             "podman-compose is not installed.\n\
@@ -179,13 +292,15 @@ pub async fn logs(config: &Config, service: Option<String>, follow: bool) -> Res
         args.push("-f".to_string());
     }
     
-    if let Some(svc) = service {
-        args.push(svc);
-    }
+    let mut selection = selection.clone();
+    selection.only.extend(services);
+    let services = select(config, &selection, false)?;
+    args.extend(service_args(&selection, &services));
     
-    let logs_task = Task::new("compose logs", "podman-compose")
+    let logs_task = config.task("compose logs", "podman-compose")
         .args(args)
-        .working_dir(repo_path.to_string_lossy().to_string());
+        .working_dir(repo_path.to_string_lossy().to_string())
+        .envs(config.profile()?.compose_env()?);
     
     logs_task.execute().await?;
 
@@ -194,101 +309,104 @@ pub async fn logs(config: &Config, service: Option<String>, follow: bool) -> Res
 
 /// Check if migrations need to be run
 async fn check_needs_migration(config: &Config) -> Result<bool> {
-    // Run liquibase status and check if there are pending changesets
-    let db_path = config.db_path();
-    
-    let status_output = tokio::process::Command::new("liquibase")
-        .args([
-            "--changeLogFile=migrations/changelog.xml",
-            "--url=jdbc:postgresql://localhost:5432/cwrdd_dev",
-            "--username=cwrdd_user",
-            "--password=cwrdd_password",
-            "--driver=org.postgresql.Driver",
-            "status",
-            "--verbose"
-        ])
-        .current_dir(&db_path)
-        .output()
+    if config.is_dry_run() {
+        say!("📝 Would compare {} with databasechangelog", changelog::CHANGELOG_FILE);
+        return Ok(true);
+    }
+
+    let db = config.profile()?.database()?;
+    let report = changelog::status(&db, &config.db_path())
         .await
         .context("Failed to check migration status")?;
 
-    let stdout = String::from_utf8_lossy(&status_output.stdout);
-    
-    // If output contains "changesets have not been applied", we need to migrate
-    Ok(stdout.contains("changesets have not been applied") || 
-       stdout.contains("is not up to date"))
+    if !report.mismatched.is_empty() {
+        say!(
+            "⚠️  {} applied changeset(s) have been edited since they were applied; \
+             run `cwrdd-make migrate-status` for details",
+            report.mismatched.len()
+        );
+    }
+    if !report.pending.is_empty() {
+        say!("⏳ {} changeset(s) pending", report.pending.len());
+    }
+
+    Ok(!report.pending.is_empty())
 }
 
-/// Check if database needs seeding
-async fn check_needs_seed() -> Result<bool> {
-    // Check if there's any data in a key table (e.g., users table)
-    // For now, we'll use a simple query to check if tables are empty
-    
-    let check_output = tokio::process::Command::new("psql")
-        .args([
-            "-h", "localhost",
-            "-p", "5432",
-            "-U", "cwrdd_user",
-            "-d", "cwrdd_dev",
-            "-t",
-            "-c", "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = 'public' AND table_type = 'BASE TABLE';"
-        ])
-        .env("PGPASSWORD", "cwrdd_password")
-        .output()
-        .await;
-
-    match check_output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let table_count: i32 = stdout.trim().parse().unwrap_or(0);
-            
-            // If we have tables, check if they're empty
-            if table_count > 0 {
-                // Check for any rows in any table (simplified check)
-                // In a real scenario, you'd check specific seed marker tables
-                let data_check = tokio::process::Command::new("psql")
-                    .args([
-                        "-h", "localhost",
-                        "-p", "5432",
-                        "-U", "cwrdd_user",
-                        "-d", "cwrdd_dev",
-                        "-t",
-                        "-c", "SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_schema = 'public' LIMIT 1);"
-                    ])
-                    .env("PGPASSWORD", "cwrdd_password")
-                    .output()
-                    .await?;
-                
-                let has_data = String::from_utf8_lossy(&data_check.stdout);
-                
-                // Need seed if we have tables but they're likely empty
-                // This is a simplified heuristic
-                Ok(table_count > 0 && !has_data.contains("f"))
-            } else {
-                // No tables means migrations haven't run yet
-                Ok(false)
-            }
-        }
-        Err(_) => {
-            // If psql fails, assume we need to seed
-            Ok(false)
-        }
+/// Print access information for the running services
+fn print_access_info(config: &Config, services: &[String]) -> Result<()> {
+    let profile = config.profile()?;
+    let db = profile.database()?;
+    let (redis_host, redis_port) = profile.redis_address()?;
+    let ports = &profile.ports;
+    let selected = |service: &str| services.iter().any(|s| s == service);
+
+    say!("🌐 Access your services:");
+    if selected("app") {
+        say!("   cwrdd App:     https://localhost:{}", ports.app);
+    }
+    if selected("postgres") {
+        say!("   PostgreSQL:    {}:{} ({} / {} / {})", db.host, db.port, db.name, db.user, db.password);
+    }
+    if selected("redis") {
+        say!("   Redis:         {}:{}", redis_host, redis_port);
     }
+    if selected("grafana") {
+        say!("   Grafana:       http://localhost:{}", ports.grafana);
+    }
+    if selected("prometheus") {
+        say!("   Prometheus:    http://localhost:{}", ports.prometheus);
+    }
+    if selected("alloy") {
+        say!("   Alloy:         http://localhost:{}", ports.alloy);
+        say!("\n📊 Send telemetry to:");
+        say!("   OTLP gRPC:     localhost:{}", ports.otlp_grpc);
+        say!("   OTLP HTTP:     localhost:{}", ports.otlp_http);
+    }
+    say!("\n📝 Useful commands:");
+    say!("   View logs:     cwrdd-make logs [service]");
+    say!("   Stop all:      cwrdd-make down");
+
+    Ok(())
 }
 
-/// Print access information for running services
-fn print_access_info() {
-    println!("🌐 Access your services:");
-    println!("   cwrdd App:     https://localhost:8443");
-    println!("   PostgreSQL:    localhost:5432 (cwrdd_dev / cwrdd_user / cwrdd_password)");
-    println!("   Redis:         localhost:6379");
-    println!("   Grafana:       http://localhost:3000");
-    println!("   Prometheus:    http://localhost:9090");
-    println!("   Alloy:         http://localhost:12345");
-    println!("\n📊 Send telemetry to:");
-    println!("   OTLP gRPC:     localhost:4319");
-    println!("   OTLP HTTP:     localhost:4320");
-    println!("\n📝 Useful commands:");
-    println!("   View logs:     cwrdd-make logs [service]");
-    println!("   Stop all:      cwrdd-make down");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::Plan;
+
+    async fn planned(cert_issued: bool, recreate: bool) -> Vec<(String, Vec<String>)> {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir(repo.path().join("app")).unwrap();
+        let plan = Plan::new();
+        let config = Config {
+            repo_path: repo.path().to_path_buf(),
+            dry_run: Some(plan.clone()),
+            ..Config::default()
+        };
+        let services = ["app".to_string(), "postgres".to_string()];
+
+        start_graph(&config, recreate, &Selection::default(), &services, cert_issued)
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+        plan.steps().into_iter().map(|s| (s.name, s.args)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_issued_cert_restarts_app() {
+        let steps = planned(true, false).await;
+        let names: Vec<&str> = steps.iter().map(|(name, _)| name.as_str()).collect();
+        let position = |name: &str| names.iter().position(|n| *n == name);
+        assert!(position("compose up").unwrap() < position("restart app").unwrap());
+        assert_eq!(steps[position("restart app").unwrap()].1, ["restart", "app"]);
+
+        let restarts = |steps: Vec<(String, Vec<String>)>| {
+            steps.iter().any(|(name, _)| name == "restart app")
+        };
+        assert!(!restarts(planned(false, false).await));
+        // --force-recreate already starts a new app container
+        assert!(!restarts(planned(true, true).await));
+    }
 }
@@ -0,0 +1,37 @@
+use crate::config::Config;
+use crate::output;
+use crate::say;
+use anyhow::Result;
+use serde_json::json;
+
+/// Print every effective setting and where it came from
+pub async fn show(config: &Config) -> Result<()> {
+    say!("⚙️  Effective configuration\n");
+
+    let settings = config.settings();
+    let key_width = settings.iter().map(|(key, _, _)| key.len()).max().unwrap_or(0);
+    let value_width = settings.iter().map(|(_, value, _)| value.len()).max().unwrap_or(0);
+    for (key, value, source) in &settings {
+        say!(
+            "  {:kw$} = {:vw$}  ({})",
+            key,
+            value,
+            source,
+            kw = key_width,
+            vw = value_width
+        );
+    }
+    say!();
+
+    output::record(
+        "settings",
+        settings
+            .iter()
+            .map(|(key, value, source)| {
+                json!({ "name": key, "value": value, "source": source.to_string() })
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    Ok(())
+}
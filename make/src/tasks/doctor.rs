@@ -0,0 +1,567 @@
+use crate::config::Config;
+use crate::output;
+use crate::say;
+use crate::task::command_exists;
+use crate::tasks::certs::{expiry_warning, read_certificate};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::net::TcpListener;
+use std::path::Path;
+use std::time::Duration;
+
+/// Free disk space below which a warning is shown
+const DISK_WARN_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Free disk space below which the check fails
+const DISK_FAIL_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Pass => "✅ PASS",
+            Status::Warn => "⚠️  WARN",
+            Status::Fail => "❌ FAIL",
+        }
+    }
+}
+
+/// The result of one check, with a suggested fix when it didn't pass
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// A tool cwrdd-make runs, and the oldest version known to work
+struct Tool {
+    command: &'static str,
+    version_args: &'static [&'static str],
+    minimum: &'static str,
+    required: bool,
+    fix: &'static str,
+}
+
+const TOOLS: &[Tool] = &[
+    Tool {
+        command: "podman",
+        version_args: &["--version"],
+        minimum: "4.0",
+        required: true,
+        fix: "Run: cwrdd-make get-tools",
+    },
+    Tool {
+        command: "podman-compose",
+        version_args: &["--version"],
+        minimum: "1.0",
+        required: true,
+        fix: "Run: cwrdd-make get-tools",
+    },
+    Tool {
+        command: "liquibase",
+        version_args: &["--version"],
+        minimum: "4.20",
+        required: true,
+        fix: "Run: cwrdd-make get-tools",
+    },
+    Tool {
+        command: "java",
+        version_args: &["--version"],
+        minimum: "11",
+        required: true,
+        fix: "Install a Java 11+ runtime for Liquibase, e.g. sudo apt install default-jre",
+    },
+    Tool {
+        command: "psql",
+        version_args: &["--version"],
+        minimum: "14",
+        required: true,
+        fix: "Run: cwrdd-make get-tools",
+    },
+    Tool {
+        command: "cargo-nextest",
+        version_args: &["--version"],
+        minimum: "0.9",
+        required: false,
+        fix: "Run: cargo install cargo-nextest --locked",
+    },
+];
+
+/// Diagnose the development setup, failing if any check fails (or warns, with `strict`)
+pub async fn run(config: &Config, strict: bool) -> Result<()> {
+    say!("🩺 Checking development setup\n");
+
+    let mut checks = repo_layout(&config.repo_path);
+    for tool in TOOLS {
+        checks.push(check_tool(config, tool).await);
+    }
+    checks.extend(podman_setup(config).await);
+    checks.extend(ports(config).await?);
+    checks.push(certificate(&config.cert_path(), config.certs.warn_days, Utc::now()));
+    checks.extend(disk_space(config));
+
+    print_table(&checks);
+    output::record("checks", serde_json::to_value(&checks)?);
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warned = checks.iter().filter(|c| c.status == Status::Warn).count();
+    if failed > 0 || strict && warned > 0 {
+        bail!("{} check(s) failed, {} warning(s)", failed, warned);
+    }
+
+    if warned > 0 {
+        say!("✅ No failures ({} warning(s))", warned);
+    } else {
+        say!("✅ Everything looks good!");
+    }
+    Ok(())
+}
+
+/// Print the checks as a table, followed by the fixes for anything that didn't pass
+fn print_table(checks: &[Check]) {
+    let width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    for check in checks {
+        say!(
+            "  {}  {:width$}  {}",
+            check.status.label(),
+            check.name,
+            check.detail,
+            width = width
+        );
+    }
+    say!();
+
+    let fixes: Vec<_> = checks
+        .iter()
+        .filter_map(|c| c.fix.as_ref().map(|fix| (c, fix)))
+        .collect();
+    if !fixes.is_empty() {
+        say!("🔧 Suggested fixes:");
+        for (check, fix) in fixes {
+            say!("   {}: {}", check.name, fix);
+        }
+        say!();
+    }
+}
+
+/// Check that the repository has the directories and files cwrdd-make works with
+fn repo_layout(repo: &Path) -> Vec<Check> {
+    if !repo.is_dir() {
+        return vec![Check::fail(
+            "repository",
+            format!("{} does not exist", repo.display()),
+            "Run cwrdd-make from inside your checkout, or set repo_path (see: cwrdd-make config show)",
+        )];
+    }
+
+    let mut checks = vec![Check::pass("repository", repo.display().to_string())];
+    for entry in [
+        "app/",
+        "db/",
+        "compose.yaml",
+        "db/liquibase.properties",
+        "db/migrations/changelog.xml",
+    ] {
+        let path = repo.join(entry);
+        let exists = if entry.ends_with('/') {
+            path.is_dir()
+        } else {
+            path.is_file()
+        };
+        checks.push(if exists {
+            Check::pass(entry, "present")
+        } else {
+            Check::fail(
+                entry,
+                "missing",
+                format!(
+                    "Check that {} is a complete cwrdd checkout (git status)",
+                    repo.display()
+                ),
+            )
+        });
+    }
+    checks
+}
+
+/// Check that a tool is installed and new enough
+async fn check_tool(config: &Config, tool: &Tool) -> Check {
+    let missing = |detail: &str| {
+        if tool.required {
+            Check::fail(tool.command, detail, tool.fix)
+        } else {
+            Check::warn(tool.command, detail, tool.fix)
+        }
+    };
+
+    if !command_exists(tool.command) {
+        return missing("not installed");
+    }
+    if config.is_dry_run() {
+        return Check::pass(tool.command, "installed (version not checked in dry run)");
+    }
+
+    let output = config.task(format!("{} version", tool.command), tool.command)
+        .args(tool.version_args.iter().copied())
+        .timeout(Duration::from_secs(30))
+        .capture()
+        .await;
+    let Ok(output) = output else {
+        return missing("installed, but failed to report its version");
+    };
+
+    let text = format!("{}\n{}", output.stdout, output.stderr);
+    match parse_version(&text) {
+        Some(version) if version_at_least(&version, tool.minimum) => {
+            Check::pass(tool.command, format!("{} (>= {})", version, tool.minimum))
+        }
+        Some(version) => missing(&format!("{} is older than {}", version, tool.minimum)),
+        None => Check::warn(
+            tool.command,
+            "installed, but its version could not be determined",
+            format!("Check that `{} {}` works", tool.command, tool.version_args.join(" ")),
+        ),
+    }
+}
+
+/// Find the version number in a tool's `--version` output.
+///
+/// Prefers a number on a line mentioning "version", so banners with dates and
+/// times don't get picked up.
+fn parse_version(text: &str) -> Option<String> {
+    let token = |word: &str| {
+        let word = word.trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != '.');
+        let word = word.strip_prefix('v').unwrap_or(word);
+        let is_version = word.chars().next().is_some_and(|c| c.is_ascii_digit())
+            && word.chars().all(|c| c.is_ascii_digit() || c == '.');
+        is_version.then(|| word.trim_end_matches('.').to_string())
+    };
+
+    let version_line = text
+        .lines()
+        .filter(|line| line.to_ascii_lowercase().contains("version"))
+        .find_map(|line| line.split_whitespace().find_map(token));
+    version_line.or_else(|| text.split_whitespace().find_map(token))
+}
+
+/// Compare dotted version numbers component by component
+fn version_at_least(version: &str, minimum: &str) -> bool {
+    let parts = |v: &str| -> Vec<u64> { v.split('.').map(|p| p.parse().unwrap_or(0)).collect() };
+    parts(version) >= parts(minimum)
+}
+
+/// Check that podman runs rootless with subordinate IDs and its user socket
+async fn podman_setup(config: &Config) -> Vec<Check> {
+    if !cfg!(target_os = "linux") || !command_exists("podman") || config.is_dry_run() {
+        return Vec::new();
+    }
+    let mut checks = Vec::new();
+
+    let rootless = config.task("podman info", "podman")
+        .args(["info", "--format", "{{.Host.Security.Rootless}}"])
+        .timeout(Duration::from_secs(30))
+        .capture()
+        .await;
+    checks.push(match rootless {
+        Ok(output) if output.stdout.trim() == "true" => Check::pass("podman rootless", "yes"),
+        Ok(output) if output.success() => Check::warn(
+            "podman rootless",
+            "podman is running as root",
+            "Run cwrdd-make as your normal user, not with sudo",
+        ),
+        _ => Check::fail(
+            "podman rootless",
+            "podman info failed",
+            "Run `podman info` to see what is wrong; try: podman system migrate",
+        ),
+    });
+
+    let user = nix::unistd::User::from_uid(nix::unistd::getuid())
+        .ok()
+        .flatten()
+        .map(|user| user.name);
+    if let Some(user) = user {
+        let has_ids = |file: &str| {
+            std::fs::read_to_string(file)
+                .map(|content| has_subordinate_ids(&content, &user))
+                .unwrap_or(false)
+        };
+        checks.push(if has_ids("/etc/subuid") && has_ids("/etc/subgid") {
+            Check::pass("subordinate IDs", format!("configured for {}", user))
+        } else {
+            Check::fail(
+                "subordinate IDs",
+                format!("no /etc/subuid or /etc/subgid entry for {}", user),
+                format!(
+                    "sudo usermod --add-subuids 100000-165535 --add-subgids 100000-165535 {} && podman system migrate",
+                    user
+                ),
+            )
+        });
+    }
+
+    if command_exists("systemctl") {
+        let socket = config.task("podman socket status", "systemctl")
+            .args(["--user", "is-active", "podman.socket"])
+            .timeout(Duration::from_secs(10))
+            .capture()
+            .await;
+        checks.push(match socket {
+            Ok(output) if output.success() => Check::pass("podman socket", "active"),
+            _ => Check::warn(
+                "podman socket",
+                "not active",
+                "systemctl --user enable --now podman.socket",
+            ),
+        });
+    }
+
+    checks
+}
+
+/// Whether a subuid/subgid file has an entry for `user`
+fn has_subordinate_ids(content: &str, user: &str) -> bool {
+    content
+        .lines()
+        .any(|line| line.split(':').next() == Some(user))
+}
+
+/// Check that the ports the selected profile publishes are free, or used by
+/// the profile's own containers
+async fn ports(config: &Config) -> Result<Vec<Check>> {
+    let profile = config.profile()?;
+    let running = running_containers(config).await;
+
+    let mut checks = Vec::new();
+    let mut seen = BTreeSet::new();
+    for (service, port) in profile.service_ports()? {
+        if !seen.insert(port) {
+            continue;
+        }
+        let name = format!("port {}", port);
+        let container = profile.container(service);
+
+        checks.push(if port_is_free(port) {
+            Check::pass(name, format!("free for {}", service))
+        } else if running.contains(&container) {
+            Check::pass(name, format!("in use by {}", container))
+        } else {
+            Check::fail(
+                name,
+                format!("in use by another process (needed by {})", service),
+                format!(
+                    "Stop whatever is listening (see: ss -ltnp 'sport = :{}'), or pick another port with a profile",
+                    port
+                ),
+            )
+        });
+    }
+    Ok(checks)
+}
+
+/// Names of running containers, or none if podman isn't available
+async fn running_containers(config: &Config) -> BTreeSet<String> {
+    if !command_exists("podman") {
+        return BTreeSet::new();
+    }
+    let output = config.task("list containers", "podman")
+        .args(["ps", "--format", "{{.Names}}"])
+        .timeout(Duration::from_secs(30))
+        .capture()
+        .await;
+    match output {
+        Ok(output) => output.stdout.lines().map(|l| l.trim().to_string()).collect(),
+        Err(_) => BTreeSet::new(),
+    }
+}
+
+/// Whether nothing is listening on `port`
+fn port_is_free(port: u16) -> bool {
+    TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+/// Check that the dev certificate is readable and not expiring within `warn_days`
+fn certificate(path: &Path, warn_days: u32, now: DateTime<Utc>) -> Check {
+    let name = "dev certificate";
+    let regenerate = format!(
+        "Delete {} and run: cwrdd-make up",
+        path.parent().unwrap_or(path).display()
+    );
+
+    if !path.exists() {
+        return Check::warn(
+            name,
+            "not generated yet",
+            "Run: cwrdd-make up (generates it automatically)",
+        );
+    }
+    let cert = match read_certificate(path) {
+        Ok(cert) => cert,
+        Err(e) => return Check::fail(name, format!("{:#}", e), regenerate),
+    };
+
+    match expiry_warning(cert.not_after, warn_days, now) {
+        Some(warning) if cert.not_after <= now => {
+            Check::fail(name, warning, "Run: cwrdd-make up (reissues it automatically)")
+        }
+        Some(warning) => Check::warn(name, warning, regenerate),
+        None => Check::pass(
+            name,
+            format!("valid until {}", cert.not_after.format("%Y-%m-%d")),
+        ),
+    }
+}
+
+/// Check free space for the repository and podman's image storage
+fn disk_space(config: &Config) -> Vec<Check> {
+    let mut locations = vec![("disk space (repo)", config.repo_path.clone())];
+    if let Some(home) = std::env::var_os("HOME") {
+        let storage = Path::new(&home).join(".local/share/containers");
+        if storage.exists() {
+            locations.push(("disk space (podman)", storage));
+        }
+    }
+
+    locations
+        .into_iter()
+        .filter_map(|(name, path)| {
+            let stat = nix::sys::statvfs::statvfs(&path).ok()?;
+            let free = stat.blocks_available() as u64 * stat.fragment_size() as u64;
+            Some(disk_check(name, free))
+        })
+        .collect()
+}
+
+/// Classify an amount of free disk space
+fn disk_check(name: &str, free: u64) -> Check {
+    let gib = free as f64 / (1024.0 * 1024.0 * 1024.0);
+    let detail = format!("{:.1} GiB free", gib);
+    let fix = "Free up space, e.g. remove unused images with: podman system prune";
+    if free < DISK_FAIL_BYTES {
+        Check::fail(name, detail, fix)
+    } else if free < DISK_WARN_BYTES {
+        Check::warn(name, detail, fix)
+    } else {
+        Check::pass(name, detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("podman version 4.9.3").as_deref(), Some("4.9.3"));
+        assert_eq!(parse_version("psql (PostgreSQL) 16.1").as_deref(), Some("16.1"));
+        assert_eq!(
+            parse_version("openjdk 17.0.9 2023-10-17\nOpenJDK Runtime Environment").as_deref(),
+            Some("17.0.9")
+        );
+        assert_eq!(
+            parse_version("####\nStarting Liquibase at 10:45:03 (version 4.29.2 #3683 built at 2024-08-29)")
+                .as_deref(),
+            Some("4.29.2")
+        );
+        assert_eq!(parse_version("OpenSSL 3.0.13 30 Jan 2024").as_deref(), Some("3.0.13"));
+        assert_eq!(parse_version("no numbers here"), None);
+    }
+
+    #[test]
+    fn test_version_at_least() {
+        assert!(version_at_least("4.9.3", "4.0"));
+        assert!(version_at_least("11", "11"));
+        assert!(version_at_least("4.29.2", "4.20"));
+        assert!(!version_at_least("4.3", "4.20"));
+        assert!(!version_at_least("1.8.0", "11"));
+    }
+
+    #[test]
+    fn test_repo_layout() {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir(repo.path().join("app")).unwrap();
+        std::fs::write(repo.path().join("compose.yaml"), "").unwrap();
+
+        let checks = repo_layout(repo.path());
+        let status = |name: &str| checks.iter().find(|c| c.name == name).unwrap().status;
+        assert_eq!(status("repository"), Status::Pass);
+        assert_eq!(status("app/"), Status::Pass);
+        assert_eq!(status("compose.yaml"), Status::Pass);
+        assert_eq!(status("db/"), Status::Fail);
+
+        let missing = repo_layout(&repo.path().join("nope"));
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].status, Status::Fail);
+    }
+
+    #[test]
+    fn test_port_in_use() {
+        let listener = TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(!port_is_free(port));
+        drop(listener);
+        assert!(port_is_free(port));
+    }
+
+    #[test]
+    fn test_certificate_missing() {
+        let check = certificate(Path::new("/nonexistent/cert.pem"), 14, Utc::now());
+        assert_eq!(check.status, Status::Warn);
+        assert!(check.fix.is_some());
+    }
+
+    #[test]
+    fn test_has_subordinate_ids() {
+        let content = "alice:100000:65536\nbob:165536:65536\n";
+        assert!(has_subordinate_ids(content, "bob"));
+        assert!(!has_subordinate_ids(content, "bo"));
+    }
+
+    #[test]
+    fn test_disk_check() {
+        assert_eq!(disk_check("disk", 50 * 1024 * 1024 * 1024).status, Status::Pass);
+        assert_eq!(disk_check("disk", 5 * 1024 * 1024 * 1024).status, Status::Warn);
+        assert_eq!(disk_check("disk", 1024).status, Status::Fail);
+    }
+}
@@ -1,18 +1,20 @@
 use crate::config::Config;
+use crate::graph::TaskGraph;
+use crate::say;
 use crate::task::{command_exists, run_tasks, Task};
 use anyhow::{bail, Result};
 
 /// Install development tools (Podman, Liquibase, PostgreSQL client, cargo-nextest)
-pub async fn run(_config: &Config) -> Result<()> {
-    println!("🔧 Installing development tools\n");
+pub async fn run(config: &Config) -> Result<()> {
+    say!("🔧 Installing development tools\n");
 
     // Detect OS
     let os = detect_os()?;
-    println!("Detected OS: {}\n", os);
+    say!("Detected OS: {}\n", os);
 
     match os.as_str() {
-        "ubuntu" | "debian" => install_ubuntu().await?,
-        "macos" => install_macos().await?,
+        "ubuntu" | "debian" => install_ubuntu(config).await?,
+        "macos" => install_macos(config).await?,
         _ => {
             bail!("Unsupported OS: {}. Please install tools manually:\n\
                    - Podman: https://podman.io/getting-started/installation\n\
@@ -48,10 +50,10 @@ fn detect_os() -> Result<String> {
 }
 
 /// Install tools on Ubuntu/Debian
-async fn install_ubuntu() -> Result<()> {
-    println!("Installing tools for Ubuntu/Debian...\n");
+async fn install_ubuntu(config: &Config) -> Result<()> {
+    say!("Installing tools for Ubuntu/Debian...\n");
 
-    let mut tasks = vec![];
+    let mut graph = TaskGraph::new();
 
     // Check what needs to be installed
     let needs_podman = !command_exists("podman");
@@ -61,62 +63,51 @@ async fn install_ubuntu() -> Result<()> {
     let needs_nextest = !command_exists("cargo-nextest");
 
     if !needs_podman && !needs_podman_compose && !needs_liquibase && !needs_psql && !needs_nextest {
-        println!("✅ All tools already installed!");
+        say!("✅ All tools already installed!");
         return Ok(());
     }
 
     // Update apt if we need to install anything
     if needs_podman || needs_podman_compose || needs_liquibase || needs_psql {
-        println!("📦 Updating package lists...");
-        tasks.push(
-            Task::new("apt update", "sudo")
+        say!("📦 Updating package lists...");
+        graph.push(
+            config.task("apt update", "sudo")
                 .args(["apt", "update"])
+                .interactive()
         );
     }
 
     // Install Podman
     if needs_podman {
-        println!("🐳 Installing Podman...");
-        tasks.push(
-            Task::new("install podman", "sudo")
-                .args(["apt", "install", "-y", "podman"])
-        );
+        say!("🐳 Installing Podman...");
+        graph.push(apt_install(config, "install podman", "podman"));
     } else {
-        println!("✓ Podman already installed");
+        say!("✓ Podman already installed");
     }
 
     // Install podman-compose
     if needs_podman_compose {
-        println!("🐳 Installing podman-compose...");
-        tasks.push(
-            Task::new("install podman-compose", "sudo")
-                .args(["apt", "install", "-y", "podman-compose"])
-        );
+        say!("🐳 Installing podman-compose...");
+        graph.push(apt_install(config, "install podman-compose", "podman-compose"));
     } else {
-        println!("✓ podman-compose already installed");
+        say!("✓ podman-compose already installed");
     }
 
     // Install PostgreSQL client
     if needs_psql {
-        println!("🐘 Installing PostgreSQL client...");
-        tasks.push(
-            Task::new("install postgresql-client", "sudo")
-                .args(["apt", "install", "-y", "postgresql-client"])
-        );
+        say!("🐘 Installing PostgreSQL client...");
+        graph.push(apt_install(config, "install postgresql-client", "postgresql-client"));
     } else {
-        println!("✓ PostgreSQL client already installed");
+        say!("✓ PostgreSQL client already installed");
     }
 
     // Install Liquibase
     if needs_liquibase {
-        println!("💧 Installing Liquibase...");
+        say!("💧 Installing Liquibase...");
         
         // Install Java if needed
         if !command_exists("java") {
-            tasks.push(
-                Task::new("install java", "sudo")
-                    .args(["apt", "install", "-y", "default-jre"])
-            );
+            graph.push(apt_install(config, "install java", "default-jre"));
         }
 
         // Download and install Liquibase
@@ -126,63 +117,69 @@ async fn install_ubuntu() -> Result<()> {
             liquibase_version, liquibase_version
         );
 
-        tasks.push(
-            Task::new("download liquibase", "wget")
+        // The download doesn't need apt, so it overlaps with the package installs
+        graph.push(
+            config.task("download liquibase", "wget")
                 .args(["-q", &liquibase_url, "-O", "/tmp/liquibase.tar.gz"])
         );
 
-        tasks.push(
-            Task::new("create liquibase dir", "sudo")
+        graph.push(
+            config.task("create liquibase dir", "sudo")
                 .args(["mkdir", "-p", "/opt/liquibase"])
+                .interactive()
         );
 
-        tasks.push(
-            Task::new("extract liquibase", "sudo")
+        graph.push(
+            config.task("extract liquibase", "sudo")
                 .args(["tar", "-xzf", "/tmp/liquibase.tar.gz", "-C", "/opt/liquibase"])
+                .interactive()
+                .depends_on(["download liquibase", "create liquibase dir"])
         );
 
-        tasks.push(
-            Task::new("symlink liquibase", "sudo")
+        graph.push(
+            config.task("symlink liquibase", "sudo")
                 .args(["ln", "-sf", "/opt/liquibase/liquibase", "/usr/local/bin/liquibase"])
+                .interactive()
+                .depends_on(["extract liquibase"])
         );
 
-        tasks.push(
-            Task::new("chmod liquibase", "sudo")
+        graph.push(
+            config.task("chmod liquibase", "sudo")
                 .args(["chmod", "+x", "/usr/local/bin/liquibase"])
+                .interactive()
+                .depends_on(["symlink liquibase"])
         );
 
-        tasks.push(
-            Task::new("cleanup", "rm")
+        graph.push(
+            config.task("cleanup", "rm")
                 .args(["/tmp/liquibase.tar.gz"])
+                .depends_on(["extract liquibase"])
         );
     } else {
-        println!("✓ Liquibase already installed");
-    }
-
-    // Run apt tasks
-    if !tasks.is_empty() {
-        run_tasks(tasks).await?;
+        say!("✓ Liquibase already installed");
     }
 
     // Install cargo-nextest (doesn't need sudo)
     if needs_nextest {
-        println!("\n🧪 Installing cargo-nextest...");
-        let nextest_tasks = vec![
-            Task::new("install cargo-nextest", "cargo")
+        say!("🧪 Installing cargo-nextest...");
+        graph.push(
+            config.task("install cargo-nextest", "cargo")
                 .args(["install", "cargo-nextest", "--locked"])
-        ];
-        run_tasks(nextest_tasks).await?;
+        );
     } else {
-        println!("✓ cargo-nextest already installed");
+        say!("✓ cargo-nextest already installed");
     }
 
+    say!();
+    graph.run().await?;
+
     // Enable podman socket for current user
     if needs_podman {
-        println!("\n🔌 Configuring Podman socket...");
+        say!("\n🔌 Configuring Podman socket...");
         let socket_tasks = vec![
-            Task::new("enable podman socket", "systemctl")
+            config.task("enable podman socket", "systemctl")
                 .args(["--user", "enable", "podman.socket"]),
-            Task::new("start podman socket", "systemctl")
+            config.task("start podman socket", "systemctl")
                 .args(["--user", "start", "podman.socket"]),
         ];
         
@@ -190,15 +187,31 @@ async fn install_ubuntu() -> Result<()> {
         let _ = run_tasks(socket_tasks).await;
     }
 
-    println!("\n✅ All tools installed successfully!\n");
-    print_versions();
+    if config.is_dry_run() {
+        say!("\n✅ Dry run complete: nothing was installed");
+        return Ok(());
+    }
+
+    say!("\n✅ All tools installed successfully!\n");
+    print_versions(config).await;
 
     Ok(())
 }
 
+/// Create an `apt install` task that runs after `apt update`.
+///
+/// Installs run concurrently, so each one waits for the dpkg lock rather than
+/// failing when another install holds it.
+fn apt_install(config: &Config, name: &str, package: &str) -> Task {
+    config.task(name, "sudo")
+        .args(["apt", "install", "-y", "-o", "DPkg::Lock::Timeout=600", package])
+        .interactive()
+        .depends_on(["apt update"])
+}
+
 /// Install tools on macOS
-async fn install_macos() -> Result<()> {
-    println!("Installing tools for macOS...\n");
+async fn install_macos(config: &Config) -> Result<()> {
+    say!("Installing tools for macOS...\n");
 
     if !command_exists("brew") {
         bail!("Homebrew is required. Install it from: https://brew.sh");
@@ -214,39 +227,39 @@ async fn install_macos() -> Result<()> {
     let needs_nextest = !command_exists("cargo-nextest");
 
     if !needs_podman && !needs_podman_compose && !needs_liquibase && !needs_psql && !needs_nextest {
-        println!("✅ All tools already installed!");
+        say!("✅ All tools already installed!");
         return Ok(());
     }
 
     // Install via Homebrew
     if needs_podman {
-        println!("🐳 Installing Podman...");
-        tasks.push(Task::new("brew install podman", "brew").args(["install", "podman"]));
+        say!("🐳 Installing Podman...");
+        tasks.push(config.task("brew install podman", "brew").args(["install", "podman"]));
     } else {
-        println!("✓ Podman already installed");
+        say!("✓ Podman already installed");
     }
 
     if needs_podman_compose {
-        println!("🐳 Installing podman-compose...");
-        tasks.push(Task::new("brew install podman-compose", "brew").args(["install", "podman-compose"]));
+        say!("🐳 Installing podman-compose...");
+        tasks.push(config.task("brew install podman-compose", "brew").args(["install", "podman-compose"]));
     } else {
-        println!("✓ podman-compose already installed");
+        say!("✓ podman-compose already installed");
     }
 
     if needs_liquibase {
-        println!("💧 Installing Liquibase...");
-        tasks.push(Task::new("brew install liquibase", "brew").args(["install", "liquibase"]));
+        say!("💧 Installing Liquibase...");
+        tasks.push(config.task("brew install liquibase", "brew").args(["install", "liquibase"]));
     } else {
-        println!("✓ Liquibase already installed");
+        say!("✓ Liquibase already installed");
     }
 
     if needs_psql {
-        println!("🐘 Installing PostgreSQL client...");
+        say!("🐘 Installing PostgreSQL client...");
         tasks.push(
-            Task::new("brew install postgresql", "brew").args(["install", "postgresql@16"])
+            config.task("brew install postgresql", "brew").args(["install", "postgresql@16"])
         );
     } else {
-        println!("✓ PostgreSQL client already installed");
+        say!("✓ PostgreSQL client already installed");
     }
 
     if !tasks.is_empty() {
@@ -255,68 +268,70 @@ async fn install_macos() -> Result<()> {
 
     // Install cargo-nextest
     if needs_nextest {
-        println!("\n🧪 Installing cargo-nextest...");
+        say!("\n🧪 Installing cargo-nextest...");
         let nextest_tasks = vec![
-            Task::new("install cargo-nextest", "cargo")
+            config.task("install cargo-nextest", "cargo")
                 .args(["install", "cargo-nextest", "--locked"])
         ];
         run_tasks(nextest_tasks).await?;
     } else {
-        println!("✓ cargo-nextest already installed");
+        say!("✓ cargo-nextest already installed");
     }
 
-    println!("\n✅ All tools installed successfully!\n");
-    print_versions();
+    if config.is_dry_run() {
+        say!("\n✅ Dry run complete: nothing was installed");
+        return Ok(());
+    }
+
+    say!("\n✅ All tools installed successfully!\n");
+    print_versions(config).await;
 
     Ok(())
 }
 
 /// Print versions of installed tools
-fn print_versions() {
-    println!("Installed versions:");
-    
-    if command_exists("podman") {
-        if let Ok(output) = std::process::Command::new("podman").arg("--version").output() {
-            let version = String::from_utf8_lossy(&output.stdout);
-            println!("  Podman: {}", version.trim());
-        }
-    }
-    
-    if command_exists("podman-compose") {
-        if let Ok(output) = std::process::Command::new("podman-compose").arg("--version").output() {
-            let version = String::from_utf8_lossy(&output.stdout);
-            println!("  podman-compose: {}", version.trim());
-        }
-    }
-    
-    if command_exists("liquibase") {
-        if let Ok(output) = std::process::Command::new("liquibase").arg("--version").output() {
-            let version = String::from_utf8_lossy(&output.stdout);
-            if let Some(line) = version.lines().next() {
-                println!("  Liquibase: {}", line.trim());
-            }
-        }
-    }
-    
-    if command_exists("psql") {
-        if let Ok(output) = std::process::Command::new("psql").arg("--version").output() {
-            let version = String::from_utf8_lossy(&output.stdout);
-            println!("  PostgreSQL: {}", version.trim());
+async fn print_versions(config: &Config) {
+    say!("Installed versions:");
+
+    let tools = [
+        ("Podman", "podman"),
+        ("podman-compose", "podman-compose"),
+        ("Liquibase", "liquibase"),
+        ("PostgreSQL", "psql"),
+        ("cargo-nextest", "cargo-nextest"),
+    ];
+
+    for (label, command) in tools {
+        if let Some(version) = tool_version(config, command).await {
+            say!("  {}: {}", label, version);
         }
     }
-    
-    if command_exists("cargo-nextest") {
-        if let Ok(output) = std::process::Command::new("cargo-nextest").arg("--version").output() {
-            let version = String::from_utf8_lossy(&output.stdout);
-            println!("  cargo-nextest: {}", version.trim());
-        }
+}
+
+/// Get the first line of `<command> --version`, if the command is installed
+async fn tool_version(config: &Config, command: &str) -> Option<String> {
+    if !command_exists(command) {
+        return None;
     }
+
+    let output = config.task(format!("{} version", command), command)
+        .args(["--version"])
+        .capture()
+        .await
+        .ok()?;
+
+    output.stdout.lines().next().map(|line| line.trim().to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_tool_version_missing_command() {
+        assert_eq!(tool_version(&Config::default(), "nonexistent-command-12345").await, None);
+    }
+
     #[test]
     fn test_detect_os() {
         let result = detect_os();
@@ -1,4 +1,5 @@
 use crate::config::Config;
+use crate::say;
 use anyhow::{Context, Result};
 use std::env;
 use std::fs;
@@ -6,33 +7,38 @@ use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
 /// Install cwrdd-make to user's PATH
-pub async fn run(_config: &Config) -> Result<()> {
-    println!("📦 Installing cwrdd-make to your PATH\n");
+pub async fn run(config: &Config) -> Result<()> {
+    say!("📦 Installing cwrdd-make to your PATH\n");
 
     // Get the current executable path (the one we're running)
     let current_exe = env::current_exe().context("Failed to get current executable path")?;
     
-    println!("Current executable: {}", current_exe.display());
+    say!("Current executable: {}", current_exe.display());
 
     // Determine installation directory
     let install_dir = get_install_dir()?;
     let install_path = install_dir.join("cwrdd-make");
 
-    println!("Installation directory: {}", install_dir.display());
-    println!("Target path: {}\n", install_path.display());
+    say!("Installation directory: {}", install_dir.display());
+    say!("Target path: {}\n", install_path.display());
 
     // Check if install directory is in PATH
     check_path(&install_dir)?;
 
+    if config.is_dry_run() {
+        say!("\n📝 Would copy {} to {}", current_exe.display(), install_path.display());
+        return Ok(());
+    }
+
     // Create install directory if it doesn't exist
     if !install_dir.exists() {
-        println!("Creating directory: {}", install_dir.display());
+        say!("Creating directory: {}", install_dir.display());
         fs::create_dir_all(&install_dir)
             .context(format!("Failed to create directory: {}", install_dir.display()))?;
     }
 
     // Copy the binary
-    println!("Copying binary...");
+    say!("Copying binary...");
     fs::copy(&current_exe, &install_path).context(format!(
         "Failed to copy binary from {} to {}",
         current_exe.display(),
@@ -44,13 +50,13 @@ pub async fn run(_config: &Config) -> Result<()> {
     perms.set_mode(0o755);
     fs::set_permissions(&install_path, perms)?;
 
-    println!("✅ cwrdd-make installed successfully!\n");
-    println!("Installation location: {}", install_path.display());
-    println!("\nYou can now run:");
-    println!("  cwrdd-make --help");
-    println!("  cwrdd-make build");
-    println!("  cwrdd-make migrate");
-    println!("  etc.\n");
+    say!("✅ cwrdd-make installed successfully!\n");
+    say!("Installation location: {}", install_path.display());
+    say!("\nYou can now run:");
+    say!("  cwrdd-make --help");
+    say!("  cwrdd-make build");
+    say!("  cwrdd-make migrate");
+    say!("  etc.\n");
 
     // Check if we need to add to PATH
     if !is_in_path(&install_dir) {
@@ -92,29 +98,29 @@ fn is_in_path(dir: &Path) -> bool {
 /// Check PATH and provide feedback
 fn check_path(install_dir: &Path) -> Result<()> {
     if is_in_path(install_dir) {
-        println!("✓ {} is in your PATH", install_dir.display());
+        say!("✓ {} is in your PATH", install_dir.display());
         Ok(())
     } else {
-        println!("⚠️  {} is not in your PATH", install_dir.display());
-        println!("   (This is okay, we'll show you how to add it after installation)");
+        say!("⚠️  {} is not in your PATH", install_dir.display());
+        say!("   (This is okay, we'll show you how to add it after installation)");
         Ok(())
     }
 }
 
 /// Print instructions for adding to PATH
 fn print_path_instructions(install_dir: &Path) {
-    println!("📝 To add {} to your PATH:", install_dir.display());
-    println!("\nFor bash, add this to ~/.bashrc:");
-    println!("  export PATH=\"{}:$PATH\"", install_dir.display());
-    println!("\nFor zsh, add this to ~/.zshrc:");
-    println!("  export PATH=\"{}:$PATH\"", install_dir.display());
-    println!("\nFor fish, run:");
-    println!(
+    say!("📝 To add {} to your PATH:", install_dir.display());
+    say!("\nFor bash, add this to ~/.bashrc:");
+    say!("  export PATH=\"{}:$PATH\"", install_dir.display());
+    say!("\nFor zsh, add this to ~/.zshrc:");
+    say!("  export PATH=\"{}:$PATH\"", install_dir.display());
+    say!("\nFor fish, run:");
+    say!(
         "  fish_add_path {}",
         install_dir.display()
     );
-    println!("\nThen reload your shell or run:");
-    println!("  source ~/.bashrc   # or ~/.zshrc");
+    say!("\nThen reload your shell or run:");
+    say!("  source ~/.bashrc   # or ~/.zshrc");
 }
 
 #[cfg(test)]
@@ -139,6 +145,6 @@ mod tests {
         // Test with paths that should be in PATH
         let result = is_in_path(Path::new("/usr/bin"));
         // This should generally be true, but might not be in all test environments
-        println!("Is /usr/bin in PATH: {}", result);
+        say!("Is /usr/bin in PATH: {}", result);
     }
 }
@@ -1,12 +1,23 @@
+use crate::changelog;
 use crate::config::Config;
+use crate::lint;
+use crate::output;
+use crate::say;
+use crate::profile::Database;
 use crate::task::{command_exists, run_tasks, verify_directory, Task};
+use crate::tasks::shadow::{self, ShadowDatabase};
 use anyhow::{bail, Context, Result};
 use std::fs;
+use std::path::Path;
+use std::time::Duration;
 
 const LIQUIBASE_VERSION: &str = "4.29.2";
 const LIQUIBASE_URL: &str =
     "https://github.com/liquibase/liquibase/releases/download/v4.29.2/liquibase-4.29.2.tar.gz";
 
+/// Upper bound on a single Liquibase run, so a hung JDBC connection doesn't block forever
+const LIQUIBASE_TIMEOUT: Duration = Duration::from_secs(600);
+
 /// Check if liquibase is installed
 fn check_liquibase() -> Result<bool> {
     Ok(command_exists("liquibase"))
@@ -43,14 +54,54 @@ After installation, run this command again.
     )
 }
 
-/// Generate migration diff from schema files
+/// Create a Liquibase task in the db directory, connected to the selected profile's database.
+///
+/// Connection settings are passed as arguments and `LIQUIBASE_COMMAND_PASSWORD`,
+/// so they take precedence over `liquibase.properties`.
+pub(crate) fn liquibase_task<'a>(
+    config: &Config,
+    name: &str,
+    args: impl IntoIterator<Item = &'a str>,
+) -> Result<Task> {
+    liquibase_task_for(config, &config.profile()?.database()?, name, args)
+}
+
+/// Create a Liquibase task in the db directory, connected to `db`
+fn liquibase_task_for<'a>(
+    config: &Config,
+    db: &Database,
+    name: &str,
+    args: impl IntoIterator<Item = &'a str>,
+) -> Result<Task> {
+    let mut all_args = db.liquibase_args();
+    all_args.extend(args.into_iter().map(str::to_string));
+
+    Ok(config.task(name, "liquibase")
+        .args(all_args)
+        .envs(db.liquibase_env())
+        .working_dir(config.db_path().to_string_lossy().to_string())
+        .timeout(LIQUIBASE_TIMEOUT))
+}
+
+/// Generate a changeset from the difference between the schema files and the migrations.
+///
+/// Two shadow databases are started: `desired` gets `db/schema/*.sql` and
+/// `current` gets the existing migrations. Liquibase diffs them and writes the
+/// changes needed to turn `current` into `desired`. The profile's database is
+/// never touched, and both containers are removed afterwards.
 pub async fn diff(config: &Config) -> Result<()> {
-    println!("🔍 Generating migration diff from schema files\n");
+    say!("🔍 Generating migration diff from schema files\n");
 
-    // Check liquibase
+    // Check liquibase and podman
     if !check_liquibase()? {
         bail!("{}", liquibase_install_instructions());
     }
+    if !command_exists("podman") {
+        bail!(
+            "podman is required to run the shadow databases.\n\
+             Run: cwrdd-make get-tools"
+        );
+    }
 
     // Verify db directory exists
     let db_path = config.db_path();
@@ -59,8 +110,8 @@ pub async fn diff(config: &Config) -> Result<()> {
     let schema_path = db_path.join("schema");
     verify_directory(&schema_path)?;
 
-    println!("Database path: {}", db_path.display());
-    println!("Schema path: {}\n", schema_path.display());
+    say!("Database path: {}", db_path.display());
+    say!("Schema path: {}\n", schema_path.display());
 
     // Check if there are any schema files
     let schema_files: Vec<_> = fs::read_dir(&schema_path)?
@@ -77,86 +128,270 @@ pub async fn diff(config: &Config) -> Result<()> {
         bail!("No schema files found in {}", schema_path.display());
     }
 
-    println!("Found {} schema file(s):", schema_files.len());
+    say!("Found {} schema file(s):", schema_files.len());
     for file in &schema_files {
-        println!("  - {}", file.file_name().to_string_lossy());
+        say!("  - {}", file.file_name().to_string_lossy());
     }
-    println!();
+    say!();
 
     // Create timestamp for changeset
     let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
-    let changeset_file = format!("{}-generated.xml", timestamp);
-
-    println!("⚠️  Note: This command requires a reference database.");
-    println!("   You'll need to:");
-    println!("   1. Have a PostgreSQL database running");
-    println!("   2. Configure connection in db/liquibase.properties");
-    println!("   3. Run this command to generate diff\n");
-
-    // For now, we'll create a task that runs liquibase diffChangeLog
-    // This will compare the actual database with the schema files
-    let tasks = vec![Task::new("liquibase diffChangeLog", "liquibase")
-        .args([
-            "diffChangeLog",
-            "--changeLogFile",
-            &format!("migrations/{}", changeset_file),
-        ])
-        .working_dir(db_path.to_string_lossy().to_string())];
-
-    match run_tasks(tasks).await {
-        Ok(_) => {
-            println!("✅ Generated migration: migrations/{}", changeset_file);
-            println!("\nNext steps:");
-            println!("  1. Review the generated changeset:");
-            println!("     cat db/migrations/{}", changeset_file);
-            println!("  2. Apply the migration:");
-            println!("     cwrdd-make migrate");
-            println!("  3. Commit both schema and migration:");
-            println!("     git add db/schema/ db/migrations/");
-            Ok(())
-        }
+    let changeset_file = format!("migrations/{}-generated.xml", timestamp);
+
+    say!("🐘 Starting shadow databases...\n");
+    let (desired, current) = shadow::start_pair(config, "desired", "current").await?;
+    let result = diff_shadows(config, &desired, &current, &schema_path, &changeset_file).await;
+    say!("🧹 Removing shadow databases...\n");
+    shadow::remove_all(config, &[desired, current]).await;
+
+    let changesets = match result {
+        Ok(changesets) => changesets,
         Err(e) => {
             eprintln!("\n❌ Error generating diff:");
-            eprintln!("   {}", e);
+            eprintln!("   {:#}", e);
             eprintln!("\nCommon issues:");
-            eprintln!("  - Database not running or not accessible");
-            eprintln!("  - Connection settings incorrect in liquibase.properties");
-            eprintln!("  - JDBC driver not available");
+            eprintln!("  - A schema file has a syntax error");
+            eprintln!("  - An existing migration fails on an empty database");
+            eprintln!("  - The postgres image could not be pulled");
             bail!("Migration diff failed")
         }
+    };
+
+    if config.is_dry_run() {
+        return Ok(());
     }
+
+    output::record("changesets", changesets);
+    if changesets == 0 {
+        let _ = fs::remove_file(db_path.join(&changeset_file));
+        say!("✅ No differences: the migrations already match db/schema/");
+        return Ok(());
+    }
+
+    output::record("changeset_file", changeset_file.as_str());
+    changelog::register(&db_path, &changeset_file)?;
+    say!("✅ Generated {} changeset(s) in {}", changesets, changeset_file);
+    say!("✅ Included it in {}", changelog::CHANGELOG_FILE);
+    say!("\nNext steps:");
+    say!("  1. Review the generated changeset:");
+    say!("     cat db/{}", changeset_file);
+    say!("  2. Apply the migration:");
+    say!("     cwrdd-make migrate");
+    say!("  3. Commit both schema and migration:");
+    say!("     git add db/schema/ db/migrations/");
+    Ok(())
+}
+
+/// Scaffold an empty changeset named after `name` and include it in the changelog
+pub async fn new(config: &Config, name: &str) -> Result<()> {
+    let slug = slugify(name);
+    if slug.is_empty() {
+        bail!("Migration name '{}' has no letters or digits", name);
+    }
+
+    let db_path = config.db_path();
+    verify_directory(&db_path)?;
+
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let id = format!("{}-{}", timestamp, slug);
+    let changeset_file = format!("migrations/{}.xml", id);
+    let path = db_path.join(&changeset_file);
+    if path.exists() {
+        bail!("Migration already exists: {}", path.display());
+    }
+
+    let author = changeset_author(config).await;
+    output::record("changeset_file", changeset_file.as_str());
+
+    if config.is_dry_run() {
+        say!(
+            "📝 Would create db/{} and include it in {}",
+            changeset_file,
+            changelog::CHANGELOG_FILE
+        );
+        return Ok(());
+    }
+
+    fs::write(&path, changeset_template(&id, &author))
+        .context(format!("Failed to write {}", path.display()))?;
+    changelog::register(&db_path, &changeset_file)?;
+
+    say!("✅ Created db/{}", changeset_file);
+    say!("✅ Included it in {}", changelog::CHANGELOG_FILE);
+    say!("\nNext steps:");
+    say!("  1. Add changes and a rollback to the changeset");
+    say!("  2. Apply it:");
+    say!("     cwrdd-make migrate");
+    Ok(())
+}
+
+/// Check that every migration file is included in the changelog and every include exists
+pub async fn check(config: &Config) -> Result<()> {
+    say!("🔍 Checking db/{}\n", changelog::CHANGELOG_FILE);
+
+    let db_path = config.db_path();
+    verify_directory(&db_path)?;
+
+    check_includes(&db_path)?;
+    say!("✅ Every migration file is included and every include exists");
+    Ok(())
+}
+
+/// Fail if a migration file would be skipped or an include is broken
+fn check_includes(db_path: &Path) -> Result<()> {
+    let check = changelog::check_includes(db_path)?;
+    output::record("not_included", check.not_included.clone());
+    output::record("missing_includes", check.missing.clone());
+    if check.is_ok() {
+        return Ok(());
+    }
+
+    for file in &check.not_included {
+        say!("❌ {} is not included, so it will never run", file);
+    }
+    for file in &check.missing {
+        say!("❌ {} is included but does not exist", file);
+    }
+    bail!(
+        "{} problem(s) with the <include> entries in db/{}",
+        check.not_included.len() + check.missing.len(),
+        changelog::CHANGELOG_FILE
+    )
+}
+
+/// Lower-case `name` with runs of other characters replaced by single dashes
+fn slugify(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The changeset author: git's user.name, falling back to $USER
+async fn changeset_author(config: &Config) -> String {
+    let git = Task::new("git user.name", "git")
+        .args(["config", "user.name"])
+        .working_dir(config.repo_path.to_string_lossy().to_string())
+        .timeout(Duration::from_secs(10))
+        .capture()
+        .await;
+    git.ok()
+        .map(|output| output.stdout.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| "cwrdd".to_string())
+}
+
+/// An empty changeset for `migrate-new`
+fn changeset_template(id: &str, author: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<databaseChangeLog
+    xmlns="http://www.liquibase.org/xml/ns/dbchangelog"
+    xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+    xsi:schemaLocation="http://www.liquibase.org/xml/ns/dbchangelog
+        http://www.liquibase.org/xml/ns/dbchangelog/dbchangelog-4.20.xsd">
+
+    <changeSet id="{}" author="{}">
+        <!-- Add changes here, e.g. <sql>ALTER TABLE users ADD COLUMN nickname VARCHAR(100);</sql> -->
+
+        <rollback>
+            <!-- Undo the changes above -->
+        </rollback>
+    </changeSet>
+
+</databaseChangeLog>
+"#,
+        xml_escape(id),
+        xml_escape(author)
+    )
+}
+
+/// Escape text for use in an XML attribute
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Build both shadow databases and write the diff, returning how many changesets it has
+async fn diff_shadows(
+    config: &Config,
+    desired: &ShadowDatabase,
+    current: &ShadowDatabase,
+    schema_path: &Path,
+    changeset_file: &str,
+) -> Result<usize> {
+    say!("📐 Applying schema files to the desired database...");
+    let applied = desired.apply_sql_dir(config, schema_path).await?;
+    say!("✅ Applied {} schema file(s)\n", applied);
+
+    say!("📜 Running existing migrations on the current database...");
+    liquibase_task_for(config, &current.db, "liquibase update (shadow)", ["update"])?
+        .execute()
+        .await?;
+
+    let reference = [
+        format!("--referenceUrl={}", desired.db.jdbc_url()),
+        format!("--referenceUsername={}", desired.db.user),
+    ];
+    let mut args = vec!["diffChangeLog", "--changeLogFile", changeset_file];
+    args.extend(reference.iter().map(String::as_str));
+    liquibase_task_for(config, &current.db, "liquibase diffChangeLog", args)?
+        .env("LIQUIBASE_COMMAND_REFERENCE_PASSWORD", &desired.db.password)
+        .execute()
+        .await?;
+
+    if config.is_dry_run() || !config.db_path().join(changeset_file).exists() {
+        return Ok(0);
+    }
+    Ok(changelog::Changelog::load(&config.db_path(), changeset_file)?
+        .changesets
+        .len())
 }
 
 /// Apply pending migrations
 pub async fn apply(config: &Config) -> Result<()> {
-    println!("🚀 Applying database migrations\n");
+    apply_to(config, None).await
+}
 
-    // Check liquibase
-    if !check_liquibase()? {
-        bail!("{}", liquibase_install_instructions());
+/// Apply pending migrations, stopping after the `<tagDatabase>` change for `tag` if given
+pub async fn apply_to(config: &Config, tag: Option<&str>) -> Result<()> {
+    match tag {
+        Some(tag) => say!("🚀 Applying database migrations up to tag '{}'\n", tag),
+        None => say!("🚀 Applying database migrations\n"),
     }
 
-    // Verify db directory exists
     let db_path = config.db_path();
-    verify_directory(&db_path)?;
+    check_can_apply(config, tag)?;
 
-    println!("Database path: {}\n", db_path.display());
+    say!("Database path: {}\n", db_path.display());
 
     // Run liquibase update
-    let tasks = vec![Task::new("liquibase update", "liquibase")
-        .args(["update"])
-        .working_dir(db_path.to_string_lossy().to_string())];
-
-    run_tasks(tasks).await?;
+    let tag_arg = tag.map(|tag| format!("--tag={}", tag));
+    let task = match &tag_arg {
+        Some(arg) => liquibase_task(config, "liquibase update", ["updateToTag", arg.as_str()])?,
+        None => liquibase_task(config, "liquibase update", ["update"])?,
+    };
+
+    run_tasks(vec![task]).await?;
+
+    if !config.is_dry_run() {
+        let db = config.profile()?.database()?;
+        if let Err(e) = changelog::record_checksums(&db, &db_path).await {
+            say!("⚠️  Could not record changeset checksums: {:#}", e);
+        }
+    }
 
-    println!("✅ Migrations applied successfully!");
+    say!("✅ Migrations applied successfully!");
     Ok(())
 }
 
-/// Show migration status
-pub async fn status(config: &Config) -> Result<()> {
-    println!("📊 Checking migration status\n");
-
+/// Fail if migrations can't be applied (up to `tag`), without touching the database
+pub fn check_can_apply(config: &Config, tag: Option<&str>) -> Result<()> {
     // Check liquibase
     if !check_liquibase()? {
         bail!("{}", liquibase_install_instructions());
@@ -166,21 +401,209 @@ pub async fn status(config: &Config) -> Result<()> {
     let db_path = config.db_path();
     verify_directory(&db_path)?;
 
-    println!("Database path: {}\n", db_path.display());
+    // Refuse to run with changesets that would be silently skipped
+    check_includes(&db_path)?;
+
+    if let Some(tag) = tag {
+        let tags = changelog::Changelog::load(&db_path, changelog::CHANGELOG_FILE)?.tags()?;
+        if !tags.iter().any(|t| t == tag) {
+            let known = if tags.is_empty() {
+                "The changelog has no <tagDatabase> changes".to_string()
+            } else {
+                format!("Tags in the changelog: {}", tags.join(", "))
+            };
+            bail!("Unknown tag '{}'. {}", tag, known);
+        }
+    }
+    Ok(())
+}
 
-    // Run liquibase status
-    let tasks = vec![Task::new("liquibase status", "liquibase")
-        .args(["status", "--verbose"])
-        .working_dir(db_path.to_string_lossy().to_string())];
+/// Show migration status by comparing the changelog with `databasechangelog`
+pub async fn status(config: &Config) -> Result<()> {
+    say!("📊 Checking migration status\n");
 
-    run_tasks(tasks).await?;
+    // Verify db directory exists
+    let db_path = config.db_path();
+    verify_directory(&db_path)?;
+
+    let db = config.profile()?.database()?;
+    say!("Database path: {}", db_path.display());
+    say!("Database: {}\n", db.jdbc_url());
+
+    if config.is_dry_run() {
+        say!("📝 Would compare {} with databasechangelog", changelog::CHANGELOG_FILE);
+        return Ok(());
+    }
+
+    let report = changelog::status(&db, &db_path).await?;
+    print_report(&report);
+
+    output::record("applied_changesets", report.applied.len());
+    output::record("pending_changesets", report.pending.len());
+    output::record("checksum_mismatches", report.mismatched.clone());
+    output::record("unknown_changesets", report.unknown.clone());
+    output::record("up_to_date", report.is_up_to_date());
+
+    Ok(())
+}
 
+/// Check changesets for unsafe operations, failing on errors (or warnings, with `strict`).
+///
+/// Only pending changesets are checked unless `all` is set, which also works
+/// without a database.
+pub async fn lint(config: &Config, all: bool, strict: bool) -> Result<()> {
+    say!("🧹 Linting migrations\n");
+
+    let db_path = config.db_path();
+    verify_directory(&db_path)?;
+    let changelog = changelog::Changelog::load(&db_path, changelog::CHANGELOG_FILE)?;
+
+    let changesets: Vec<changelog::ChangeSet> = if all {
+        changelog.changesets
+    } else if config.is_dry_run() {
+        say!("📝 Would lint changesets not yet in databasechangelog");
+        return Ok(());
+    } else {
+        let db = config.profile()?.database()?;
+        let report = changelog::status(&db, &db_path).await?;
+        changelog
+            .changesets
+            .into_iter()
+            .filter(|changeset| report.pending.contains(&changeset.key()))
+            .collect()
+    };
+
+    let schema = lint::SchemaColumns::load(&db_path.join("schema"))?;
+    let findings = lint::lint(&changesets, &schema, &config.lint)?;
+    output::record("changesets", changesets.len());
+    output::record("findings", serde_json::to_value(&findings)?);
+
+    for finding in &findings {
+        let icon = match finding.severity {
+            lint::Severity::Error => "❌",
+            _ => "⚠️ ",
+        };
+        say!("{} [{}] {}", icon, finding.rule, finding.changeset);
+        say!("     {}", finding.message);
+    }
+    if !findings.is_empty() {
+        say!("");
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == lint::Severity::Error)
+        .count();
+    let warnings = findings.len() - errors;
+    if errors > 0 || strict && warnings > 0 {
+        bail!("{} error(s), {} warning(s)", errors, warnings);
+    }
+
+    if warnings > 0 {
+        say!("✅ {} changeset(s) linted, no errors ({} warning(s))", changesets.len(), warnings);
+    } else {
+        say!("✅ {} changeset(s) linted, no problems found", changesets.len());
+    }
     Ok(())
 }
 
-/// Rollback last migration
-pub async fn rollback(config: &Config) -> Result<()> {
-    println!("⏪ Rolling back last migration\n");
+/// Print a migration report, listing anything that needs attention
+fn print_report(report: &changelog::MigrationReport) {
+    say!("✅ {} changeset(s) applied", report.applied.len());
+
+    if report.pending.is_empty() {
+        say!("✅ No pending changesets");
+    } else {
+        say!("⏳ {} changeset(s) pending:", report.pending.len());
+        for key in &report.pending {
+            say!("     {}", key);
+        }
+    }
+
+    if !report.mismatched.is_empty() {
+        say!(
+            "\n⚠️  {} applied changeset(s) have been edited since they were applied:",
+            report.mismatched.len()
+        );
+        for key in &report.mismatched {
+            say!("     {}", key);
+        }
+        say!("   Revert the edits and add a new changeset instead.");
+    }
+
+    if !report.unknown.is_empty() {
+        say!(
+            "\n⚠️  {} applied changeset(s) are no longer in the changelog:",
+            report.unknown.len()
+        );
+        for key in &report.unknown {
+            say!("     {}", key);
+        }
+    }
+
+    if !report.pending.is_empty() {
+        say!("\nApply them with: cwrdd-make migrate");
+    }
+}
+
+/// How far `rollback` should go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RollbackTarget {
+    /// The most recent N changesets
+    Count(u32),
+    /// Everything applied after the changeset with this tag
+    Tag(String),
+    /// Everything applied after this time
+    Date(String),
+}
+
+impl RollbackTarget {
+    /// Liquibase's rollback command, its SQL preview command, and their argument
+    fn liquibase_args(&self) -> (&'static str, &'static str, String) {
+        match self {
+            Self::Count(count) => {
+                ("rollbackCount", "rollbackCountSQL", format!("--count={}", count))
+            }
+            Self::Tag(tag) => ("rollback", "rollbackSQL", format!("--tag={}", tag)),
+            Self::Date(date) => {
+                ("rollbackToDate", "rollbackToDateSQL", format!("--date={}", date))
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Count(1) => "the most recent changeset".to_string(),
+            Self::Count(count) => format!("the {} most recent changesets", count),
+            Self::Tag(tag) => format!("every changeset applied after tag '{}'", tag),
+            Self::Date(date) => format!("every changeset applied after {}", date),
+        }
+    }
+}
+
+/// Parse a `--to-date` value into the `yyyy-MM-ddTHH:mm:ss` form Liquibase expects
+pub fn parse_rollback_date(value: &str) -> Result<String> {
+    let value = value.trim();
+    let parsed = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M"))
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        })
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid date '{}'. Use YYYY-MM-DD, YYYY-MM-DD HH:MM or YYYY-MM-DDTHH:MM:SS",
+                value
+            )
+        })?;
+    Ok(parsed.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+/// Roll back migrations, previewing the SQL and asking for confirmation unless `yes` is set
+pub async fn rollback(config: &Config, target: RollbackTarget, yes: bool) -> Result<()> {
+    say!("⏪ Rolling back {}\n", target.describe());
 
     // Check liquibase
     if !check_liquibase()? {
@@ -191,134 +614,213 @@ pub async fn rollback(config: &Config) -> Result<()> {
     let db_path = config.db_path();
     verify_directory(&db_path)?;
 
-    println!("Database path: {}\n", db_path.display());
-    println!("⚠️  Warning: This will rollback the most recent changeset.");
-    println!("   Make sure this is what you want to do!\n");
+    say!("Database path: {}\n", db_path.display());
+
+    if let RollbackTarget::Tag(tag) = &target {
+        if !config.is_dry_run() {
+            check_tag_exists(config, tag).await?;
+        }
+    }
+
+    let (command, preview_command, arg) = target.liquibase_args();
+
+    // Preview the SQL Liquibase would run
+    let preview_args = [preview_command, arg.as_str()];
+    let preview = liquibase_task(config, "liquibase rollback preview", preview_args)?
+        .capture()
+        .await?;
+    if !preview.success() {
+        for line in preview.stderr.lines().chain(preview.stdout.lines()) {
+            eprintln!("   {}", line);
+        }
+        bail!(
+            "Task 'liquibase rollback preview' failed with exit code: {}",
+            preview.exit_code.unwrap_or(-1)
+        );
+    }
+    if !config.is_dry_run() {
+        say!("📜 SQL that will run:\n");
+        for line in preview.stdout.lines() {
+            say!("   {}", line);
+        }
+        say!();
+        output::record("rollback_sql", preview.stdout.clone());
+    }
+
+    if !yes && !config.is_dry_run() {
+        let question = format!("⚠️  Roll back {}?", target.describe());
+        if !output::confirm(&question).await? {
+            say!("❎ Rollback cancelled, nothing was changed");
+            output::record("rolled_back", false);
+            return Ok(());
+        }
+    }
 
     // Run liquibase rollback
-    let tasks = vec![Task::new("liquibase rollback", "liquibase")
-        .args(["rollbackCount", "1"])
-        .working_dir(db_path.to_string_lossy().to_string())];
+    let tasks = vec![liquibase_task(config, "liquibase rollback", [command, arg.as_str()])?];
 
     run_tasks(tasks).await?;
 
-    println!("✅ Rollback completed successfully!");
+    // Forget the checksums of what was rolled back, so it can be edited and reapplied
+    if !config.is_dry_run() {
+        let db = config.profile()?.database()?;
+        if let Err(e) = changelog::record_checksums(&db, &db_path).await {
+            say!("⚠️  Could not update changeset checksums: {:#}", e);
+        }
+    }
+
+    output::record("rolled_back", true);
+    say!("✅ Rollback completed successfully!");
+    Ok(())
+}
+
+/// Fail with the known tags if no applied changeset has `tag`
+async fn check_tag_exists(config: &Config, tag: &str) -> Result<()> {
+    let client = config.profile()?.database()?.connect().await?;
+    let tags: Vec<String> = changelog::applied(&client)
+        .await?
+        .into_iter()
+        .filter_map(|changeset| changeset.tag)
+        .collect();
+
+    if !tags.iter().any(|t| t == tag) {
+        if tags.is_empty() {
+            bail!(
+                "Unknown tag '{}'. No tags have been set; add one with: cwrdd-make migrate tag <name>",
+                tag
+            );
+        }
+        bail!("Unknown tag '{}'. Available tags: {}", tag, tags.join(", "));
+    }
     Ok(())
 }
 
-/// Seed database with development data
-pub async fn seed(config: &Config) -> Result<()> {
-    println!("🌱 Seeding database with development data\n");
+/// Tag the most recently applied changeset, so `rollback --to-tag` can return to this state
+pub async fn tag(config: &Config, name: &str) -> Result<()> {
+    say!("🏷️  Tagging the current database state as '{}'\n", name);
+
+    if name.trim().is_empty() {
+        bail!("Tag name cannot be empty");
+    }
+
+    // Check liquibase
+    if !check_liquibase()? {
+        bail!("{}", liquibase_install_instructions());
+    }
 
     // Verify db directory exists
     let db_path = config.db_path();
     verify_directory(&db_path)?;
 
-    let seed_file = db_path.join("scripts/seed-dev-data.sql");
-    if !seed_file.exists() {
-        bail!("Seed file not found: {}", seed_file.display());
-    }
-
-    println!("Database path: {}", db_path.display());
-    println!("Seed file: {}\n", seed_file.display());
-
-    // Read database connection from liquibase.properties
-    let props_file = db_path.join("liquibase.properties");
-    let props_content =
-        fs::read_to_string(&props_file).context("Failed to read liquibase.properties")?;
-
-    // Parse connection details (simple parsing)
-    let url = extract_property(&props_content, "url")?;
-    let username = extract_property(&props_content, "username")?;
-    let password = extract_property(&props_content, "password")?;
-
-    // Extract database name from JDBC URL
-    let db_name = extract_db_name(&url)?;
-
-    println!("Connecting to database: {}", db_name);
-    println!("User: {}\n", username);
-
-    // Use psql to run the seed file
-    let tasks = vec![Task::new("psql seed", "psql")
-        .args([
-            "-h",
-            "localhost",
-            "-U",
-            &username,
-            "-d",
-            &db_name,
-            "-f",
-            seed_file.to_str().unwrap(),
-        ])
-        .env("PGPASSWORD", &password)
-        .working_dir(db_path.to_string_lossy().to_string())];
+    if !config.is_dry_run() {
+        let client = config.profile()?.database()?.connect().await?;
+        let applied = changelog::applied(&client).await?;
+        if applied.is_empty() {
+            bail!("No migrations have been applied yet, so there is nothing to tag");
+        }
+        if applied.iter().any(|changeset| changeset.tag.as_deref() == Some(name)) {
+            bail!("Tag '{}' already exists", name);
+        }
+    }
+
+    let tag_arg = format!("--tag={}", name);
+    let tasks = vec![liquibase_task(config, "liquibase tag", ["tag", tag_arg.as_str()])?];
 
     run_tasks(tasks).await?;
 
-    println!("✅ Database seeded successfully!");
+    output::record("tag", name);
+    say!("✅ Tagged! Roll back to this point with: cwrdd-make rollback --to-tag {}", name);
     Ok(())
 }
 
-/// Extract property value from liquibase.properties
-fn extract_property(content: &str, key: &str) -> Result<String> {
-    for line in content.lines() {
-        let line = line.trim();
-        if line.starts_with(key) {
-            // Split on first : and take everything after
-            if let Some(colon_pos) = line.find(':') {
-                let value = &line[colon_pos + 1..];
-                return Ok(value.trim().to_string());
-            }
-        }
-    }
-    bail!("Property '{}' not found in liquibase.properties", key)
-}
-
-/// Extract database name from JDBC URL
-fn extract_db_name(url: &str) -> Result<String> {
-    // JDBC URL format: jdbc:postgresql://localhost:5432/dbname
-    if let Some(last_part) = url.split('/').last() {
-        let db_name = last_part.split('?').next().unwrap_or(last_part);
-        return Ok(db_name.to_string());
-    }
-    bail!("Could not extract database name from URL: {}", url)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_extract_db_name() {
-        let url = "jdbc:postgresql://localhost:5432/cwrdd_dev";
-        let result = extract_db_name(url);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "cwrdd_dev");
+    fn test_parse_rollback_date() {
+        assert_eq!(parse_rollback_date("2026-10-17").unwrap(), "2026-10-17T00:00:00");
+        assert_eq!(parse_rollback_date("2026-10-17 09:30").unwrap(), "2026-10-17T09:30:00");
+        assert_eq!(parse_rollback_date("2026-10-17T09:30:15").unwrap(), "2026-10-17T09:30:15");
+        assert!(parse_rollback_date("17/10/2026").is_err());
     }
 
     #[test]
-    fn test_extract_db_name_with_params() {
-        let url = "jdbc:postgresql://localhost:5432/cwrdd_dev?ssl=true";
-        let result = extract_db_name(url);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "cwrdd_dev");
+    fn test_rollback_target_liquibase_args() {
+        assert_eq!(
+            RollbackTarget::Count(3).liquibase_args(),
+            ("rollbackCount", "rollbackCountSQL", "--count=3".to_string())
+        );
+        assert_eq!(
+            RollbackTarget::Tag("v1".to_string()).liquibase_args(),
+            ("rollback", "rollbackSQL", "--tag=v1".to_string())
+        );
+        assert_eq!(
+            RollbackTarget::Date("2026-10-17T00:00:00".to_string()).liquibase_args(),
+            ("rollbackToDate", "rollbackToDateSQL", "--date=2026-10-17T00:00:00".to_string())
+        );
     }
 
     #[test]
-    fn test_extract_property() {
-        let content = "url: jdbc:postgresql://localhost:5432/cwrdd_dev\nusername: cwrdd_user\npassword: cwrdd_password";
-
-        let url = extract_property(content, "url");
-        assert!(url.is_ok(), "Failed to extract url: {:?}", url);
-        let url_value = url.unwrap();
-        assert!(
-            url_value.contains("cwrdd_dev"),
-            "URL '{}' doesn't contain 'cwrdd_dev'",
-            url_value
-        );
+    fn test_slugify() {
+        assert_eq!(slugify("Add organizations table"), "add-organizations-table");
+        assert_eq!(slugify("  users: add  nickname! "), "users-add-nickname");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[tokio::test]
+    async fn test_new_creates_included_changeset() {
+        let repo = tempfile::TempDir::new().unwrap();
+        let migrations = repo.path().join("db/migrations");
+        fs::create_dir_all(&migrations).unwrap();
+        fs::write(
+            migrations.join("changelog.xml"),
+            "<databaseChangeLog>\n</databaseChangeLog>\n",
+        )
+        .unwrap();
+        let config = Config {
+            repo_path: repo.path().to_path_buf(),
+            ..Config::default()
+        };
+
+        new(&config, "Add nickname").await.unwrap();
+
+        let loaded =
+            changelog::Changelog::load(&config.db_path(), changelog::CHANGELOG_FILE).unwrap();
+        assert_eq!(loaded.changesets.len(), 1);
+        assert!(loaded.changesets[0].id.ends_with("-add-nickname"));
+        assert!(changelog::check_includes(&config.db_path()).unwrap().is_ok());
+    }
 
-        let username = extract_property(content, "username");
-        assert!(username.is_ok());
-        assert_eq!(username.unwrap(), "cwrdd_user");
+    #[tokio::test]
+    async fn test_liquibase_task_uses_selected_profile() {
+        let plan = crate::plan::Plan::new();
+        let config = Config {
+            profile_name: "test".to_string(),
+            dry_run: Some(plan.clone()),
+            ..Config::default()
+        };
+
+        liquibase_task(&config, "liquibase update", ["update"])
+            .unwrap()
+            .execute()
+            .await
+            .unwrap();
+
+        let steps = plan.steps();
+        assert_eq!(
+            steps[0].args,
+            [
+                "--url=jdbc:postgresql://localhost:15432/cwrdd_test",
+                "--username=cwrdd_user",
+                "update",
+            ]
+        );
+        assert_eq!(
+            steps[0].env["LIQUIBASE_COMMAND_PASSWORD"],
+            crate::plan::REDACTED
+        );
+        assert!(!steps[0].args.iter().any(|arg| arg.contains("assword")));
+        assert!(steps[0].working_dir.as_deref().unwrap().ends_with("db"));
     }
 }
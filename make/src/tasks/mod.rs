@@ -3,19 +3,33 @@
 //! Each submodule implements one or more cwrdd-make commands:
 //!
 //! - [`build`]: Build the Rust application
-//! - [`certs`]: TLS certificate management (trust/untrust)
+//! - [`certs`]: Dev TLS certificates from the local CA (trust/untrust)
 //! - [`compose`]: Docker/Podman compose management (up/down/logs)
+//! - [`config`]: Show the effective configuration
 //! - [`doc`]: Build and view rustdoc documentation
+//! - [`doctor`]: Diagnose the development setup
 //! - [`get_tools`]: Install development dependencies
 //! - [`install`]: Install cwrdd-make to user's PATH
 //! - [`migrate`]: Database migration commands
+//! - [`snapshot`]: Snapshot and restore the local database
+//! - [`reset`]: Rebuild the local database from scratch
+//! - [`seed`]: Load fixture sets and generate development data
+//! - [`shadow`]: Throwaway PostgreSQL containers for schema diffing
+//! - [`status`]: Show the state of every compose service
 //! - [`test`]: Run tests
 
 pub mod build;
 pub mod certs;
 pub mod compose;
+pub mod config;
 pub mod doc;
+pub mod doctor;
 pub mod get_tools;
 pub mod install;
 pub mod migrate;
+pub mod reset;
+pub mod seed;
+pub mod shadow;
+pub mod snapshot;
+pub mod status;
 pub mod test;
@@ -0,0 +1,99 @@
+//! Rebuild the local database from scratch (`db reset`).
+//!
+//! Drops and recreates the selected profile's database inside its running
+//! `postgres` container, then applies the migrations and seed data, which is
+//! much faster than recreating the container and its volume. Only profiles
+//! marked `disposable` can be reset, and only when `database_url` points at
+//! that container, since migrating and seeding go through `database_url`.
+
+use crate::config::Config;
+use crate::output;
+use crate::say;
+use crate::tasks::snapshot::{ensure_container_database, pg_tool};
+use crate::tasks::{migrate, seed as seed_data};
+use anyhow::{bail, Result};
+
+/// Drop and recreate the database, migrate it (up to `to_tag`) and seed it unless `seed` is false
+pub async fn run(config: &Config, seed: bool, to_tag: Option<&str>) -> Result<()> {
+    let profile = config.profile()?;
+    let db = profile.database()?;
+    say!("♻️  Resetting {}\n", db.name);
+
+    if !profile.disposable {
+        bail!(
+            "Profile '{}' isn't marked as disposable, so its database won't be reset. \
+             If {} can be thrown away, set disposable = true under [profiles.{}]",
+            config.profile_name,
+            db.name,
+            config.profile_name
+        );
+    }
+
+    // Check everything migrating needs before dropping anything
+    let container = profile.container("postgres");
+    ensure_container_database(config, &db, &container).await?;
+    migrate::check_can_apply(config, to_tag)?;
+
+    let pg = |name: &str, tool: &str, args: &[&str]| {
+        pg_tool(config, name, &container, &db.user, tool, args)
+    };
+    pg(
+        "drop database",
+        "dropdb",
+        &["--if-exists", "--force", &db.name],
+    )
+    .execute()
+    .await?;
+    pg(
+        "create database",
+        "createdb",
+        &["--owner", &db.user, &db.name],
+    )
+    .execute()
+    .await?;
+    say!("✅ Recreated empty database {}\n", db.name);
+
+    migrate::apply_to(config, to_tag).await?;
+    if seed {
+        say!();
+        seed_data::run(config, &Default::default()).await?;
+    }
+
+    output::record("seeded", seed);
+    say!("\n✅ {} has been reset", db.name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_refuses_profiles_that_are_not_disposable() {
+        let mut config = Config::default();
+        config.profiles.get_mut("dev").unwrap().disposable = false;
+
+        let err = run(&config, true, None).await.unwrap_err();
+        assert!(err.to_string().contains("isn't marked as disposable"));
+    }
+
+    #[tokio::test]
+    async fn test_refuses_databases_outside_the_local_container() {
+        let plan = crate::plan::Plan::new();
+        let mut config = Config {
+            dry_run: Some(plan.clone()),
+            ..Config::default()
+        };
+        let dev = config.profiles.get_mut("dev").unwrap();
+        dev.database_url = "postgres://db.internal:5432/cwrdd_dev".to_string();
+
+        let err = run(&config, true, None).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("not the local cwrdd-postgres container"),
+            "{}",
+            err
+        );
+        assert!(plan.steps().is_empty());
+    }
+}
@@ -0,0 +1,801 @@
+//! Development data (`seed`).
+//!
+//! Fixture sets are SQL in `db/seeds/`: either `<name>.sql` or a `<name>/`
+//! directory of `.sql` files, run in file name order. `seed` loads the sets
+//! named with `--set`, or the profile's `seed_sets` (`dev` by default).
+//! `--generate users=N` then adds N realistic fake users on top.
+//!
+//! Each load of a set is recorded in `cwrdd_seed_history` with a checksum of
+//! its files, so `up` only seeds when a configured set has never been loaded
+//! or has changed since, and `seed --status` can show what has been applied.
+//!
+//! Every user, fixture or generated, has the password [`DEV_PASSWORD`]; the
+//! hashes are Argon2id with the `argon2` crate's defaults (m=19456, t=2, p=1)
+//! in PHC format. Generated data comes from a ChaCha RNG seeded with `--seed`
+//! (or a random seed that is printed), so the same seed always produces the
+//! same rows.
+
+use crate::changelog::table_exists;
+use crate::config::Config;
+use crate::output;
+use crate::say;
+use anyhow::{bail, Context, Result};
+use argon2::password_hash::{PasswordHasher, SaltString};
+use argon2::Argon2;
+use chrono::{Duration as ChronoDuration, NaiveDate, TimeZone, Utc};
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Password of every seeded user
+pub const DEV_PASSWORD: &str = "cwrdd-dev-password";
+
+/// Table recording every load of a fixture set
+pub const HISTORY_TABLE: &str = "cwrdd_seed_history";
+
+/// Where fixture sets live, relative to the db directory
+const SEEDS_DIR: &str = "seeds";
+
+/// Generated rows inserted per statement
+const BATCH_SIZE: usize = 1000;
+
+/// Something `--generate` can create
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    Users,
+}
+
+impl Generator {
+    const ALL: [Generator; 1] = [Generator::Users];
+
+    fn name(self) -> &'static str {
+        match self {
+            Generator::Users => "users",
+        }
+    }
+}
+
+/// One `--generate <kind>=<count>` request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Generate {
+    pub generator: Generator,
+    pub count: usize,
+}
+
+impl Generate {
+    /// Parse `users=10000`
+    pub fn parse(value: &str) -> Result<Self> {
+        let Some((kind, count)) = value.split_once('=') else {
+            bail!(
+                "Invalid --generate '{}'. Use <kind>=<count>, e.g. users=10000",
+                value
+            );
+        };
+        let Some(generator) = Generator::ALL.into_iter().find(|g| g.name() == kind.trim()) else {
+            bail!(
+                "Unknown --generate kind '{}'. Available kinds: {}",
+                kind,
+                Generator::ALL.map(Generator::name).join(", ")
+            );
+        };
+        let count = count
+            .trim()
+            .parse()
+            .context(format!("Invalid count in --generate '{}'", value))?;
+        Ok(Self { generator, count })
+    }
+}
+
+/// What `seed` should load
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeedOptions {
+    /// Fixture sets to load; the profile's `seed_sets` if empty
+    pub sets: Vec<String>,
+    pub generate: Vec<Generate>,
+    /// RNG seed for generated data, random if unset
+    pub seed: Option<u64>,
+}
+
+/// The last load of a fixture set, from [`HISTORY_TABLE`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SeedRecord {
+    pub set: String,
+    pub checksum: String,
+    pub seeded_at: String,
+}
+
+/// How a fixture set compares with what was last loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedState {
+    /// Loaded, and unchanged since
+    Applied,
+    /// Loaded, but its files have changed since
+    Changed,
+    /// Never loaded
+    NotApplied,
+    /// Loaded, but no longer in `db/seeds/`
+    Removed,
+}
+
+impl SeedState {
+    /// Compare the last load of a set with the checksum of its files now
+    pub fn of(record: Option<&SeedRecord>, checksum: Option<&str>) -> Self {
+        match (record, checksum) {
+            (None, _) => SeedState::NotApplied,
+            (Some(_), None) => SeedState::Removed,
+            (Some(record), Some(checksum)) if record.checksum == checksum => SeedState::Applied,
+            (Some(_), Some(_)) => SeedState::Changed,
+        }
+    }
+}
+
+/// Load fixture sets and generate any requested data
+pub async fn run(config: &Config, options: &SeedOptions) -> Result<()> {
+    say!("🌱 Seeding database with development data\n");
+
+    let db_path = config.db_path();
+    let profile = config.profile()?;
+    let names = if options.sets.is_empty() {
+        &profile.seed_sets
+    } else {
+        &options.sets
+    };
+    let sets = names
+        .iter()
+        .map(|name| Ok((name, set_files(&db_path, name)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let db = profile.database()?;
+
+    if names.is_empty() {
+        say!("Seed sets: none");
+    } else {
+        say!("Seed sets: {}", names.join(", "));
+    }
+    say!("Database: {}\n", db.name);
+    output::record("sets", names.clone());
+
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let generated: usize = options.generate.iter().map(|g| g.count).sum();
+    if generated > 0 {
+        say!(
+            "🎲 Seed {} (pass --seed {} to generate the same data)",
+            seed,
+            seed
+        );
+        output::record("seed", seed);
+    }
+
+    if config.is_dry_run() {
+        for (name, files) in &sets {
+            for file in files {
+                say!("📝 Would run {}", file.display());
+            }
+            say!("📝 Would record {} in {}", name, HISTORY_TABLE);
+        }
+        for generate in &options.generate {
+            say!(
+                "📝 Would generate {} {}",
+                generate.count,
+                generate.generator.name()
+            );
+        }
+        return Ok(());
+    }
+
+    let client = db.connect().await?;
+    for (name, files) in &sets {
+        for file in files {
+            let sql = std::fs::read_to_string(file)
+                .context(format!("Failed to read {}", file.display()))?;
+            client
+                .batch_execute(&sql)
+                .await
+                .context(format!("Failed to run {}", file.display()))?;
+            say!(
+                "✅ Ran {}",
+                file.strip_prefix(&db_path).unwrap_or(file).display()
+            );
+        }
+        record(&client, name, &set_checksum(&db_path, files)?).await?;
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    for generate in &options.generate {
+        match generate.generator {
+            Generator::Users => {
+                let hash = hash_password(DEV_PASSWORD, &mut rng)?;
+                let users = generate_users(&mut rng, generate.count, &hash);
+                let mut inserted = 0;
+                for batch in users.chunks(BATCH_SIZE) {
+                    inserted += client
+                        .execute(&insert_users_sql(batch), &[])
+                        .await
+                        .context("Failed to insert generated users")?;
+                }
+                output::record("generated_users", inserted);
+                say!(
+                    "✅ Generated {} user(s) ({} already existed)",
+                    inserted,
+                    users.len() as u64 - inserted
+                );
+            }
+        }
+    }
+
+    say!("\n✅ Database seeded successfully!");
+    say!("   Every seeded user's password is: {}", DEV_PASSWORD);
+    Ok(())
+}
+
+/// The profile's seed sets that have never been loaded, or have changed since
+pub async fn pending_sets(config: &Config) -> Result<Vec<String>> {
+    let profile = config.profile()?;
+    if config.is_dry_run() {
+        say!(
+            "📝 Would compare {} with {}",
+            profile.seed_sets.join(", "),
+            HISTORY_TABLE
+        );
+        return Ok(profile.seed_sets.clone());
+    }
+
+    let db_path = config.db_path();
+    let client = profile.database()?.connect().await?;
+    let history = history(&client).await?;
+
+    let mut pending = Vec::new();
+    for name in &profile.seed_sets {
+        let checksum = set_checksum(&db_path, &set_files(&db_path, name)?)?;
+        match SeedState::of(history.get(name), Some(&checksum)) {
+            SeedState::Applied => continue,
+            SeedState::Changed => say!("🔄 Seed set {} has changed since it was loaded", name),
+            _ => say!("⏳ Seed set {} hasn't been loaded", name),
+        }
+        pending.push(name.clone());
+    }
+    Ok(pending)
+}
+
+/// Show which fixture sets have been loaded, and whether they have changed since
+pub async fn status(config: &Config) -> Result<()> {
+    say!("🌱 Seed status (profile: {})\n", config.profile_name);
+
+    let db_path = config.db_path();
+    let profile = config.profile()?;
+    let db = profile.database()?;
+    let client = db.connect().await?;
+    let history = history(&client).await?;
+
+    let mut names = available_sets(&db_path)?;
+    names.extend(profile.seed_sets.iter().cloned());
+    names.extend(history.keys().cloned());
+    names.sort();
+    names.dedup();
+
+    say!("Database: {}\n", db.name);
+    let mut sets = Vec::new();
+    for name in &names {
+        let checksum = match set_files(&db_path, name) {
+            Ok(files) => Some(set_checksum(&db_path, &files)?),
+            Err(_) => None,
+        };
+        let record = history.get(name);
+        let state = SeedState::of(record, checksum.as_deref());
+        let configured = profile.seed_sets.contains(name);
+
+        let (icon, detail) = match (state, record) {
+            (SeedState::Applied, Some(r)) => ("✅", format!("loaded {}", r.seeded_at)),
+            (SeedState::Changed, Some(r)) => {
+                ("🔄", format!("changed since loaded {}", r.seeded_at))
+            }
+            (SeedState::Removed, Some(r)) => (
+                "🗑️ ",
+                format!("loaded {}, no longer in db/seeds/", r.seeded_at),
+            ),
+            _ => ("⏳", "not loaded".to_string()),
+        };
+        say!(
+            "{} {}{}: {}",
+            icon,
+            name,
+            if configured { " (configured)" } else { "" },
+            detail
+        );
+        sets.push(serde_json::json!({
+            "set": name,
+            "configured": configured,
+            "state": state,
+            "checksum": checksum,
+            "seeded_at": record.map(|r| r.seeded_at.as_str()),
+        }));
+    }
+    if names.is_empty() {
+        say!("No seed sets in {}", db_path.join(SEEDS_DIR).display());
+    }
+    output::record("sets", sets);
+    Ok(())
+}
+
+/// SHA-256 over the names and contents of a set's files
+pub fn set_checksum(db_path: &Path, files: &[PathBuf]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for file in files {
+        let name = file.strip_prefix(db_path).unwrap_or(file);
+        hasher.update(name.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(std::fs::read(file).context(format!("Failed to read {}", file.display()))?);
+        hasher.update([0]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The last load of each set, by name; empty if nothing has been seeded
+async fn history(client: &tokio_postgres::Client) -> Result<BTreeMap<String, SeedRecord>> {
+    if !table_exists(client, HISTORY_TABLE).await? {
+        return Ok(BTreeMap::new());
+    }
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT DISTINCT ON (set_name) set_name, checksum, \
+                        to_char(seeded_at, 'YYYY-MM-DD HH24:MI:SS') \
+                 FROM {} ORDER BY set_name, seeded_at DESC, id DESC",
+                HISTORY_TABLE
+            ),
+            &[],
+        )
+        .await
+        .context(format!("Failed to read {}", HISTORY_TABLE))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let record = SeedRecord {
+                set: row.get(0),
+                checksum: row.get(1),
+                seeded_at: row.get(2),
+            };
+            (record.set.clone(), record)
+        })
+        .collect())
+}
+
+/// Add a load of `set` to the history
+async fn record(client: &tokio_postgres::Client, set: &str, checksum: &str) -> Result<()> {
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+                 id BIGSERIAL PRIMARY KEY, \
+                 set_name TEXT NOT NULL, \
+                 checksum TEXT NOT NULL, \
+                 seeded_at TIMESTAMPTZ NOT NULL DEFAULT now())",
+            HISTORY_TABLE
+        ))
+        .await
+        .context(format!("Failed to create {}", HISTORY_TABLE))?;
+    client
+        .execute(
+            &format!(
+                "INSERT INTO {} (set_name, checksum) VALUES ($1, $2)",
+                HISTORY_TABLE
+            ),
+            &[&set, &checksum],
+        )
+        .await
+        .context(format!("Failed to record seed set {}", set))?;
+    Ok(())
+}
+
+/// The SQL files of the fixture set called `name`, in the order they run
+pub fn set_files(db_path: &Path, name: &str) -> Result<Vec<PathBuf>> {
+    let sets = available_sets(db_path)?;
+    if !sets.iter().any(|set| set == name) {
+        bail!(
+            "Unknown seed set '{}'. Available sets in {}: {}",
+            name,
+            db_path.join(SEEDS_DIR).display(),
+            if sets.is_empty() {
+                "none".to_string()
+            } else {
+                sets.join(", ")
+            }
+        );
+    }
+
+    let file = db_path.join(SEEDS_DIR).join(format!("{}.sql", name));
+    if file.is_file() {
+        return Ok(vec![file]);
+    }
+    let dir = db_path.join(SEEDS_DIR).join(name);
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .context(format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sql"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Names of the fixture sets in `db/seeds/`
+pub fn available_sets(db_path: &Path) -> Result<Vec<String>> {
+    let dir = db_path.join(SEEDS_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sets = Vec::new();
+    for entry in std::fs::read_dir(&dir).context(format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let name = if path.is_dir() {
+            path.file_name()
+        } else if path.extension().is_some_and(|ext| ext == "sql") {
+            path.file_stem()
+        } else {
+            None
+        };
+        sets.extend(name.map(|n| n.to_string_lossy().to_string()));
+    }
+    sets.sort();
+    sets.dedup();
+    Ok(sets)
+}
+
+/// Argon2id hash of `password` in PHC format, with a salt drawn from `rng`
+pub fn hash_password(password: &str, rng: &mut impl RngCore) -> Result<String> {
+    let mut salt = [0u8; 16];
+    rng.fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt).map_err(|e| anyhow::anyhow!("Invalid salt: {}", e))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+    Ok(hash.to_string())
+}
+
+/// A generated row for the `users` table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedUser {
+    pub id: String,
+    pub email: String,
+    pub password_hash: String,
+    pub email_verified: bool,
+    pub account_status: &'static str,
+    pub created_at: String,
+    pub username: String,
+    pub display_name: String,
+    pub public_bio: Option<&'static str>,
+    pub public_location: Option<&'static str>,
+    pub date_of_birth: String,
+    pub phone: Option<String>,
+}
+
+/// Generate `count` users that satisfy the `users` constraints.
+///
+/// Every user shares `password_hash`: hashing each one separately would
+/// take minutes for large counts, and the password is the same anyway.
+pub fn generate_users(rng: &mut impl Rng, count: usize, password_hash: &str) -> Vec<GeneratedUser> {
+    let joined_from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let born_from = NaiveDate::from_ymd_opt(1945, 1, 1).unwrap();
+
+    (1..=count)
+        .map(|n| {
+            let first = *FIRST_NAMES.choose(rng).unwrap();
+            let last = *LAST_NAMES.choose(rng).unwrap();
+            let handle = format!("{}.{}{}", first, last, n).to_lowercase();
+            let created_at =
+                joined_from + ChronoDuration::seconds(rng.gen_range(0..2 * 365 * 86_400));
+            let born = born_from + ChronoDuration::days(rng.gen_range(0..60 * 365));
+
+            GeneratedUser {
+                id: uuid_v4(rng),
+                email: format!("{}@example.com", handle),
+                password_hash: password_hash.to_string(),
+                email_verified: rng.gen_bool(0.85),
+                account_status: match rng.gen_range(0..100) {
+                    0..=93 => "active",
+                    94..=96 => "suspended",
+                    97..=98 => "banned",
+                    _ => "deleted",
+                },
+                created_at: created_at.format("%Y-%m-%d %H:%M:%S+00").to_string(),
+                username: handle,
+                display_name: format!("{} {}", first, last),
+                public_bio: rng.gen_bool(0.4).then(|| *BIOS.choose(rng).unwrap()),
+                public_location: rng.gen_bool(0.5).then(|| *CITIES.choose(rng).unwrap()),
+                date_of_birth: born.format("%Y-%m-%d").to_string(),
+                phone: rng
+                    .gen_bool(0.3)
+                    .then(|| format!("+44 7700 9{:05}", rng.gen_range(0..100_000))),
+            }
+        })
+        .collect()
+}
+
+/// One multi-row INSERT for `users`, skipping rows that already exist
+fn insert_users_sql(users: &[GeneratedUser]) -> String {
+    let rows: Vec<String> = users
+        .iter()
+        .map(|user| {
+            format!(
+                "({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {})",
+                literal(&user.id),
+                literal(&user.email),
+                literal(&user.password_hash),
+                user.email_verified,
+                literal(user.account_status),
+                literal(&user.created_at),
+                literal(&user.username),
+                literal(&user.display_name),
+                user.public_bio.map(literal).unwrap_or("NULL".to_string()),
+                user.public_location
+                    .map(literal)
+                    .unwrap_or("NULL".to_string()),
+                literal(&user.date_of_birth),
+                user.phone
+                    .as_deref()
+                    .map(literal)
+                    .unwrap_or("NULL".to_string()),
+            )
+        })
+        .collect();
+    format!(
+        "INSERT INTO users (id, email, password_hash, email_verified, account_status, \
+         created_at, updated_at, username, display_name, public_bio, public_location, \
+         date_of_birth, phone) \
+         SELECT id::uuid, email, password_hash, email_verified, account_status, \
+         created_at::timestamptz, created_at::timestamptz, username, display_name, \
+         public_bio, public_location, date_of_birth::date, phone \
+         FROM (VALUES {}) AS v(id, email, password_hash, email_verified, account_status, \
+         created_at, username, display_name, public_bio, public_location, date_of_birth, phone) \
+         ON CONFLICT DO NOTHING",
+        rows.join(",\n")
+    )
+}
+
+/// SQL string literal
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// A random (version 4) UUID
+fn uuid_v4(rng: &mut impl Rng) -> String {
+    let mut bytes: [u8; 16] = rng.gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+const FIRST_NAMES: &[&str] = &[
+    "Aisha", "Alex", "Amara", "Ana", "Ben", "Carlos", "Chen", "Chloe", "Daniel", "Dev", "Elena",
+    "Emeka", "Fatima", "Finn", "Grace", "Hannah", "Hiro", "Ibrahim", "Isla", "Jamal", "Jess",
+    "Jonas", "Kai", "Leila", "Liam", "Lucia", "Maya", "Mohammed", "Nadia", "Noah", "Olga", "Omar",
+    "Priya", "Ravi", "Rosa", "Sam", "Sofia", "Tariq", "Yuki", "Zoe",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Adeyemi",
+    "Ahmed",
+    "Brown",
+    "Campbell",
+    "Chen",
+    "Costa",
+    "Davies",
+    "Dubois",
+    "Evans",
+    "Fernandez",
+    "Garcia",
+    "Hughes",
+    "Ivanova",
+    "Jones",
+    "Kaur",
+    "Kim",
+    "Kowalski",
+    "Lee",
+    "Martin",
+    "Mensah",
+    "Muller",
+    "Nakamura",
+    "Nguyen",
+    "Novak",
+    "Okafor",
+    "Patel",
+    "Rossi",
+    "Santos",
+    "Schmidt",
+    "Silva",
+    "Singh",
+    "Smith",
+    "Taylor",
+    "Thomas",
+    "Walker",
+    "Williams",
+    "Wilson",
+    "Wright",
+    "Yilmaz",
+    "Zhang",
+];
+
+const CITIES: &[&str] = &[
+    "Amsterdam",
+    "Berlin",
+    "Bristol",
+    "Cardiff",
+    "Edinburgh",
+    "Glasgow",
+    "Lagos",
+    "Leeds",
+    "Lisbon",
+    "London",
+    "Madrid",
+    "Manchester",
+    "Nairobi",
+    "Paris",
+    "Toronto",
+    "Warsaw",
+];
+
+const BIOS: &[&str] = &[
+    "Community organiser and tea enthusiast.",
+    "Interested in local democracy and open data.",
+    "Volunteer at the neighbourhood food bank.",
+    "Software developer interested in privacy and democracy.",
+    "Teacher, runner and occasional baker.",
+    "Trying to make the city a little greener.",
+    "Campaigning for better cycle lanes.",
+    "Here to meet people and get involved.",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+
+    #[test]
+    fn test_parse_generate() {
+        assert_eq!(
+            Generate::parse("users=10000").unwrap(),
+            Generate {
+                generator: Generator::Users,
+                count: 10000
+            }
+        );
+        assert!(Generate::parse("users").is_err());
+        assert!(Generate::parse("users=lots").is_err());
+        let err = Generate::parse("orgs=5").unwrap_err();
+        assert!(err.to_string().contains("Available kinds: users"));
+    }
+
+    #[test]
+    fn test_hash_password_verifies() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let hash = hash_password(DEV_PASSWORD, &mut rng).unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+        let parsed = PasswordHash::new(&hash).unwrap();
+        assert!(Argon2::default()
+            .verify_password(DEV_PASSWORD.as_bytes(), &parsed)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_generated_users_are_reproducible_and_valid() {
+        let users = generate_users(&mut ChaCha8Rng::seed_from_u64(42), 500, "hash");
+        assert_eq!(
+            users,
+            generate_users(&mut ChaCha8Rng::seed_from_u64(42), 500, "hash")
+        );
+        assert_ne!(
+            users,
+            generate_users(&mut ChaCha8Rng::seed_from_u64(43), 500, "hash")
+        );
+
+        let mut usernames: Vec<_> = users.iter().map(|u| &u.username).collect();
+        usernames.sort();
+        usernames.dedup();
+        assert_eq!(usernames.len(), 500);
+
+        for user in &users {
+            let (local, domain) = user.email.split_once('@').unwrap();
+            assert!(local
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._%+-".contains(c)));
+            assert!(domain.contains('.'));
+            assert!(["active", "suspended", "banned", "deleted"].contains(&user.account_status));
+            assert!(user.username.len() <= 100 && user.display_name.len() <= 100);
+            assert_eq!(user.id.len(), 36);
+            assert_eq!(&user.id[14..15], "4");
+        }
+    }
+
+    #[test]
+    fn test_set_files() {
+        let db = tempfile::tempdir().unwrap();
+        let seeds = db.path().join(SEEDS_DIR);
+        std::fs::create_dir_all(seeds.join("perf")).unwrap();
+        std::fs::write(seeds.join("dev.sql"), "SELECT 1;").unwrap();
+        std::fs::write(seeds.join("perf/02-orgs.sql"), "SELECT 2;").unwrap();
+        std::fs::write(seeds.join("perf/01-users.sql"), "SELECT 1;").unwrap();
+        std::fs::write(seeds.join("README.md"), "not a set").unwrap();
+
+        assert_eq!(available_sets(db.path()).unwrap(), ["dev", "perf"]);
+        assert_eq!(
+            set_files(db.path(), "dev").unwrap(),
+            [seeds.join("dev.sql")]
+        );
+        assert_eq!(
+            set_files(db.path(), "perf").unwrap(),
+            [
+                seeds.join("perf/01-users.sql"),
+                seeds.join("perf/02-orgs.sql")
+            ]
+        );
+        let err = set_files(db.path(), "../secrets").unwrap_err();
+        assert!(err.to_string().contains("Available sets in"));
+    }
+
+    #[test]
+    fn test_set_checksum_tracks_names_and_contents() {
+        let db = tempfile::tempdir().unwrap();
+        let seeds = db.path().join(SEEDS_DIR);
+        std::fs::create_dir_all(&seeds).unwrap();
+        std::fs::write(seeds.join("dev.sql"), "SELECT 1;").unwrap();
+        let files = set_files(db.path(), "dev").unwrap();
+        let original = set_checksum(db.path(), &files).unwrap();
+        assert_eq!(set_checksum(db.path(), &files).unwrap(), original);
+
+        std::fs::write(seeds.join("dev.sql"), "SELECT 2;").unwrap();
+        assert_ne!(set_checksum(db.path(), &files).unwrap(), original);
+
+        std::fs::write(seeds.join("demo.sql"), "SELECT 1;").unwrap();
+        let demo = set_files(db.path(), "demo").unwrap();
+        assert_ne!(set_checksum(db.path(), &demo).unwrap(), original);
+    }
+
+    #[test]
+    fn test_seed_state() {
+        let record = SeedRecord {
+            set: "dev".to_string(),
+            checksum: "abc".to_string(),
+            seeded_at: "2026-10-17 09:30:00".to_string(),
+        };
+        assert_eq!(SeedState::of(None, Some("abc")), SeedState::NotApplied);
+        assert_eq!(
+            SeedState::of(Some(&record), Some("abc")),
+            SeedState::Applied
+        );
+        assert_eq!(
+            SeedState::of(Some(&record), Some("def")),
+            SeedState::Changed
+        );
+        assert_eq!(SeedState::of(Some(&record), None), SeedState::Removed);
+    }
+
+    #[test]
+    fn test_repo_dev_set_passwords_verify() {
+        let db_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../db");
+        let sql = std::fs::read_to_string(&set_files(&db_path, "dev").unwrap()[0]).unwrap();
+        let hashes: Vec<&str> = sql
+            .split('\'')
+            .filter(|part| part.starts_with("$argon2id$"))
+            .collect();
+        assert!(!hashes.is_empty());
+        for hash in hashes {
+            let parsed = PasswordHash::new(hash).unwrap();
+            assert!(Argon2::default()
+                .verify_password(DEV_PASSWORD.as_bytes(), &parsed)
+                .is_ok());
+        }
+    }
+}
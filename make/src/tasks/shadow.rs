@@ -0,0 +1,220 @@
+//! Throwaway PostgreSQL containers ("shadow databases").
+//!
+//! `migrate-diff` needs two databases it can freely modify: one built from the
+//! declarative schema in `db/schema/` and one built by running the existing
+//! migrations. Each [`ShadowDatabase`] is a separate container on a random
+//! local port, with its data on tmpfs, so nothing touches the developer's
+//! `postgres` container or volume. Call [`ShadowDatabase::remove`] when done;
+//! it still runs after Ctrl-C.
+
+use crate::config::Config;
+use crate::probe;
+use crate::profile::Database;
+use crate::retry::RetryPolicy;
+use anyhow::{Context, Result};
+use std::net::TcpListener;
+use std::path::Path;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Same image as the `postgres` service in compose.yaml, so diffs match what runs locally
+pub const POSTGRES_IMAGE: &str = "docker.io/library/postgres:16-alpine";
+
+/// A PostgreSQL container that is removed after use
+#[derive(Debug)]
+pub struct ShadowDatabase {
+    /// Container name
+    pub container: String,
+    /// Connection details, on a random port on 127.0.0.1
+    pub db: Database,
+}
+
+impl ShadowDatabase {
+    /// Start a container named after `role` and wait until it accepts connections
+    pub async fn start(config: &Config, role: &str) -> Result<Self> {
+        let profile = config.profile()?;
+        let shadow = Self {
+            container: format!(
+                "{}-shadow-{}-{}",
+                profile.container_prefix,
+                role,
+                std::process::id()
+            ),
+            db: Database {
+                host: "127.0.0.1".to_string(),
+                port: free_port()?,
+                name: format!("cwrdd_{}", role),
+                user: profile.database()?.user,
+                password: "cwrdd_shadow".to_string(),
+            },
+        };
+
+        config
+            .task(format!("start {} database", role), "podman")
+            .args(shadow.run_args())
+            .timeout(Duration::from_secs(300))
+            .execute()
+            .await?;
+
+        if config.is_dry_run() {
+            crate::say!(
+                "📝 Would wait for {} database on port {}\n",
+                role,
+                shadow.db.port
+            );
+            return Ok(shadow);
+        }
+
+        let ready = RetryPolicy::fixed(30, Duration::from_secs(1))
+            .deadline(Duration::from_secs(60))
+            .quiet()
+            .run(&format!("{} database", role), || {
+                probe::postgres(&shadow.db)
+            })
+            .await;
+        if let Err(e) = ready {
+            shadow.remove(config).await?;
+            return Err(e);
+        }
+
+        crate::say!("✅ {} database is ready on port {}\n", role, shadow.db.port);
+        Ok(shadow)
+    }
+
+    fn run_args(&self) -> Vec<String> {
+        vec![
+            "run".to_string(),
+            "--detach".to_string(),
+            "--rm".to_string(),
+            "--name".to_string(),
+            self.container.clone(),
+            "--label".to_string(),
+            "cwrdd-make.shadow=true".to_string(),
+            "--publish".to_string(),
+            format!("127.0.0.1:{}:5432", self.db.port),
+            "--tmpfs".to_string(),
+            "/var/lib/postgresql/data".to_string(),
+            "--env".to_string(),
+            format!("POSTGRES_DB={}", self.db.name),
+            "--env".to_string(),
+            format!("POSTGRES_USER={}", self.db.user),
+            "--env".to_string(),
+            format!("POSTGRES_PASSWORD={}", self.db.password),
+            "--env".to_string(),
+            "POSTGRES_INITDB_ARGS=--encoding=UTF8 --locale=C".to_string(),
+            POSTGRES_IMAGE.to_string(),
+        ]
+    }
+
+    /// Run every `.sql` file in `dir` against the database, in file name order.
+    ///
+    /// Returns the number of files applied.
+    pub async fn apply_sql_dir(&self, config: &Config, dir: &Path) -> Result<usize> {
+        let mut files: Vec<_> = std::fs::read_dir(dir)
+            .context(format!("Failed to read {}", dir.display()))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "sql"))
+            .collect();
+        files.sort();
+
+        if config.is_dry_run() {
+            crate::say!(
+                "📝 Would apply {} schema file(s) to {}\n",
+                files.len(),
+                self.db.name
+            );
+            return Ok(files.len());
+        }
+
+        let client = self.db.connect().await?;
+        for file in &files {
+            let sql = std::fs::read_to_string(file)
+                .context(format!("Failed to read {}", file.display()))?;
+            client
+                .batch_execute(&sql)
+                .await
+                .context(format!("Failed to apply {}", file.display()))?;
+        }
+        Ok(files.len())
+    }
+
+    /// Remove the container, even if cwrdd-make is shutting down
+    pub async fn remove(&self, config: &Config) -> Result<()> {
+        config
+            .task(format!("remove {}", self.container), "podman")
+            .args(["rm", "--force", "--ignore", &self.container])
+            .timeout(Duration::from_secs(60))
+            .cancel_token(CancellationToken::new())
+            .capture()
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Start two shadow databases concurrently, removing one if the other fails to start
+pub async fn start_pair(
+    config: &Config,
+    first: &str,
+    second: &str,
+) -> Result<(ShadowDatabase, ShadowDatabase)> {
+    let (a, b) = tokio::join!(
+        ShadowDatabase::start(config, first),
+        ShadowDatabase::start(config, second)
+    );
+    match (a, b) {
+        (Ok(a), Ok(b)) => Ok((a, b)),
+        (Ok(started), Err(e)) | (Err(e), Ok(started)) => {
+            remove_all(config, &[started]).await;
+            Err(e)
+        }
+        (Err(e), Err(_)) => Err(e),
+    }
+}
+
+/// Remove every shadow database, reporting but not failing on errors
+pub async fn remove_all(config: &Config, shadows: &[ShadowDatabase]) {
+    for shadow in shadows {
+        if let Err(e) = shadow.remove(config).await {
+            eprintln!("⚠️  Failed to remove {}: {:#}", shadow.container, e);
+            eprintln!("   Remove it with: podman rm -f {}", shadow.container);
+        }
+    }
+}
+
+/// Ask the OS for a port nothing is listening on
+fn free_port() -> Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0").context("Failed to find a free port")?;
+    Ok(listener.local_addr()?.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_start_in_dry_run_records_podman_run() {
+        let plan = crate::plan::Plan::new();
+        let config = Config {
+            dry_run: Some(plan.clone()),
+            ..Config::default()
+        };
+
+        let shadow = ShadowDatabase::start(&config, "desired").await.unwrap();
+        shadow.remove(&config).await.unwrap();
+
+        let steps = plan.steps();
+        assert_eq!(steps[0].command, "podman");
+        assert!(steps[0]
+            .args
+            .contains(&format!("127.0.0.1:{}:5432", shadow.db.port)));
+        assert!(steps[0]
+            .args
+            .contains(&"POSTGRES_DB=cwrdd_desired".to_string()));
+        assert_eq!(
+            steps[1].args,
+            ["rm", "--force", "--ignore", shadow.container.as_str()]
+        );
+        assert!(shadow.container.starts_with("cwrdd-shadow-desired-"));
+    }
+}
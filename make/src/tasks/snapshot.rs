@@ -0,0 +1,825 @@
+//! Snapshots of the local database (`db snapshot`, `db restore`, `db snapshots`).
+//!
+//! A snapshot is a `pg_dump` custom-format archive of the selected profile's
+//! database, taken with the tools inside its `postgres` container so no local
+//! PostgreSQL client is needed. Snapshots live in `.cwrdd/snapshots/` in the
+//! repository as `<name>.dump`, next to `<name>.json` describing the migration
+//! state they were taken at. Restoring warns when the changelog has moved on
+//! since, because the restored database then needs `cwrdd-make migrate`.
+
+use crate::changelog::{self, Changelog};
+use crate::config::Config;
+use crate::output;
+use crate::profile::Database;
+use crate::say;
+use crate::task::Task;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// pg_dump and pg_restore can take a while on a large dev database
+const DUMP_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// What a snapshot was taken of, stored as `<name>.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    /// Profile whose database was dumped
+    pub profile: String,
+    pub database: String,
+    /// Applied changesets in the order they ran, in `filename::id::author` notation
+    pub applied: Vec<String>,
+    /// Checksum of each applied changeset's source, as the changelog stood at the time
+    pub checksums: BTreeMap<String, String>,
+}
+
+/// How the changelog differs from the state a snapshot was taken at
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SchemaDrift {
+    /// Changesets the snapshot hasn't had applied
+    pub added: Vec<String>,
+    /// Changesets applied in the snapshot whose source has been edited since
+    pub edited: Vec<String>,
+    /// Changesets applied in the snapshot that are no longer in the changelog
+    pub removed: Vec<String>,
+}
+
+impl SchemaDrift {
+    /// Compare a snapshot with the changelog as it is now
+    pub fn between(snapshot: &Snapshot, changelog: &Changelog) -> Self {
+        let current: BTreeMap<String, &str> = changelog
+            .changesets
+            .iter()
+            .map(|changeset| (changeset.key(), changeset.checksum.as_str()))
+            .collect();
+
+        let mut drift = SchemaDrift::default();
+        for changeset in &changelog.changesets {
+            let key = changeset.key();
+            if !snapshot.applied.contains(&key) {
+                drift.added.push(key);
+            } else if snapshot
+                .checksums
+                .get(&key)
+                .is_some_and(|checksum| *checksum != changeset.checksum)
+            {
+                drift.edited.push(key);
+            }
+        }
+        drift.removed = snapshot
+            .applied
+            .iter()
+            .filter(|key| !current.contains_key(*key))
+            .cloned()
+            .collect();
+        drift
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.edited.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Dump the selected profile's database to a new snapshot called `name`
+pub async fn create(config: &Config, name: &str) -> Result<()> {
+    validate_name(name)?;
+    say!("📸 Taking snapshot '{}'\n", name);
+
+    let profile = config.profile()?;
+    let db = profile.database()?;
+    let container = profile.container("postgres");
+    let (dump, metadata) = paths(config, name);
+    if metadata.exists() || dump.exists() {
+        bail!(
+            "Snapshot '{}' already exists. Remove it first with: cwrdd-make db snapshot rm {}",
+            name,
+            name
+        );
+    }
+    ensure_container_database(config, &db, &container).await?;
+
+    let applied = if config.is_dry_run() {
+        say!("📝 Would record the changesets applied to {}", db.name);
+        Vec::new()
+    } else {
+        let client = db.connect().await?;
+        changelog::applied(&client).await?
+    };
+    let checksums = match Changelog::load(&config.db_path(), changelog::CHANGELOG_FILE) {
+        Ok(changelog) => changelog
+            .changesets
+            .into_iter()
+            .map(|changeset| (changeset.key(), changeset.checksum))
+            .collect(),
+        Err(e) => {
+            eprintln!(
+                "⚠️  Couldn't read the changelog, so edits to changesets won't be detected: {:#}",
+                e
+            );
+            BTreeMap::new()
+        }
+    };
+
+    let dir = config.snapshots_path();
+    if !config.is_dry_run() {
+        std::fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+    }
+
+    let scratch = container_path(name);
+    let pg = |name: &str, tool: &str, args: &[&str]| {
+        pg_tool(config, name, &container, &db.user, tool, args)
+    };
+    let dumped = async {
+        pg(
+            "pg_dump",
+            "pg_dump",
+            &[
+                "--dbname", &db.name, "--format", "custom", "--file", &scratch,
+            ],
+        )
+        .timeout(DUMP_TIMEOUT)
+        .execute()
+        .await?;
+        config
+            .task("copy snapshot out", "podman")
+            .args([
+                "cp",
+                &format!("{}:{}", container, scratch),
+                &path_arg(&dump),
+            ])
+            .timeout(DUMP_TIMEOUT)
+            .execute()
+            .await
+    }
+    .await;
+    remove_scratch(config, &container, &scratch).await;
+    if let Err(e) = dumped {
+        let _ = std::fs::remove_file(&dump);
+        return Err(e);
+    }
+
+    let snapshot = Snapshot {
+        name: name.to_string(),
+        created_at: Utc::now(),
+        profile: config.profile_name.clone(),
+        database: db.name.clone(),
+        applied: applied.iter().map(|changeset| changeset.key()).collect(),
+        checksums: checksums
+            .into_iter()
+            .filter(|(key, _)| applied.iter().any(|changeset| changeset.key() == *key))
+            .collect(),
+    };
+    if config.is_dry_run() {
+        say!("📝 Would write {}", metadata.display());
+        return Ok(());
+    }
+    std::fs::write(&metadata, serde_json::to_string_pretty(&snapshot)? + "\n")
+        .context(format!("Failed to write {}", metadata.display()))?;
+
+    output::record("snapshot", path_arg(&dump));
+    output::record("applied_changesets", snapshot.applied.len());
+    say!(
+        "✅ Saved snapshot '{}' of {} ({} changeset(s) applied)",
+        name,
+        db.name,
+        snapshot.applied.len()
+    );
+    say!("   Restore it with: cwrdd-make db restore {}", name);
+    Ok(())
+}
+
+/// Replace the selected profile's database with the snapshot called `name`
+pub async fn restore(config: &Config, name: &str, yes: bool) -> Result<()> {
+    validate_name(name)?;
+    say!("⏮️  Restoring snapshot '{}'\n", name);
+
+    let profile = config.profile()?;
+    let db = profile.database()?;
+    let container = profile.container("postgres");
+    let snapshot = load(config, name)?;
+    let (dump, _) = paths(config, name);
+    if !dump.exists() {
+        bail!("Snapshot '{}' has no dump at {}", name, dump.display());
+    }
+
+    say!(
+        "Snapshot taken {} from {} ({} changeset(s) applied)",
+        snapshot.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        snapshot.database,
+        snapshot.applied.len()
+    );
+    if snapshot.profile != config.profile_name {
+        say!("⚠️  Snapshot was taken with profile '{}'", snapshot.profile);
+    }
+    say!();
+
+    let changelog = Changelog::load(&config.db_path(), changelog::CHANGELOG_FILE)?;
+    let drift = SchemaDrift::between(&snapshot, &changelog);
+    print_drift(&drift);
+    output::record("changesets_added_since", drift.added.clone());
+    output::record("changesets_edited_since", drift.edited.clone());
+    output::record("changesets_removed_since", drift.removed.clone());
+
+    ensure_container_database(config, &db, &container).await?;
+
+    if !yes && !config.is_dry_run() {
+        let question = format!("⚠️  Replace {} with snapshot '{}'?", db.name, name);
+        if !output::confirm(&question).await? {
+            say!("❎ Restore cancelled, nothing was changed");
+            output::record("restored", false);
+            return Ok(());
+        }
+    }
+
+    // Restore into a new database and only swap it in once pg_restore succeeds,
+    // so a broken dump leaves the current database alone. The swap renames the
+    // current database aside in the same transaction, so there is never a
+    // moment when neither exists.
+    let scratch = container_path(name);
+    let staging = format!("{}_restore", db.name);
+    let previous = format!("{}_previous", db.name);
+    let pg = |name: &str, tool: &str, args: &[&str]| {
+        pg_tool(config, name, &container, &db.user, tool, args)
+    };
+    let restored = async {
+        config
+            .task("copy snapshot in", "podman")
+            .args([
+                "cp",
+                &path_arg(&dump),
+                &format!("{}:{}", container, scratch),
+            ])
+            .timeout(DUMP_TIMEOUT)
+            .execute()
+            .await?;
+        for leftover in [&staging, &previous] {
+            pg(
+                "drop leftover database",
+                "dropdb",
+                &["--if-exists", "--force", leftover],
+            )
+            .execute()
+            .await?;
+        }
+        pg(
+            "create staging database",
+            "createdb",
+            &["--owner", &db.user, &staging],
+        )
+        .execute()
+        .await?;
+        pg(
+            "pg_restore",
+            "pg_restore",
+            &[
+                "--dbname",
+                &staging,
+                "--no-owner",
+                "--exit-on-error",
+                &scratch,
+            ],
+        )
+        .timeout(DUMP_TIMEOUT)
+        .execute()
+        .await?;
+        pg(
+            "swap in restored database",
+            "psql",
+            &[
+                "--dbname",
+                "postgres",
+                "--command",
+                &swap_sql(&db.name, &previous, &staging),
+            ],
+        )
+        .execute()
+        .await
+    }
+    .await;
+    remove_scratch(config, &container, &scratch).await;
+    if let Err(e) = restored {
+        let _ = pg(
+            "drop staging database",
+            "dropdb",
+            &["--if-exists", "--force", &staging],
+        )
+        .cancel_token(CancellationToken::new())
+        .capture()
+        .await;
+        return Err(e);
+    }
+
+    let dropped = pg(
+        "drop previous database",
+        "dropdb",
+        &["--if-exists", "--force", &previous],
+    )
+    .capture()
+    .await;
+    if !dropped.is_ok_and(|output| output.success()) {
+        say!(
+            "⚠️  Could not drop {}; the restore itself succeeded",
+            previous
+        );
+    }
+
+    output::record("restored", true);
+    say!("✅ Restored {} from snapshot '{}'", db.name, name);
+    if !drift.added.is_empty() {
+        say!("   Bring it up to date with: cwrdd-make migrate");
+    }
+    Ok(())
+}
+
+/// List every snapshot, oldest first
+pub async fn list(config: &Config) -> Result<()> {
+    say!("📚 Database snapshots\n");
+
+    let snapshots = all(config)?;
+    if snapshots.is_empty() {
+        say!("No snapshots yet. Take one with: cwrdd-make db snapshot <name>");
+    }
+
+    let name_width = snapshots.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    let mut records = Vec::new();
+    for snapshot in &snapshots {
+        let (dump, _) = paths(config, &snapshot.name);
+        let size = std::fs::metadata(&dump).map(|m| m.len()).ok();
+        say!(
+            "  {:w$}  {}  {:>9}  {} ({} changeset(s))",
+            snapshot.name,
+            snapshot.created_at.format("%Y-%m-%d %H:%M"),
+            size.map(format_size)
+                .unwrap_or_else(|| "missing".to_string()),
+            snapshot.database,
+            snapshot.applied.len(),
+            w = name_width
+        );
+        records.push(serde_json::json!({
+            "name": snapshot.name,
+            "created_at": snapshot.created_at.to_rfc3339(),
+            "profile": snapshot.profile,
+            "database": snapshot.database,
+            "applied_changesets": snapshot.applied.len(),
+            "size_bytes": size,
+        }));
+    }
+
+    output::record("snapshots", records);
+    Ok(())
+}
+
+/// Delete the snapshot called `name`
+pub async fn remove(config: &Config, name: &str) -> Result<()> {
+    validate_name(name)?;
+    let (dump, metadata) = paths(config, name);
+    if !dump.exists() && !metadata.exists() {
+        bail!("{}", unknown_snapshot(config, name));
+    }
+
+    if config.is_dry_run() {
+        say!(
+            "📝 Would delete {} and {}",
+            dump.display(),
+            metadata.display()
+        );
+        return Ok(());
+    }
+    for path in [&dump, &metadata] {
+        if path.exists() {
+            std::fs::remove_file(path).context(format!("Failed to delete {}", path.display()))?;
+        }
+    }
+
+    output::record("removed", name);
+    say!("🗑️  Removed snapshot '{}'", name);
+    Ok(())
+}
+
+/// Every snapshot with readable metadata, oldest first
+fn all(config: &Config) -> Result<Vec<Snapshot>> {
+    let dir = config.snapshots_path();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(&dir).context(format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            match read_metadata(&path) {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => eprintln!("⚠️  Skipping {}: {:#}", path.display(), e),
+            }
+        }
+    }
+    snapshots.sort_by(|a, b| (a.created_at, &a.name).cmp(&(b.created_at, &b.name)));
+    Ok(snapshots)
+}
+
+fn load(config: &Config, name: &str) -> Result<Snapshot> {
+    let (_, metadata) = paths(config, name);
+    if !metadata.exists() {
+        bail!("{}", unknown_snapshot(config, name));
+    }
+    read_metadata(&metadata)
+}
+
+fn read_metadata(path: &Path) -> Result<Snapshot> {
+    let content =
+        std::fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content)
+        .context(format!("Invalid snapshot metadata in {}", path.display()))
+}
+
+fn unknown_snapshot(config: &Config, name: &str) -> String {
+    let names: Vec<String> = all(config)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|snapshot| snapshot.name)
+        .collect();
+    if names.is_empty() {
+        format!("Unknown snapshot '{}'. There are no snapshots yet", name)
+    } else {
+        format!(
+            "Unknown snapshot '{}'. Available snapshots: {}",
+            name,
+            names.join(", ")
+        )
+    }
+}
+
+fn print_drift(drift: &SchemaDrift) {
+    if drift.is_empty() {
+        say!("✅ The changelog hasn't changed since this snapshot\n");
+        return;
+    }
+
+    say!("⚠️  The schema has moved on since this snapshot was taken:");
+    for key in &drift.added {
+        say!("     added:   {}", key);
+    }
+    for key in &drift.edited {
+        say!("     edited:  {}", key);
+    }
+    for key in &drift.removed {
+        say!("     removed: {}", key);
+    }
+    say!();
+}
+
+/// Fail unless `db` is the database in the running `container`. The PostgreSQL
+/// tools run inside the container, while migrating, seeding and reading the
+/// applied changesets connect to `database_url`, so both must reach the same
+/// server: a local host, on the port the container publishes.
+pub(crate) async fn ensure_container_database(
+    config: &Config,
+    db: &Database,
+    container: &str,
+) -> Result<()> {
+    if !db.is_local() {
+        bail!(
+            "database_url points at {}:{}, not the local {} container, \
+             so it can't be dumped, restored or reset from here",
+            db.host,
+            db.port,
+            container
+        );
+    }
+    ensure_running(config, container).await?;
+
+    let output = config
+        .task(format!("ports of {}", container), "podman")
+        .args(["port", container, "5432/tcp"])
+        .capture()
+        .await?;
+    if config.is_dry_run() {
+        return Ok(());
+    }
+    let published: Vec<&str> = output
+        .stdout
+        .lines()
+        .filter_map(|line| line.trim().rsplit_once(':'))
+        .map(|(_, port)| port)
+        .collect();
+    if !published.contains(&db.port.to_string().as_str()) {
+        bail!(
+            "database_url uses port {}, but {} publishes PostgreSQL on {}. \
+             Recreate the container with: cwrdd-make up --recreate",
+            db.port,
+            container,
+            if published.is_empty() {
+                "no port".to_string()
+            } else {
+                published.join(", ")
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Fail unless `container` is running, since the dump tools run inside it
+async fn ensure_running(config: &Config, container: &str) -> Result<()> {
+    let output = config
+        .task(format!("inspect {}", container), "podman")
+        .args([
+            "container",
+            "inspect",
+            "--format",
+            "{{.State.Running}}",
+            container,
+        ])
+        .capture()
+        .await?;
+    if !config.is_dry_run() && output.stdout.trim() != "true" {
+        bail!(
+            "Container {} isn't running. Start it with: cwrdd-make up",
+            container
+        );
+    }
+    Ok(())
+}
+
+/// Run a PostgreSQL client tool inside `container` as `user`
+pub(crate) fn pg_tool(
+    config: &Config,
+    name: &str,
+    container: &str,
+    user: &str,
+    tool: &str,
+    args: &[&str],
+) -> Task {
+    let mut all_args = vec!["exec", container, tool, "--username", user];
+    all_args.extend(args);
+    config.task(name, "podman").args(all_args)
+}
+
+/// SQL that renames `database` (if it exists) to `previous` and `staging` to
+/// `database` in one transaction, disconnecting everyone from `database` first
+fn swap_sql(database: &str, previous: &str, staging: &str) -> String {
+    format!(
+        "SELECT pg_terminate_backend(pid) FROM pg_stat_activity \
+         WHERE datname = {db} AND pid <> pg_backend_pid(); \
+         DO $$ BEGIN \
+         IF EXISTS (SELECT 1 FROM pg_database WHERE datname = {db}) THEN \
+         EXECUTE format('ALTER DATABASE %I RENAME TO %I', {db}, {previous}); \
+         END IF; END $$; \
+         ALTER DATABASE {staging} RENAME TO {database};",
+        db = quote_literal(database),
+        previous = quote_literal(previous),
+        staging = quote_identifier(staging),
+        database = quote_identifier(database),
+    )
+}
+
+/// Quote a string literal for use in SQL
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quote an identifier for use in SQL
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Delete the copy of a dump inside the container, warning on failure
+async fn remove_scratch(config: &Config, container: &str, scratch: &str) {
+    let removed = config
+        .task("remove scratch dump", "podman")
+        .args(["exec", container, "rm", "-f", scratch])
+        .capture()
+        .await;
+    if let Err(e) = removed {
+        eprintln!(
+            "⚠️  Failed to remove {} from {}: {:#}",
+            scratch, container, e
+        );
+    }
+}
+
+/// `<name>.dump` and `<name>.json`
+fn paths(config: &Config, name: &str) -> (PathBuf, PathBuf) {
+    let dir = config.snapshots_path();
+    (
+        dir.join(format!("{}.dump", name)),
+        dir.join(format!("{}.json", name)),
+    )
+}
+
+/// Where a dump is staged inside the container
+fn container_path(name: &str) -> String {
+    format!("/tmp/cwrdd-snapshot-{}.dump", name)
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Snapshot names become file names, so keep them simple
+fn validate_name(name: &str) -> Result<()> {
+    let valid = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric());
+    if !valid {
+        bail!(
+            "Invalid snapshot name '{}'. Use letters, digits, '-', '_' and '.', \
+             starting with a letter or digit",
+            name
+        );
+    }
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1} GiB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1} MiB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KiB", b as f64 / (1u64 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changelog::ChangeSet;
+
+    fn changeset(id: &str, checksum: &str) -> ChangeSet {
+        ChangeSet {
+            id: id.to_string(),
+            author: "andy".to_string(),
+            filename: "migrations/a.xml".to_string(),
+            checksum: checksum.to_string(),
+            source: String::new(),
+        }
+    }
+
+    fn snapshot(name: &str) -> Snapshot {
+        Snapshot {
+            name: name.to_string(),
+            created_at: Utc::now(),
+            profile: "dev".to_string(),
+            database: "cwrdd_dev".to_string(),
+            applied: vec![
+                "migrations/a.xml::1::andy".to_string(),
+                "migrations/a.xml::2::andy".to_string(),
+            ],
+            checksums: BTreeMap::from([
+                ("migrations/a.xml::1::andy".to_string(), "one".to_string()),
+                ("migrations/a.xml::2::andy".to_string(), "two".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_schema_drift() {
+        let changelog = Changelog {
+            changesets: vec![
+                changeset("1", "one"),
+                changeset("2", "edited"),
+                changeset("3", "three"),
+            ],
+            ..Changelog::default()
+        };
+        let mut snapshot = snapshot("before");
+        snapshot
+            .applied
+            .push("migrations/gone.xml::1::andy".to_string());
+
+        let drift = SchemaDrift::between(&snapshot, &changelog);
+        assert_eq!(drift.added, ["migrations/a.xml::3::andy"]);
+        assert_eq!(drift.edited, ["migrations/a.xml::2::andy"]);
+        assert_eq!(drift.removed, ["migrations/gone.xml::1::andy"]);
+
+        let changelog = Changelog {
+            changesets: vec![changeset("1", "one"), changeset("2", "two")],
+            ..Changelog::default()
+        };
+        assert!(SchemaDrift::between(&self::snapshot("before"), &changelog).is_empty());
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("before-orgs_v1.2").is_ok());
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_remove() {
+        let repo = tempfile::tempdir().unwrap();
+        let config = Config {
+            repo_path: repo.path().to_path_buf(),
+            ..Config::default()
+        };
+        let dir = config.snapshots_path();
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["first", "second"] {
+            let json = serde_json::to_string(&snapshot(name)).unwrap();
+            std::fs::write(dir.join(format!("{}.json", name)), json).unwrap();
+            std::fs::write(dir.join(format!("{}.dump", name)), "PGDMP").unwrap();
+        }
+
+        let names: Vec<_> = all(&config).unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["first", "second"]);
+
+        remove(&config, "first").await.unwrap();
+        assert!(!dir.join("first.dump").exists());
+        let err = load(&config, "first").unwrap_err();
+        assert!(err.to_string().contains("Available snapshots: second"));
+    }
+
+    #[tokio::test]
+    async fn test_create_in_dry_run_dumps_inside_the_container() {
+        let repo = tempfile::tempdir().unwrap();
+        let plan = crate::plan::Plan::new();
+        let config = Config {
+            repo_path: repo.path().to_path_buf(),
+            dry_run: Some(plan.clone()),
+            ..Config::default()
+        };
+
+        create(&config, "before").await.unwrap();
+
+        let steps = plan.steps();
+        assert_eq!(steps[1].args, ["port", "cwrdd-postgres", "5432/tcp"]);
+        assert_eq!(steps[2].args[..3], ["exec", "cwrdd-postgres", "pg_dump"]);
+        assert_eq!(
+            steps[3].args,
+            [
+                "cp".to_string(),
+                "cwrdd-postgres:/tmp/cwrdd-snapshot-before.dump".to_string(),
+                path_arg(&config.snapshots_path().join("before.dump")),
+            ]
+        );
+        assert!(!config.snapshots_path().exists());
+    }
+
+    #[tokio::test]
+    async fn test_restore_never_drops_the_database_before_swapping() {
+        let repo = tempfile::tempdir().unwrap();
+        let changelog = repo.path().join("db").join(changelog::CHANGELOG_FILE);
+        std::fs::create_dir_all(changelog.parent().unwrap()).unwrap();
+        std::fs::write(&changelog, "<databaseChangeLog>\n</databaseChangeLog>\n").unwrap();
+        let plan = crate::plan::Plan::new();
+        let config = Config {
+            repo_path: repo.path().to_path_buf(),
+            dry_run: Some(plan.clone()),
+            ..Config::default()
+        };
+        let snapshot = Snapshot {
+            name: "before".to_string(),
+            created_at: Utc::now(),
+            profile: "dev".to_string(),
+            database: "cwrdd_dev".to_string(),
+            applied: Vec::new(),
+            checksums: BTreeMap::new(),
+        };
+        let dir = config.snapshots_path();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("before.json"),
+            serde_json::to_string(&snapshot).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(dir.join("before.dump"), "PGDMP").unwrap();
+
+        restore(&config, "before", true).await.unwrap();
+
+        let steps = plan.steps();
+        let names: Vec<&str> = steps.iter().map(|s| s.name.as_str()).collect();
+        let dropped: Vec<&str> = steps
+            .iter()
+            .filter(|s| s.args.contains(&"dropdb".to_string()))
+            .map(|s| s.args.last().unwrap().as_str())
+            .collect();
+        assert_eq!(
+            dropped,
+            [
+                "cwrdd_dev_restore",
+                "cwrdd_dev_previous",
+                "cwrdd_dev_previous"
+            ]
+        );
+        let swap = names
+            .iter()
+            .position(|n| *n == "swap in restored database")
+            .unwrap();
+        assert_eq!(names[swap + 1], "remove scratch dump");
+        assert_eq!(names.last(), Some(&"drop previous database"));
+        let sql = steps[swap].args.last().unwrap();
+        assert!(
+            sql.contains("RENAME TO %I', 'cwrdd_dev', 'cwrdd_dev_previous'"),
+            "{}",
+            sql
+        );
+        assert!(sql.ends_with("ALTER DATABASE \"cwrdd_dev_restore\" RENAME TO \"cwrdd_dev\";"));
+    }
+}
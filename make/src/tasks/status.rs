@@ -0,0 +1,399 @@
+use crate::config::Config;
+use crate::output;
+use crate::say;
+use crate::shutdown;
+use crate::task::command_exists;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How often `--watch` refreshes the table
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The state of one compose service's container
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceStatus {
+    pub service: String,
+    pub container: String,
+    /// Container state, e.g. `running`, `exited`, or `missing` if there is no container
+    pub state: String,
+    /// Healthcheck result, if the service has a healthcheck
+    pub health: Option<String>,
+    /// Seconds since the container started, while it is running
+    pub uptime_secs: Option<i64>,
+    pub ports: Vec<String>,
+    pub image_digest: Option<String>,
+    pub restarts: u64,
+}
+
+impl ServiceStatus {
+    /// Whether `up` would consider this service healthy
+    pub fn is_ok(&self) -> bool {
+        self.state == "running" && self.health.as_deref().is_none_or(|h| h == "healthy")
+    }
+}
+
+/// The subset of `podman inspect` output that status reports on
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Inspect {
+    name: String,
+    state: InspectState,
+    #[serde(default)]
+    image_digest: Option<String>,
+    #[serde(default)]
+    restart_count: u64,
+    #[serde(default)]
+    network_settings: Option<InspectNetwork>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectState {
+    status: String,
+    #[serde(default)]
+    started_at: Option<String>,
+    // Podman 4.x and later call this Health; older versions used Healthcheck
+    #[serde(default, alias = "Healthcheck")]
+    health: Option<InspectHealth>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectHealth {
+    status: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectNetwork {
+    #[serde(default)]
+    ports: Option<BTreeMap<String, Option<Vec<InspectPort>>>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectPort {
+    #[serde(default)]
+    host_ip: String,
+    host_port: String,
+}
+
+/// Show the state of every compose service, optionally refreshing until interrupted
+pub async fn run(config: &Config, watch: bool) -> Result<()> {
+    if !command_exists("podman") || !command_exists("podman-compose") {
+        bail!(
+            "podman and podman-compose are required.\n\
+             Run: cwrdd-make get-tools"
+        );
+    }
+
+    if !watch {
+        let statuses = collect(config).await?;
+        print_table(config, &statuses);
+        output::record("services", serde_json::to_value(&statuses)?);
+        return Ok(());
+    }
+
+    let mut last = None;
+    loop {
+        let statuses = match collect(config).await {
+            Ok(statuses) => statuses,
+            // Ctrl-C cancels the podman tasks too; that's a normal way to stop
+            Err(_) if shutdown::is_cancelled() => {
+                if let Some(statuses) = last {
+                    output::record("services", statuses);
+                }
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        if !output::is_json() {
+            // Clear the screen and move the cursor home
+            print!("\x1B[2J\x1B[H");
+        }
+        print_table(config, &statuses);
+        let statuses = serde_json::to_value(&statuses)?;
+        output::update("services", statuses.clone());
+        say!("Refreshing every {}s, press Ctrl-C to stop", WATCH_INTERVAL.as_secs());
+
+        tokio::select! {
+            _ = tokio::time::sleep(WATCH_INTERVAL) => {}
+            _ = shutdown::token().cancelled() => {
+                output::record("services", statuses);
+                return Ok(());
+            }
+        }
+        last = Some(statuses);
+    }
+}
+
+/// Inspect the container of every service in compose.yaml
+async fn collect(config: &Config) -> Result<Vec<ServiceStatus>> {
+    let profile = config.profile()?;
+    let services = crate::tasks::compose::services(config).await?;
+
+    let existing = config.task("list containers", "podman")
+        .args(["ps", "--all", "--format", "{{.Names}}"])
+        .timeout(Duration::from_secs(30))
+        .capture()
+        .await?;
+    let existing: Vec<&str> = existing.stdout.lines().map(str::trim).collect();
+
+    let containers: Vec<String> = services
+        .iter()
+        .map(|service| profile.container(service))
+        .filter(|container| existing.contains(&container.as_str()))
+        .collect();
+
+    let inspected = if containers.is_empty() {
+        Vec::new()
+    } else {
+        let mut args = vec!["container".to_string(), "inspect".to_string()];
+        args.extend(containers.iter().cloned());
+        let output = config.task("inspect containers", "podman")
+            .args(args)
+            .timeout(Duration::from_secs(30))
+            .capture()
+            .await?;
+        if config.is_dry_run() {
+            Vec::new()
+        } else {
+            parse_inspect(&output.stdout)?
+        }
+    };
+
+    Ok(services
+        .iter()
+        .map(|service| {
+            let container = profile.container(service);
+            let inspect = inspected
+                .iter()
+                .find(|i| i.name.trim_start_matches('/') == container);
+            service_status(service, &container, inspect, Utc::now())
+        })
+        .collect())
+}
+
+/// Parse the JSON array printed by `podman container inspect`
+fn parse_inspect(json: &str) -> Result<Vec<Inspect>> {
+    serde_json::from_str(json).context("Failed to parse podman inspect output")
+}
+
+/// Summarise one service from its inspected container, if there is one
+fn service_status(
+    service: &str,
+    container: &str,
+    inspect: Option<&Inspect>,
+    now: DateTime<Utc>,
+) -> ServiceStatus {
+    let Some(inspect) = inspect else {
+        return ServiceStatus {
+            service: service.to_string(),
+            container: container.to_string(),
+            state: "missing".to_string(),
+            health: None,
+            uptime_secs: None,
+            ports: Vec::new(),
+            image_digest: None,
+            restarts: 0,
+        };
+    };
+
+    let state = inspect.state.status.to_lowercase();
+    let uptime_secs = inspect
+        .state
+        .started_at
+        .as_deref()
+        .filter(|_| state == "running")
+        .and_then(|started| DateTime::parse_from_rfc3339(started).ok())
+        .map(|started| (now - started.with_timezone(&Utc)).num_seconds().max(0));
+
+    let mut ports = Vec::new();
+    let published = inspect
+        .network_settings
+        .as_ref()
+        .and_then(|n| n.ports.as_ref());
+    for (container_port, bindings) in published.into_iter().flatten() {
+        for binding in bindings.iter().flatten() {
+            let host = match binding.host_ip.as_str() {
+                "" | "0.0.0.0" => String::new(),
+                ip => format!("{}:", ip),
+            };
+            ports.push(format!("{}{}->{}", host, binding.host_port, container_port));
+        }
+    }
+
+    ServiceStatus {
+        service: service.to_string(),
+        container: container.to_string(),
+        state,
+        health: inspect
+            .state
+            .health
+            .as_ref()
+            .map(|h| h.status.clone())
+            .filter(|h| !h.is_empty()),
+        uptime_secs,
+        ports,
+        image_digest: inspect.image_digest.clone().filter(|d| !d.is_empty()),
+        restarts: inspect.restart_count,
+    }
+}
+
+/// Print one row per service, then flag anything that isn't running
+fn print_table(config: &Config, statuses: &[ServiceStatus]) {
+    say!("📋 Service status (profile: {})\n", config.profile_name);
+
+    let rows: Vec<(&str, [String; 6])> = statuses
+        .iter()
+        .map(|s| {
+            let mut state = s.state.clone();
+            if s.restarts > 0 {
+                state = format!("{} ({} restarts)", state, s.restarts);
+            }
+            let icon = if s.is_ok() { "✅" } else { "❌" };
+            let cells = [
+                s.service.clone(),
+                state,
+                s.health.clone().unwrap_or_else(|| "-".to_string()),
+                s.uptime_secs.map(format_uptime).unwrap_or_else(|| "-".to_string()),
+                if s.ports.is_empty() { "-".to_string() } else { s.ports.join(", ") },
+                s.image_digest.as_deref().map(short_digest).unwrap_or("-").to_string(),
+            ];
+            (icon, cells)
+        })
+        .collect();
+
+    let header = ["SERVICE", "STATE", "HEALTH", "UPTIME", "PORTS", "IMAGE"].map(String::from);
+    let mut widths = header.clone().map(|h| h.len());
+    for (_, cells) in &rows {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    // The status icons are two cells wide, so the header is indented to match
+    for (icon, cells) in std::iter::once(&("  ", header)).chain(&rows) {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        say!("  {} {}", icon, cells.join("  ").trim_end());
+    }
+    say!();
+
+    let down: Vec<&str> = statuses
+        .iter()
+        .filter(|s| s.state != "running")
+        .map(|s| s.service.as_str())
+        .collect();
+    if !down.is_empty() {
+        // `up --only` and `--without` leave some services down on purpose, so
+        // this only says which aren't running, not that they should be
+        say!(
+            "⚠️  {} service(s) in compose.yaml are not running: {}",
+            down.len(),
+            down.join(", ")
+        );
+        say!("   Start them with: cwrdd-make up --only {}\n", down.join(","));
+    }
+}
+
+/// Format an uptime in seconds, e.g. `2h 5m`
+fn format_uptime(secs: i64) -> String {
+    let (days, hours, mins) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    match (days, hours, mins) {
+        (0, 0, 0) => format!("{}s", secs),
+        (0, 0, m) => format!("{}m {}s", m, secs % 60),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
+}
+
+/// The first 12 hex digits of an image digest
+fn short_digest(digest: &str) -> &str {
+    let hex = digest.strip_prefix("sha256:").unwrap_or(digest);
+    &hex[..hex.len().min(12)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSPECT: &str = r#"[
+        {
+            "Name": "cwrdd-postgres",
+            "State": {
+                "Status": "running",
+                "StartedAt": "2026-10-17T10:00:00Z",
+                "Health": { "Status": "healthy", "FailingStreak": 0 }
+            },
+            "ImageDigest": "sha256:0123456789abcdef0123456789abcdef",
+            "RestartCount": 0,
+            "NetworkSettings": {
+                "Ports": {
+                    "5432/tcp": [{ "HostIp": "", "HostPort": "5432" }],
+                    "9187/tcp": null
+                }
+            }
+        },
+        {
+            "Name": "cwrdd-redis",
+            "State": { "Status": "exited", "StartedAt": "2026-10-17T09:00:00+01:00" },
+            "RestartCount": 3
+        }
+    ]"#;
+
+    #[test]
+    fn test_service_status_from_inspect() {
+        let inspected = parse_inspect(INSPECT).unwrap();
+        let now = DateTime::parse_from_rfc3339("2026-10-17T12:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let postgres = service_status("postgres", "cwrdd-postgres", Some(&inspected[0]), now);
+        assert_eq!(postgres.state, "running");
+        assert_eq!(postgres.health.as_deref(), Some("healthy"));
+        assert_eq!(postgres.uptime_secs, Some(2 * 3600 + 30 * 60));
+        assert_eq!(postgres.ports, ["5432->5432/tcp"]);
+        assert!(postgres.is_ok());
+
+        let redis = service_status("redis", "cwrdd-redis", Some(&inspected[1]), now);
+        assert_eq!(redis.state, "exited");
+        assert_eq!(redis.uptime_secs, None);
+        assert_eq!(redis.restarts, 3);
+        assert!(!redis.is_ok());
+
+        let app = service_status("app", "cwrdd-app", None, now);
+        assert_eq!(app.state, "missing");
+        assert!(!app.is_ok());
+    }
+
+    #[test]
+    fn test_unhealthy_is_not_ok() {
+        let mut inspected = parse_inspect(INSPECT).unwrap();
+        inspected[0].state.health = Some(InspectHealth {
+            status: "unhealthy".to_string(),
+        });
+        let status = service_status("postgres", "cwrdd-postgres", Some(&inspected[0]), Utc::now());
+        assert!(!status.is_ok());
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(42), "42s");
+        assert_eq!(format_uptime(5 * 60 + 3), "5m 3s");
+        assert_eq!(format_uptime(2 * 3600 + 5 * 60), "2h 5m");
+        assert_eq!(format_uptime(3 * 86400 + 4 * 3600), "3d 4h");
+    }
+
+    #[test]
+    fn test_short_digest() {
+        assert_eq!(short_digest("sha256:0123456789abcdef"), "0123456789ab");
+        assert_eq!(short_digest("abc"), "abc");
+    }
+}
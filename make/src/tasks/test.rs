@@ -1,10 +1,11 @@
 use crate::config::Config;
-use crate::task::{command_exists, run_tasks, verify_directory, Task};
+use crate::say;
+use crate::task::{command_exists, run_tasks, verify_directory};
 use anyhow::{bail, Result};
 
 /// Run the test task
 pub async fn run(config: &Config) -> Result<()> {
-    println!("🧪 Running tests for cwrdd application\n");
+    say!("🧪 Running tests for cwrdd application\n");
 
     // Verify cargo exists
     if !command_exists("cargo") {
@@ -15,27 +16,27 @@ pub async fn run(config: &Config) -> Result<()> {
     let use_nextest = command_exists("cargo-nextest");
 
     if !use_nextest {
-        println!("⚠️  cargo-nextest not found. Using cargo test instead.");
-        println!("   Install nextest for better performance: cargo install cargo-nextest --locked\n");
+        say!("⚠️  cargo-nextest not found. Using cargo test instead.");
+        say!("   Install nextest for better performance: cargo install cargo-nextest --locked\n");
     }
 
     // Verify app directory exists
     let app_path = config.app_path();
     verify_directory(&app_path)?;
 
-    println!("Repository path: {}", config.repo_path.display());
-    println!("App path: {}\n", app_path.display());
+    say!("Repository path: {}", config.repo_path.display());
+    say!("App path: {}\n", app_path.display());
 
     // Create test tasks
     let tasks = if use_nextest {
         vec![
-            Task::new("cargo nextest run", "cargo")
+            config.task("cargo nextest run", "cargo")
                 .args(["nextest", "run", "--workspace"])
                 .working_dir(app_path.to_string_lossy().to_string()),
         ]
     } else {
         vec![
-            Task::new("cargo test", "cargo")
+            config.task("cargo test", "cargo")
                 .args(["test", "--workspace"])
                 .working_dir(app_path.to_string_lossy().to_string()),
         ]
@@ -44,7 +45,7 @@ pub async fn run(config: &Config) -> Result<()> {
     // Run tasks
     run_tasks(tasks).await?;
 
-    println!("🎉 All tests passed!");
+    say!("🎉 All tests passed!");
     Ok(())
 }
 
@@ -62,6 +63,7 @@ mod tests {
     async fn test_with_nonexistent_directory() {
         let config = Config {
             repo_path: PathBuf::from("/nonexistent-directory-12345"),
+            ..Config::default()
         };
 
         let result = run(&config).await;
@@ -41,6 +41,19 @@ fn test_cwrdd_make_general_help() {
     assert!(stdout.contains("test"));
 }
 
+#[test]
+fn test_cwrdd_make_dry_run_flag() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "--help"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--dry-run"));
+}
+
 #[test]
 fn test_cwrdd_make_migrate_commands() {
     let output = Command::new("cargo")
@@ -57,3 +70,47 @@ fn test_cwrdd_make_migrate_commands() {
     assert!(stdout.contains("rollback"));
     assert!(stdout.contains("seed"));
 }
+
+#[test]
+fn test_cwrdd_make_json_output() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "--output", "json", "--dry-run", "doc"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let events: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every stdout line is a JSON event"))
+        .collect();
+
+    let planned: Vec<_> = events.iter().filter(|e| e["event"] == "planned").collect();
+    assert_eq!(planned.len(), 2);
+    assert!(planned.iter().all(|e| e["command"] == "cargo"));
+
+    let result = events.last().expect("at least one event");
+    assert_eq!(result["event"], "result");
+    assert_eq!(result["command"], "doc");
+    assert_eq!(result["success"], true);
+}
+
+#[test]
+fn test_cwrdd_make_config_show() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "config", "show"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("CWRDD_REPO_PATH", "/tmp/cwrdd-from-env")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let repo_line = stdout
+        .lines()
+        .find(|line| line.trim_start().starts_with("repo_path"))
+        .expect("repo_path is listed");
+    assert!(repo_line.contains("/tmp/cwrdd-from-env"));
+    assert!(repo_line.contains("(environment variable CWRDD_REPO_PATH)"));
+}